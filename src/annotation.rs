@@ -0,0 +1,135 @@
+// 标注数据：与界面无关的矩形、椭圆、箭头、画笔路径、打码和文字，用于把标注绘制到导出的图像上
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Annotation {
+    Rectangle {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: [u8; 4],
+        stroke_width: f32,
+    },
+    Ellipse {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        color: [u8; 4],
+        stroke_width: f32,
+    },
+    Arrow {
+        start_x: f32,
+        start_y: f32,
+        end_x: f32,
+        end_y: f32,
+        color: [u8; 4],
+        stroke_width: f32,
+    },
+    Pen {
+        points: Vec<(f32, f32)>,
+        color: [u8; 4],
+        stroke_width: f32,
+    },
+    // 打码：区域内按 block_size 大小的方块取平均颜色
+    Pixelate {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        block_size: f32,
+    },
+    // (x, y) 是文字左上角
+    Text {
+        x: f32,
+        y: f32,
+        text: String,
+        color: [u8; 4],
+        font_size: f32,
+    },
+}
+
+impl Annotation {
+    // 平移后的标注，用于在屏幕坐标和图像坐标之间换算
+    pub fn translated(&self, dx: f32, dy: f32) -> Annotation {
+        let mut annotation = self.clone();
+        match &mut annotation {
+            Annotation::Rectangle { x, y, .. }
+            | Annotation::Ellipse { x, y, .. }
+            | Annotation::Pixelate { x, y, .. }
+            | Annotation::Text { x, y, .. } => {
+                *x += dx;
+                *y += dy;
+            }
+            Annotation::Arrow {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                ..
+            } => {
+                *start_x += dx;
+                *start_y += dy;
+                *end_x += dx;
+                *end_y += dy;
+            }
+            Annotation::Pen { points, .. } => {
+                for (x, y) in points.iter_mut() {
+                    *x += dx;
+                    *y += dy;
+                }
+            }
+        }
+        annotation
+    }
+}
+
+// 箭头头部的长度和张开角度，与界面上的绘制保持一致
+pub const ARROW_HEAD_LENGTH: f32 = 15.0;
+pub const ARROW_HEAD_ANGLE: f32 = std::f32::consts::PI / 6.0;
+
+// 解析 "#rrggbb" 或 "#rrggbbaa" 格式的颜色，失败时返回默认的标注红色
+pub fn parse_hex_color(color: &str) -> [u8; 4] {
+    const DEFAULT: [u8; 4] = [0xff, 0x00, 0x44, 0xff];
+
+    let hex = color.trim_start_matches('#');
+    if hex.len() != 6 && hex.len() != 8 {
+        return DEFAULT;
+    }
+    let Ok(value) = u32::from_str_radix(hex, 16) else {
+        return DEFAULT;
+    };
+    if hex.len() == 6 {
+        [(value >> 16) as u8, (value >> 8) as u8, value as u8, 0xff]
+    } else {
+        [
+            (value >> 24) as u8,
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ]
+    }
+}
+
+// 箭头头部两条边的端点
+pub fn arrow_head_points(start_x: f32, start_y: f32, end_x: f32, end_y: f32) -> [(f32, f32); 2] {
+    let dx = end_x - start_x;
+    let dy = end_y - start_y;
+    let length = (dx * dx + dy * dy).sqrt();
+    let (unit_x, unit_y) = if length > 0.0 {
+        (dx / length, dy / length)
+    } else {
+        (0.0, 0.0)
+    };
+
+    [ARROW_HEAD_ANGLE, -ARROW_HEAD_ANGLE].map(|angle| {
+        let (sin, cos) = angle.sin_cos();
+        (
+            end_x - ARROW_HEAD_LENGTH * (unit_x * cos - unit_y * sin),
+            end_y - ARROW_HEAD_LENGTH * (unit_y * cos + unit_x * sin),
+        )
+    })
+}
@@ -0,0 +1,600 @@
+// 识别选区中的二维码和条码
+//
+// 二维码交给 rqrr 识别，它本身能处理旋转和透视；码太小时把图像放大后再试一次。
+// 一维条码目前支持 EAN-13（商品条码）：逐行（以及逐列，对应旋转 90°）二值化后按
+// 条和空的宽度比例解码，正反两个方向都尝试，最后用校验位排除误识别。
+
+use crate::detect;
+
+// 识别结果，坐标相对于传入图像的左上角
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedCode {
+    pub kind: &'static str, // "QR" 或 "EAN-13"
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+// 小于这个尺寸的图像放大后再识别二维码
+const SMALL_IMAGE_SIZE: usize = 300;
+// 一行中最亮和最暗的差小于这个值时不识别条码
+const MIN_CONTRAST: u8 = 48;
+// 每个数字的四段宽度与标准比例的最大允许误差（以模块为单位的总和）
+const MAX_DIGIT_ERROR: f32 = 1.6;
+
+// L 码每个数字的四段宽度（空、条、空、条）；R 码宽度相同、颜色相反，G 码为 L 码的倒序
+const DIGIT_WIDTHS: [[u8; 4]; 10] = [
+    [3, 2, 1, 1],
+    [2, 2, 2, 1],
+    [2, 1, 2, 2],
+    [1, 4, 1, 1],
+    [1, 1, 3, 2],
+    [1, 2, 3, 1],
+    [1, 1, 1, 4],
+    [1, 3, 1, 2],
+    [1, 2, 1, 3],
+    [3, 1, 1, 2],
+];
+// 左半部分六个数字使用 L/G 码的组合决定第一位数字，true 表示 G 码
+const FIRST_DIGIT_PARITY: [[bool; 6]; 10] = [
+    [false, false, false, false, false, false],
+    [false, false, true, false, true, true],
+    [false, false, true, true, false, true],
+    [false, false, true, true, true, false],
+    [false, true, false, false, true, true],
+    [false, true, true, false, false, true],
+    [false, true, true, true, false, false],
+    [false, true, false, true, false, true],
+    [false, true, false, true, true, false],
+    [false, true, true, false, true, false],
+];
+
+// 识别 RGBA 图像中的所有二维码和 EAN-13 条码
+pub fn scan(data: &[u8], width: u32, height: u32) -> Vec<DetectedCode> {
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+    let gray = detect::luminance(data, width, height);
+    let (width, height) = (width as usize, height as usize);
+    let mut codes = scan_qr(&gray, width, height);
+    codes.extend(scan_ean13(&gray, width, height));
+    codes
+}
+
+fn scan_qr(gray: &[u8], width: usize, height: usize) -> Vec<DetectedCode> {
+    let scales: &[usize] = if width.min(height) < SMALL_IMAGE_SIZE {
+        &[1, 2, 3]
+    } else {
+        &[1]
+    };
+    for &scale in scales {
+        // 模块只有一两个像素时 rqrr 内部的断言可能失败，当作没有识别到，用放大的图像再试
+        let codes = std::panic::catch_unwind(|| scan_qr_scaled(gray, width, height, scale))
+            .unwrap_or_default();
+        if !codes.is_empty() {
+            return codes;
+        }
+    }
+    Vec::new()
+}
+
+// 把图像放大 scale 倍后识别二维码，坐标换算回原图
+fn scan_qr_scaled(gray: &[u8], width: usize, height: usize, scale: usize) -> Vec<DetectedCode> {
+    let mut prepared =
+        rqrr::PreparedImage::prepare_from_greyscale(width * scale, height * scale, |x, y| {
+            gray[(y / scale) * width + x / scale]
+        });
+    prepared
+        .detect_grids()
+        .into_iter()
+        .filter_map(|grid| {
+            let (_, text) = grid.decode().ok()?;
+            let xs = grid.bounds.map(|point| point.x as f32 / scale as f32);
+            let ys = grid.bounds.map(|point| point.y as f32 / scale as f32);
+            let (min_x, max_x) = min_max(&xs);
+            let (min_y, max_y) = min_max(&ys);
+            Some(DetectedCode {
+                kind: "QR",
+                text,
+                x: min_x,
+                y: min_y,
+                width: max_x - min_x,
+                height: max_y - min_y,
+            })
+        })
+        .collect()
+}
+
+fn min_max(values: &[f32]) -> (f32, f32) {
+    values
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        })
+}
+
+// 逐行、逐列扫描 EAN-13，同一个码在多行上识别出的位置合并为一个范围
+fn scan_ean13(gray: &[u8], width: usize, height: usize) -> Vec<DetectedCode> {
+    let mut codes: Vec<DetectedCode> = Vec::new();
+    let mut add = |text: String, x0: f32, y0: f32, x1: f32, y1: f32| {
+        if let Some(code) = codes.iter_mut().find(|code| code.text == text) {
+            let (right, bottom) = (code.x + code.width, code.y + code.height);
+            code.x = code.x.min(x0);
+            code.y = code.y.min(y0);
+            code.width = right.max(x1) - code.x;
+            code.height = bottom.max(y1) - code.y;
+        } else {
+            codes.push(DetectedCode {
+                kind: "EAN-13",
+                text,
+                x: x0,
+                y: y0,
+                width: x1 - x0,
+                height: y1 - y0,
+            });
+        }
+    };
+
+    let mut line = Vec::with_capacity(width.max(height));
+    for y in (0..height).step_by(2) {
+        line.clear();
+        line.extend_from_slice(&gray[y * width..(y + 1) * width]);
+        if let Some((text, start, end)) = decode_line(&line) {
+            add(text, start as f32, y as f32, end as f32, y as f32 + 1.0);
+        }
+    }
+    for x in (0..width).step_by(2) {
+        line.clear();
+        line.extend((0..height).map(|y| gray[y * width + x]));
+        if let Some((text, start, end)) = decode_line(&line) {
+            add(text, x as f32, start as f32, x as f32 + 1.0, end as f32);
+        }
+    }
+    codes
+}
+
+// 在一行像素中寻找 EAN-13，返回内容和条码在这一行中的起止位置。先正向再反向尝试
+fn decode_line(line: &[u8]) -> Option<(String, usize, usize)> {
+    let (min, max) = line.iter().fold((u8::MAX, u8::MIN), |(min, max), &value| {
+        (min.min(value), max.max(value))
+    });
+    if max.saturating_sub(min) < MIN_CONTRAST {
+        return None;
+    }
+    let threshold = ((min as u16 + max as u16) / 2) as u8;
+
+    // 游程：(是否为条, 起点, 宽度)
+    let mut runs: Vec<(bool, usize, usize)> = Vec::new();
+    for (index, &value) in line.iter().enumerate() {
+        let dark = value < threshold;
+        match runs.last_mut() {
+            Some(run) if run.0 == dark => run.2 += 1,
+            _ => runs.push((dark, index, 1)),
+        }
+    }
+
+    if let Some((text, first, last)) = decode_runs(&runs) {
+        return Some((text, runs[first].1, runs[last].1 + runs[last].2));
+    }
+    let reversed: Vec<(bool, usize, usize)> = runs.iter().rev().copied().collect();
+    let (text, first, last) = decode_runs(&reversed)?;
+    Some((
+        text,
+        reversed[last].1,
+        reversed[first].1 + reversed[first].2,
+    ))
+}
+
+// 从每个条的位置开始尝试解码 59 段（起始符 3 + 左侧 24 + 中间分隔符 5 + 右侧 24 + 终止符 3），
+// 返回内容和首尾段的下标
+fn decode_runs(runs: &[(bool, usize, usize)]) -> Option<(String, usize, usize)> {
+    const RUN_COUNT: usize = 59;
+    if runs.len() < RUN_COUNT {
+        return None;
+    }
+    for start in 0..=runs.len() - RUN_COUNT {
+        if !runs[start].0 {
+            continue;
+        }
+        let widths: Vec<f32> = runs[start..start + RUN_COUNT]
+            .iter()
+            .map(|run| run.2 as f32)
+            .collect();
+        // 起始符三段宽度接近，前面要有足够宽的空白
+        let module = (widths[0] + widths[1] + widths[2]) / 3.0;
+        if widths[..3]
+            .iter()
+            .any(|&width| (width - module).abs() > module * 0.6)
+        {
+            continue;
+        }
+        if start > 0 && (runs[start - 1].2 as f32) < module * 3.0 {
+            continue;
+        }
+        if let Some(text) = decode_digits(&widths) {
+            return Some((text, start, start + RUN_COUNT - 1));
+        }
+    }
+    None
+}
+
+// 匹配一个数字的四段宽度，返回 (数字, 是否为 G 码)
+fn match_digit(widths: &[f32], allow_g: bool) -> Option<(u8, bool)> {
+    let total: f32 = widths.iter().sum();
+    let normalized: Vec<f32> = widths.iter().map(|width| width * 7.0 / total).collect();
+    let mut best: Option<(u8, bool, f32)> = None;
+    for (digit, pattern) in DIGIT_WIDTHS.iter().enumerate() {
+        for g in [false, true] {
+            if g && !allow_g {
+                continue;
+            }
+            let error: f32 = (0..4)
+                .map(|index| {
+                    let expected = if g {
+                        pattern[3 - index]
+                    } else {
+                        pattern[index]
+                    };
+                    (normalized[index] - expected as f32).abs()
+                })
+                .sum();
+            if best.is_none_or(|(_, _, best_error)| error < best_error) {
+                best = Some((digit as u8, g, error));
+            }
+        }
+    }
+    let (digit, g, error) = best?;
+    (error <= MAX_DIGIT_ERROR).then_some((digit, g))
+}
+
+fn decode_digits(widths: &[f32]) -> Option<String> {
+    let mut digits = Vec::with_capacity(13);
+    let mut parity = [false; 6];
+    for (index, item) in parity.iter_mut().enumerate() {
+        let offset = 3 + index * 4;
+        let (digit, g) = match_digit(&widths[offset..offset + 4], true)?;
+        digits.push(digit);
+        *item = g;
+    }
+    // 中间分隔符五段都是一个模块宽
+    let module = widths[..3].iter().sum::<f32>() / 3.0;
+    if widths[27..32]
+        .iter()
+        .any(|&width| (width - module).abs() > module * 0.7)
+    {
+        return None;
+    }
+    for index in 0..6 {
+        let offset = 32 + index * 4;
+        let (digit, _) = match_digit(&widths[offset..offset + 4], false)?;
+        digits.push(digit);
+    }
+
+    let first = FIRST_DIGIT_PARITY
+        .iter()
+        .position(|pattern| *pattern == parity)?;
+    digits.insert(0, first as u8);
+
+    let sum: u32 = digits[..12]
+        .iter()
+        .enumerate()
+        .map(|(index, &digit)| digit as u32 * if index % 2 == 0 { 1 } else { 3 })
+        .sum();
+    if (10 - sum % 10) % 10 != digits[12] as u32 {
+        return None;
+    }
+    Some(
+        digits
+            .iter()
+            .map(|digit| char::from(b'0' + digit))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 内容为 "screenshot-slint" 的版本 1（21×21）二维码，纠错等级 L，掩码 0
+    const QR_MODULES: [&str; 21] = [
+        "#######..###..#######",
+        "#.....#..#....#.....#",
+        "#.###.#.#.#...#.###.#",
+        "#.###.#..#..#.#.###.#",
+        "#.###.#..#....#.###.#",
+        "#.....#..##...#.....#",
+        "#######.#.#.#.#######",
+        "........#.#..........",
+        "###.#####.#..##...#..",
+        "###.##.#.#.#######.##",
+        "#.#####..####.####.##",
+        ".##.##..#..#....#...#",
+        "...##.#.#..#....##..#",
+        "........#.###.#.##..#",
+        "#######.#.####.##..##",
+        "#.....#.#...#..##..#.",
+        "#.###.#.##..##.#....#",
+        "#.###.#.....#..##....",
+        "#.###.#.#.###.#####.#",
+        "#.....#.#.#..#.....#.",
+        "#######.#..#...##..##",
+    ];
+    const QR_TEXT: &str = "screenshot-slint";
+    const QUIET_MODULES: usize = 4;
+
+    // EAN-13 的 L 码（G 码是 R 码的倒序，R 码是 L 码取反）和第一位数字对应的 L/G 组合
+    const L_CODES: [&str; 10] = [
+        "0001101", "0011001", "0010011", "0111101", "0100011", "0110001", "0101111", "0111011",
+        "0110111", "0001011",
+    ];
+    const PARITY: [&str; 10] = [
+        "LLLLLL", "LLGLGG", "LLGGLG", "LLGGGL", "LGLLGG", "LGGLLG", "LGGGLL", "LGLGLG", "LGLGGL",
+        "LGGLGL",
+    ];
+    const EAN_TEXT: &str = "4006381333931";
+
+    // 二值图像（true 为黑）转换为 RGBA
+    fn to_rgba(pixels: &[bool]) -> Vec<u8> {
+        pixels
+            .iter()
+            .flat_map(|&dark| if dark { [0, 0, 0, 255] } else { [255; 4] })
+            .collect()
+    }
+
+    // 按 (x, y) -> 模块坐标的映射绘制二维码，映射结果落在码外时为白色
+    fn render_qr(
+        size: usize,
+        to_module: impl Fn(f32, f32) -> (f32, f32),
+        modules: &[String],
+    ) -> Vec<u8> {
+        let count = modules.len() as f32;
+        let mut pixels = vec![false; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                let (mx, my) = to_module(x as f32 + 0.5, y as f32 + 0.5);
+                if mx >= 0.0 && my >= 0.0 && mx < count && my < count {
+                    pixels[y * size + x] = modules[my as usize].as_bytes()[mx as usize] == b'#';
+                }
+            }
+        }
+        to_rgba(&pixels)
+    }
+
+    fn qr_modules() -> Vec<String> {
+        QR_MODULES.iter().map(|row| row.to_string()).collect()
+    }
+
+    // 顺时针旋转 90°
+    fn rotate_modules(modules: &[String]) -> Vec<String> {
+        let size = modules.len();
+        (0..size)
+            .map(|y| {
+                (0..size)
+                    .map(|x| modules[size - 1 - x].as_bytes()[y] as char)
+                    .collect()
+            })
+            .collect()
+    }
+
+    // 不旋转、每个模块 module 像素、四周留白的二维码图像
+    fn upright_qr(modules: &[String], module: usize) -> (Vec<u8>, u32) {
+        let size = (modules.len() + QUIET_MODULES * 2) * module;
+        let quiet = (QUIET_MODULES * module) as f32;
+        let data = render_qr(
+            size,
+            |x, y| ((x - quiet) / module as f32, (y - quiet) / module as f32),
+            modules,
+        );
+        (data, size as u32)
+    }
+
+    fn qr_texts(codes: &[DetectedCode]) -> Vec<&str> {
+        codes
+            .iter()
+            .filter(|code| code.kind == "QR")
+            .map(|code| code.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn reads_upright_qr() {
+        let (data, size) = upright_qr(&qr_modules(), 8);
+        let codes = scan(&data, size, size);
+        assert_eq!(qr_texts(&codes), vec![QR_TEXT]);
+
+        // 范围覆盖整个码（去掉留白后 21 × 8 像素）
+        let code = &codes[0];
+        let quiet = (QUIET_MODULES * 8) as f32;
+        assert!((code.x - quiet).abs() <= 8.0, "{:?}", code);
+        assert!((code.y - quiet).abs() <= 8.0, "{:?}", code);
+        assert!((code.width - 168.0).abs() <= 16.0, "{:?}", code);
+        assert!((code.height - 168.0).abs() <= 16.0, "{:?}", code);
+    }
+
+    #[test]
+    fn reads_qr_rotated_90_degrees() {
+        let (data, size) = upright_qr(&rotate_modules(&qr_modules()), 8);
+        assert_eq!(qr_texts(&scan(&data, size, size)), vec![QR_TEXT]);
+    }
+
+    #[test]
+    fn reads_skewed_qr() {
+        // 旋转 30° 并水平错切，码的中心在图像中心
+        let (size, module) = (400usize, 8.0f32);
+        let (sin, cos) = 30f32.to_radians().sin_cos();
+        let shear = 0.15;
+        let center = size as f32 / 2.0;
+        let half = QR_MODULES.len() as f32 / 2.0;
+        let data = render_qr(
+            size,
+            |x, y| {
+                let (dx, dy) = (x - center, y - center);
+                let (rx, ry) = (dx * cos + dy * sin, -dx * sin + dy * cos);
+                let rx = rx - shear * ry;
+                (rx / module + half, ry / module + half)
+            },
+            &qr_modules(),
+        );
+        assert_eq!(
+            qr_texts(&scan(&data, size as u32, size as u32)),
+            vec![QR_TEXT]
+        );
+    }
+
+    #[test]
+    fn reads_small_qr_by_upscaling() {
+        // 每个模块只有 1 像素，原图无法识别，需要放大后再识别
+        let (data, size) = upright_qr(&qr_modules(), 1);
+        assert!((size as usize) < SMALL_IMAGE_SIZE);
+        let codes = scan(&data, size, size);
+        assert_eq!(qr_texts(&codes), vec![QR_TEXT]);
+        // 坐标换算回原图
+        assert!(codes[0].x + codes[0].width <= size as f32 + 1.0);
+        assert!(codes[0].y + codes[0].height <= size as f32 + 1.0);
+    }
+
+    // EAN-13 的模块序列（1 为条），包括起始符、中间分隔符和终止符
+    fn ean13_modules(text: &str) -> String {
+        let digits: Vec<usize> = text.bytes().map(|byte| (byte - b'0') as usize).collect();
+        let mut modules = String::from("101");
+        for (index, &digit) in digits[1..7].iter().enumerate() {
+            let l = L_CODES[digit];
+            if PARITY[digits[0]].as_bytes()[index] == b'G' {
+                // G 码：R 码（L 码取反）倒序
+                modules.extend(l.chars().rev().map(|ch| if ch == '0' { '1' } else { '0' }));
+            } else {
+                modules.push_str(l);
+            }
+        }
+        modules.push_str("01010");
+        for &digit in &digits[7..] {
+            modules.extend(
+                L_CODES[digit]
+                    .chars()
+                    .map(|ch| if ch == '0' { '1' } else { '0' }),
+            );
+        }
+        modules.push_str("101");
+        modules
+    }
+
+    // 横向的条码图像，每个模块 module 像素，左右各留 10 个模块的空白
+    fn ean13_image(text: &str, module: usize, height: usize) -> (Vec<u8>, u32, u32) {
+        let modules = format!("{0}{1}{0}", "0".repeat(10), ean13_modules(text));
+        let row: Vec<bool> = modules
+            .bytes()
+            .flat_map(|bit| std::iter::repeat_n(bit == b'1', module))
+            .collect();
+        let width = row.len();
+        let pixels = row.repeat(height);
+        (to_rgba(&pixels), width as u32, height as u32)
+    }
+
+    fn ean_codes(codes: &[DetectedCode]) -> Vec<&DetectedCode> {
+        codes.iter().filter(|code| code.kind == "EAN-13").collect()
+    }
+
+    #[test]
+    fn reads_ean13_left_to_right() {
+        let (data, width, height) = ean13_image(EAN_TEXT, 3, 60);
+        let codes = scan(&data, width, height);
+        let eans = ean_codes(&codes);
+        assert_eq!(eans.len(), 1, "{:?}", codes);
+        assert_eq!(eans[0].text, EAN_TEXT);
+        // 从起始符到终止符共 95 个模块
+        assert!((eans[0].x - 30.0).abs() <= 1.0, "{:?}", eans[0]);
+        assert!((eans[0].width - 285.0).abs() <= 2.0, "{:?}", eans[0]);
+    }
+
+    #[test]
+    fn reads_ean13_rotated_180_degrees() {
+        let (data, width, height) = ean13_image(EAN_TEXT, 3, 60);
+        // 像素整体倒序即旋转 180°
+        let rotated: Vec<u8> = data.chunks_exact(4).rev().flatten().copied().collect();
+        let codes = scan(&rotated, width, height);
+        let eans = ean_codes(&codes);
+        assert_eq!(eans.len(), 1, "{:?}", codes);
+        assert_eq!(eans[0].text, EAN_TEXT);
+    }
+
+    #[test]
+    fn reads_vertical_ean13() {
+        let (data, width, height) = ean13_image(EAN_TEXT, 2, 40);
+        // 转置为竖直的条码
+        let mut transposed = Vec::with_capacity(data.len());
+        for x in 0..width as usize {
+            for y in 0..height as usize {
+                let index = (y * width as usize + x) * 4;
+                transposed.extend_from_slice(&data[index..index + 4]);
+            }
+        }
+        let codes = scan(&transposed, height, width);
+        assert_eq!(ean_codes(&codes)[0].text, EAN_TEXT);
+    }
+
+    #[test]
+    fn rejects_ean13_with_wrong_check_digit() {
+        let (data, width, height) = ean13_image("4006381333932", 3, 60);
+        assert!(ean_codes(&scan(&data, width, height)).is_empty());
+    }
+
+    #[test]
+    fn ignores_blank_image() {
+        let data = [255u8; 4].repeat(100 * 80);
+        assert!(scan(&data, 100, 80).is_empty());
+        assert!(scan(&[], 0, 0).is_empty());
+    }
+
+    // 模块序列转换为游程，每个模块 module 像素
+    fn runs(modules: &str, module: usize) -> Vec<(bool, usize, usize)> {
+        let mut runs: Vec<(bool, usize, usize)> = Vec::new();
+        for (index, bit) in modules.bytes().enumerate() {
+            let dark = bit == b'1';
+            match runs.last_mut() {
+                Some(run) if run.0 == dark => run.2 += module,
+                _ => runs.push((dark, index * module, module)),
+            }
+        }
+        runs
+    }
+
+    #[test]
+    fn match_digit_reads_l_and_g_codes() {
+        for (digit, widths) in DIGIT_WIDTHS.iter().enumerate() {
+            let widths: Vec<f32> = widths.iter().map(|&width| width as f32 * 3.0).collect();
+            assert_eq!(match_digit(&widths, true), Some((digit as u8, false)));
+            let reversed: Vec<f32> = widths.iter().rev().copied().collect();
+            if reversed != widths {
+                assert_eq!(match_digit(&reversed, true), Some((digit as u8, true)));
+            }
+        }
+    }
+
+    #[test]
+    fn match_digit_tolerates_small_errors_only() {
+        assert_eq!(match_digit(&[3.3, 1.8, 1.1, 0.9], false), Some((0, false)));
+        // 右半部分不允许 G 码
+        assert_eq!(match_digit(&[1.0, 1.0, 2.0, 3.0], false), None);
+        assert_eq!(match_digit(&[5.0, 1.0, 1.0, 0.2], true), None);
+    }
+
+    #[test]
+    fn decode_runs_finds_code_after_quiet_zone() {
+        let modules = format!("{0}{1}{0}", "0".repeat(9), ean13_modules(EAN_TEXT));
+        let runs = runs(&modules, 2);
+        let (text, first, last) = decode_runs(&runs).unwrap();
+        assert_eq!(text, EAN_TEXT);
+        assert_eq!((first, last), (1, 59));
+        assert_eq!(runs[first].1, 18);
+        assert_eq!(runs[last].1 + runs[last].2, 18 + 95 * 2);
+    }
+
+    #[test]
+    fn decode_runs_requires_quiet_zone() {
+        let modules = format!("0{}0", ean13_modules(EAN_TEXT));
+        assert_eq!(decode_runs(&runs(&modules, 2)), None);
+        assert_eq!(decode_runs(&runs("0101", 2)), None);
+    }
+}
@@ -0,0 +1,400 @@
+// 美化：给截图加上边距、背景、圆角、阴影和可选的仿窗口标题栏，用于文档和幻灯片
+//
+// 先把标题栏和截图拼成一个「窗口」，再画背景，在窗口下方画模糊后的阴影，
+// 最后把窗口按圆角的覆盖率混合到背景上。
+
+use crate::annotation::parse_hex_color;
+use crate::config::BeautifyPreset;
+use crate::text;
+
+const TITLE_BAR_HEIGHT: u32 = 28;
+const TITLE_BAR_COLOR: [u8; 4] = [0xe8, 0xe8, 0xe8, 0xff];
+const TITLE_COLOR: [u8; 4] = [0x44, 0x44, 0x44, 0xff];
+const TITLE_FONT_SIZE: f32 = 13.0;
+// 标题栏左侧的关闭、最小化、最大化按钮
+const WINDOW_BUTTON_COLORS: [[u8; 4]; 3] = [
+    [0xff, 0x5f, 0x57, 0xff],
+    [0xfe, 0xbc, 0x2e, 0xff],
+    [0x28, 0xc8, 0x40, 0xff],
+];
+const WINDOW_BUTTON_RADIUS: f32 = 6.0;
+const WINDOW_BUTTON_SPACING: f32 = 20.0;
+// 三次盒式模糊近似高斯模糊
+const BLUR_PASSES: usize = 3;
+
+// 按预设美化 RGBA 图像，返回 (RGBA 数据, 宽, 高)
+pub fn beautify(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    preset: &BeautifyPreset,
+) -> (Vec<u8>, u32, u32) {
+    let (window, window_width, window_height) = if preset.title_bar {
+        with_title_bar(data, width, height, &preset.title)
+    } else {
+        (data.to_vec(), width, height)
+    };
+
+    let padding = preset.padding;
+    let canvas_width = window_width + padding * 2;
+    let canvas_height = window_height + padding * 2;
+    let mut canvas = background(preset, canvas_width, canvas_height);
+
+    let radius = preset
+        .corner_radius
+        .clamp(0.0, window_width.min(window_height) as f32 / 2.0);
+    if preset.shadow_blur > 0.0 {
+        draw_shadow(
+            &mut canvas,
+            (canvas_width, canvas_height),
+            (padding as f32, padding as f32 + preset.shadow_offset_y),
+            (window_width as f32, window_height as f32),
+            radius,
+            preset.shadow_blur,
+            parse_hex_color(&preset.shadow_color),
+        );
+    }
+
+    // 圆角外的部分按覆盖率与背景混合
+    for y in 0..window_height {
+        for x in 0..window_width {
+            let coverage = rounded_rect_coverage(
+                x as f32 + 0.5,
+                y as f32 + 0.5,
+                (window_width as f32, window_height as f32),
+                radius,
+            );
+            if coverage <= 0.0 {
+                continue;
+            }
+            let src = ((y * window_width + x) * 4) as usize;
+            let dst = (((y + padding) * canvas_width + x + padding) * 4) as usize;
+            let color = [
+                window[src],
+                window[src + 1],
+                window[src + 2],
+                window[src + 3],
+            ];
+            blend(&mut canvas[dst..dst + 4], color, coverage);
+        }
+    }
+    (canvas, canvas_width, canvas_height)
+}
+
+// 原图左上角在美化后图像中的位置
+pub fn content_offset(preset: &BeautifyPreset) -> (u32, u32) {
+    let title_bar = if preset.title_bar {
+        TITLE_BAR_HEIGHT
+    } else {
+        0
+    };
+    (preset.padding, preset.padding + title_bar)
+}
+
+// 按覆盖率把颜色混合到像素上
+fn blend(pixel: &mut [u8], color: [u8; 4], coverage: f32) {
+    let alpha = coverage * color[3] as f32 / 255.0;
+    if alpha <= 0.0 {
+        return;
+    }
+    for channel in 0..3 {
+        let dst = pixel[channel] as f32;
+        pixel[channel] = (dst + (color[channel] as f32 - dst) * alpha).round() as u8;
+    }
+    let dst_alpha = pixel[3] as f32 / 255.0;
+    pixel[3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+}
+
+// 像素中心 (x, y) 在圆角矩形 (0, 0, width, height) 内的覆盖率，圆角边缘做抗锯齿
+fn rounded_rect_coverage(x: f32, y: f32, (width, height): (f32, f32), radius: f32) -> f32 {
+    if x < 0.0 || y < 0.0 || x > width || y > height {
+        return 0.0;
+    }
+    let dx = (radius - x).max(x - (width - radius)).max(0.0);
+    let dy = (radius - y).max(y - (height - radius)).max(0.0);
+    if dx > 0.0 && dy > 0.0 {
+        (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0)
+    } else {
+        1.0
+    }
+}
+
+fn with_title_bar(data: &[u8], width: u32, height: u32, title: &str) -> (Vec<u8>, u32, u32) {
+    let total_height = height + TITLE_BAR_HEIGHT;
+    let mut window = Vec::with_capacity((width * total_height * 4) as usize);
+    for _ in 0..width * TITLE_BAR_HEIGHT {
+        window.extend_from_slice(&TITLE_BAR_COLOR);
+    }
+    window.extend_from_slice(data);
+
+    let center_y = TITLE_BAR_HEIGHT as f32 / 2.0;
+    for (index, color) in WINDOW_BUTTON_COLORS.iter().enumerate() {
+        let center_x = WINDOW_BUTTON_SPACING * (index as f32 + 0.7);
+        let (left, right) = (
+            center_x - WINDOW_BUTTON_RADIUS - 1.0,
+            center_x + WINDOW_BUTTON_RADIUS + 1.0,
+        );
+        for y in 0..TITLE_BAR_HEIGHT {
+            for x in left.max(0.0) as u32..(right.ceil() as u32).min(width) {
+                let distance = ((x as f32 + 0.5 - center_x).powi(2)
+                    + (y as f32 + 0.5 - center_y).powi(2))
+                .sqrt();
+                let coverage = (WINDOW_BUTTON_RADIUS - distance + 0.5).clamp(0.0, 1.0);
+                let index = ((y * width + x) * 4) as usize;
+                blend(&mut window[index..index + 4], *color, coverage);
+            }
+        }
+    }
+
+    // 标题居中，放不下时从按钮右侧开始
+    let font = (!title.is_empty()).then(text::load_system_font).flatten();
+    if let Some(font) = font {
+        let text_width = text::text_width(&font, TITLE_FONT_SIZE, title);
+        let min_x = WINDOW_BUTTON_SPACING * 3.5;
+        let x = ((width as f32 - text_width) / 2.0).max(min_x);
+        let y = (TITLE_BAR_HEIGHT as f32 - text::line_height(&font, TITLE_FONT_SIZE)) / 2.0;
+        // 只画在标题栏里，不会压到截图上
+        let bar_bytes = (width * TITLE_BAR_HEIGHT * 4) as usize;
+        text::draw_text(
+            &mut window[..bar_bytes],
+            (width, TITLE_BAR_HEIGHT),
+            &font,
+            TITLE_FONT_SIZE,
+            (x, y),
+            title,
+            TITLE_COLOR,
+        );
+    }
+    (window, width, total_height)
+}
+
+fn background(preset: &BeautifyPreset, width: u32, height: u32) -> Vec<u8> {
+    let color = parse_hex_color(&preset.color);
+    match preset.background.as_str() {
+        "gradient" => gradient(
+            width,
+            height,
+            color,
+            parse_hex_color(&preset.gradient_end),
+            preset.gradient_angle,
+        ),
+        "wallpaper" => match preset.wallpaper.as_deref().map(image::open) {
+            Some(Ok(image)) => wallpaper(image.into_rgba8(), width, height),
+            Some(Err(err)) => {
+                println!("无法读取背景图片，改用纯色背景: {}", err);
+                solid(width, height, color)
+            }
+            None => {
+                println!("没有设置背景图片，改用纯色背景");
+                solid(width, height, color)
+            }
+        },
+        "solid" => solid(width, height, color),
+        other => {
+            println!("不支持的背景类型 {}，改用纯色背景", other);
+            solid(width, height, color)
+        }
+    }
+}
+
+fn solid(width: u32, height: u32, color: [u8; 4]) -> Vec<u8> {
+    color.repeat((width * height) as usize)
+}
+
+// 线性渐变：沿渐变方向把画布投影到 0 - 1。角度与 CSS 相同，方向向量为 (sin, -cos)
+fn gradient(width: u32, height: u32, start: [u8; 4], end: [u8; 4], angle: f32) -> Vec<u8> {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (direction_x, direction_y) = (sin, -cos);
+    let half_extent =
+        ((direction_x.abs() * width as f32 + direction_y.abs() * height as f32) / 2.0).max(1.0);
+    let (center_x, center_y) = (width as f32 / 2.0, height as f32 / 2.0);
+
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let projection = (x as f32 + 0.5 - center_x) * direction_x
+                + (y as f32 + 0.5 - center_y) * direction_y;
+            let t = ((projection / half_extent + 1.0) / 2.0).clamp(0.0, 1.0);
+            for channel in 0..4 {
+                let value =
+                    start[channel] as f32 + (end[channel] as f32 - start[channel] as f32) * t;
+                data.push(value.round() as u8);
+            }
+        }
+    }
+    data
+}
+
+// 背景图片等比缩放到铺满画布，居中裁剪
+fn wallpaper(image: image::RgbaImage, width: u32, height: u32) -> Vec<u8> {
+    let (image_width, image_height) = image.dimensions();
+    let scale = (width as f32 / image_width as f32).max(height as f32 / image_height as f32);
+    let scaled_width = ((image_width as f32 * scale).ceil() as u32).max(width);
+    let scaled_height = ((image_height as f32 * scale).ceil() as u32).max(height);
+    let scaled = image::imageops::resize(
+        &image,
+        scaled_width,
+        scaled_height,
+        image::imageops::FilterType::Triangle,
+    );
+    let left = (scaled_width - width) / 2;
+    let top = (scaled_height - height) / 2;
+    image::imageops::crop_imm(&scaled, left, top, width, height)
+        .to_image()
+        .into_raw()
+}
+
+// 阴影：圆角矩形的覆盖率蒙版模糊后按阴影颜色混合到画布上
+fn draw_shadow(
+    canvas: &mut [u8],
+    (canvas_width, canvas_height): (u32, u32),
+    (left, top): (f32, f32),
+    size: (f32, f32),
+    radius: f32,
+    blur: f32,
+    color: [u8; 4],
+) {
+    let (width, height) = (canvas_width as usize, canvas_height as usize);
+    let mut mask: Vec<f32> = (0..width * height)
+        .map(|index| {
+            let (x, y) = ((index % width) as f32, (index / width) as f32);
+            rounded_rect_coverage(x + 0.5 - left, y + 0.5 - top, size, radius)
+        })
+        .collect();
+
+    let box_radius = (blur / 2.5).ceil().max(1.0) as usize;
+    for _ in 0..BLUR_PASSES {
+        for row in mask.chunks_exact_mut(width) {
+            blur_line(row, box_radius);
+        }
+        let mut column = vec![0.0; height];
+        for x in 0..width {
+            for (y, value) in column.iter_mut().enumerate() {
+                *value = mask[y * width + x];
+            }
+            blur_line(&mut column, box_radius);
+            for (y, value) in column.iter().enumerate() {
+                mask[y * width + x] = *value;
+            }
+        }
+    }
+
+    for (pixel, coverage) in canvas.chunks_exact_mut(4).zip(mask) {
+        blend(pixel, color, coverage);
+    }
+}
+
+// 一维盒式模糊，范围外按 0 计算
+fn blur_line(line: &mut [f32], radius: usize) {
+    let mut prefix = Vec::with_capacity(line.len() + 1);
+    prefix.push(0.0);
+    for value in line.iter() {
+        prefix.push(prefix[prefix.len() - 1] + value);
+    }
+    let window = (radius * 2 + 1) as f32;
+    for (index, value) in line.iter_mut().enumerate() {
+        let start = index.saturating_sub(radius);
+        let end = (index + radius + 1).min(prefix.len() - 1);
+        *value = (prefix[end] - prefix[start]) / window;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 40;
+    const HEIGHT: u32 = 30;
+
+    fn source() -> Vec<u8> {
+        [0x20, 0x40, 0x80, 0xff].repeat((WIDTH * HEIGHT) as usize)
+    }
+
+    fn pixel(data: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * width + x) * 4) as usize;
+        data[index..index + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn output_adds_padding_and_title_bar() {
+        let preset = BeautifyPreset {
+            padding: 16,
+            ..Default::default()
+        };
+        let (data, width, height) = beautify(&source(), WIDTH, HEIGHT, &preset);
+        assert_eq!((width, height), (WIDTH + 32, HEIGHT + 32));
+        assert_eq!(data.len(), (width * height * 4) as usize);
+
+        let preset = BeautifyPreset {
+            padding: 16,
+            title_bar: true,
+            ..Default::default()
+        };
+        let (data, width, height) = beautify(&source(), WIDTH, HEIGHT, &preset);
+        assert_eq!(
+            (width, height),
+            (WIDTH + 32, HEIGHT + TITLE_BAR_HEIGHT + 32)
+        );
+        assert_eq!(data.len(), (width * height * 4) as usize);
+    }
+
+    #[test]
+    fn content_offset_points_at_source() {
+        let preset = BeautifyPreset {
+            padding: 20,
+            corner_radius: 4.0,
+            title_bar: true,
+            ..Default::default()
+        };
+        assert_eq!(content_offset(&preset), (20, 20 + TITLE_BAR_HEIGHT));
+
+        let (data, width, _) = beautify(&source(), WIDTH, HEIGHT, &preset);
+        let (x, y) = content_offset(&preset);
+        // 原图中心的像素原样保留
+        assert_eq!(
+            pixel(&data, width, x + WIDTH / 2, y + HEIGHT / 2),
+            [0x20, 0x40, 0x80, 0xff]
+        );
+        assert_eq!(content_offset(&BeautifyPreset::default()), (48, 48));
+    }
+
+    #[test]
+    fn rounded_corners_stay_transparent() {
+        let preset = BeautifyPreset {
+            padding: 0,
+            color: "#00000000".to_string(),
+            corner_radius: 10.0,
+            shadow_blur: 0.0,
+            ..Default::default()
+        };
+        let (data, width, height) = beautify(&source(), WIDTH, HEIGHT, &preset);
+        assert_eq!((width, height), (WIDTH, HEIGHT));
+        for (x, y) in [
+            (0, 0),
+            (WIDTH - 1, 0),
+            (0, HEIGHT - 1),
+            (WIDTH - 1, HEIGHT - 1),
+        ] {
+            assert_eq!(pixel(&data, width, x, y)[3], 0);
+        }
+        // 边的中点不在圆角内，完全不透明
+        assert_eq!(pixel(&data, width, WIDTH / 2, 0), [0x20, 0x40, 0x80, 0xff]);
+        assert_eq!(pixel(&data, width, 0, HEIGHT / 2), [0x20, 0x40, 0x80, 0xff]);
+    }
+
+    #[test]
+    fn shadow_darkens_background_below_window() {
+        let preset = BeautifyPreset {
+            padding: 24,
+            color: "#ffffff".to_string(),
+            shadow_blur: 12.0,
+            shadow_offset_y: 8.0,
+            ..Default::default()
+        };
+        let (data, width, height) = beautify(&source(), WIDTH, HEIGHT, &preset);
+        // 窗口正下方有阴影，远处的角落仍是背景色
+        let below = pixel(&data, width, width / 2, 24 + HEIGHT + 4);
+        assert!(below[0] < 0xff);
+        assert_eq!(pixel(&data, width, 0, height - 1), [0xff, 0xff, 0xff, 0xff]);
+    }
+}
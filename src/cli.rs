@@ -0,0 +1,416 @@
+// 命令行参数解析
+
+use crate::ipc::Request;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Capture, // 默认：交互式截图
+    CaptureRegion {
+        // capture --region x,y,w,h
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+    CaptureFullscreen,    // capture --fullscreen
+    CaptureRepeat,        // capture --repeat
+    PinClipboard,         // pin --clipboard
+    PinFile(PathBuf),     // pin <file>
+    History,              // history
+    OpenProject(PathBuf), // open <project>
+    OpenImage(PathBuf),   // open --from-image <png>
+    Export {
+        // export <input> <output>
+        input: PathBuf,
+        output: PathBuf,
+    },
+    Upload(PathBuf), // upload <file>
+    Pdf {
+        // pdf <output> [--title T] [--caption C] [--no-timestamp] <input|history-id>...
+        output: PathBuf,
+        inputs: Vec<String>,
+        title: Option<String>,
+        caption: Option<String>,
+        timestamp: bool,
+    },
+    Daemon,              // 以守护进程方式常驻
+    ListPins,            // pins
+    ClosePins(Vec<u32>), // close-pins [id...]
+    ReloadConfig,        // reload-config
+    Quit,                // quit
+    Help,
+}
+
+pub const USAGE: &str = "用法:
+  screenshot-slint                              交互式截图
+  screenshot-slint capture --region x,y,w,h     截取指定区域，按配置的输出处理（默认复制到剪贴板并钉到桌面）
+  screenshot-slint capture --fullscreen         截取整个屏幕，复制到剪贴板并保存到图片目录
+  screenshot-slint capture --repeat             再次截取上一次的区域
+  screenshot-slint pin --clipboard              把剪贴板中的图片或文字钉到桌面
+  screenshot-slint pin <file>                   把图片文件钉到桌面
+  screenshot-slint history                      打开截图历史
+  screenshot-slint open <project>               打开保存的工程（标注界面中按 Ctrl+S 保存）
+  screenshot-slint open --from-image <png>      打开导出的 PNG，恢复其中的标注继续编辑
+  screenshot-slint export <input> <output>      把工程或导出的 PNG 导出为 SVG（矢量标注）或 PNG
+  screenshot-slint upload <file>                按配置把图片上传到图床，打印并复制链接
+  screenshot-slint pdf <output.pdf> [--title 标题] [--caption 说明] [--no-timestamp] <input|history-id>...
+                                                把工程、导出的 PNG 或历史记录合并导出为 PDF，每张截图一页
+  screenshot-slint daemon                       启动守护进程并注册全局快捷键，之后的命令都转发给它
+  screenshot-slint pins                         列出守护进程中的钉图
+  screenshot-slint close-pins [id...]           关闭指定编号的钉图，不带编号时关闭全部
+  screenshot-slint reload-config                让守护进程重新读取配置文件
+  screenshot-slint quit                         退出守护进程";
+
+pub fn parse_region(text: &str) -> Result<(i32, i32, u32, u32), String> {
+    let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+    let invalid = || format!("区域格式应为 x,y,w,h: {}", text);
+    let [x, y, width, height] = parts.as_slice() else {
+        return Err(invalid());
+    };
+    Ok((
+        x.parse().map_err(|_| invalid())?,
+        y.parse().map_err(|_| invalid())?,
+        width.parse().map_err(|_| invalid())?,
+        height.parse().map_err(|_| invalid())?,
+    ))
+}
+
+fn parse_pdf(args: &[String]) -> Result<Command, String> {
+    let output = args
+        .first()
+        .filter(|output| !output.starts_with('-'))
+        .ok_or("pdf 需要输出路径")?;
+    let mut inputs = Vec::new();
+    let mut title = None;
+    let mut caption = None;
+    let mut timestamp = true;
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--title" => title = Some(rest.next().ok_or("--title 需要标题")?.clone()),
+            "--caption" => caption = Some(rest.next().ok_or("--caption 需要说明文字")?.clone()),
+            "--no-timestamp" => timestamp = false,
+            other if other.starts_with('-') => {
+                return Err(format!("pdf 不支持参数: {}", other));
+            }
+            input => inputs.push(input.to_string()),
+        }
+    }
+    if inputs.is_empty() {
+        return Err("pdf 需要至少一个输入文件或历史记录编号".to_string());
+    }
+    Ok(Command::Pdf {
+        output: PathBuf::from(output),
+        inputs,
+        title,
+        caption,
+        timestamp,
+    })
+}
+
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    match args.first().map(String::as_str) {
+        None => Ok(Command::Capture),
+        Some("-h") | Some("--help") | Some("help") => Ok(Command::Help),
+        Some("capture") => match args.get(1).map(String::as_str) {
+            None => Ok(Command::Capture),
+            Some("--region") => {
+                let region = args.get(2).ok_or("--region 需要 x,y,w,h")?;
+                let (x, y, width, height) = parse_region(region)?;
+                Ok(Command::CaptureRegion {
+                    x,
+                    y,
+                    width,
+                    height,
+                })
+            }
+            Some("--fullscreen") => Ok(Command::CaptureFullscreen),
+            Some("--repeat") => Ok(Command::CaptureRepeat),
+            Some(other) => Err(format!("capture 不支持参数: {}", other)),
+        },
+        Some("pin") => match args.get(1).map(String::as_str) {
+            Some("--clipboard") | Some("-c") => Ok(Command::PinClipboard),
+            Some(path) if !path.starts_with('-') => Ok(Command::PinFile(PathBuf::from(path))),
+            _ => Err("pin 需要 --clipboard 或图片路径".to_string()),
+        },
+        Some("history") => Ok(Command::History),
+        Some("open") => match args.get(1).map(String::as_str) {
+            Some("--from-image") => {
+                let path = args.get(2).ok_or("--from-image 需要图片路径")?;
+                Ok(Command::OpenImage(PathBuf::from(path)))
+            }
+            Some(path) if !path.starts_with('-') => Ok(Command::OpenProject(PathBuf::from(path))),
+            _ => Err("open 需要工程路径或 --from-image <图片>".to_string()),
+        },
+        Some("export") => match (args.get(1), args.get(2)) {
+            (Some(input), Some(output)) => Ok(Command::Export {
+                input: PathBuf::from(input),
+                output: PathBuf::from(output),
+            }),
+            _ => Err("export 需要输入和输出路径".to_string()),
+        },
+        Some("upload") => match args.get(1) {
+            Some(path) if !path.starts_with('-') => Ok(Command::Upload(PathBuf::from(path))),
+            _ => Err("upload 需要图片路径".to_string()),
+        },
+        Some("pdf") => parse_pdf(&args[1..]),
+        Some("daemon") => Ok(Command::Daemon),
+        Some("pins") => Ok(Command::ListPins),
+        Some("close-pins") => {
+            let ids = args[1..]
+                .iter()
+                .map(|id| id.parse().map_err(|_| format!("无效的钉图编号: {}", id)))
+                .collect::<Result<Vec<u32>, String>>()?;
+            Ok(Command::ClosePins(ids))
+        }
+        Some("reload-config") => Ok(Command::ReloadConfig),
+        Some("quit") => Ok(Command::Quit),
+        Some(other) => Err(format!("未知命令: {}", other)),
+    }
+}
+
+impl Command {
+    // 可以转发给守护进程的命令对应的请求
+    pub fn to_request(&self) -> Option<Request> {
+        match self {
+            Command::Capture => Some(Request::CaptureInteractive),
+            Command::CaptureRegion {
+                x,
+                y,
+                width,
+                height,
+            } => Some(Request::CaptureRegion {
+                x: *x,
+                y: *y,
+                width: *width,
+                height: *height,
+            }),
+            Command::CaptureFullscreen => Some(Request::CaptureFullscreen),
+            Command::CaptureRepeat => Some(Request::CaptureRepeat),
+            Command::PinClipboard => Some(Request::PinClipboard),
+            // 守护进程的工作目录可能不同，转发前换成绝对路径
+            Command::PinFile(path) => Some(Request::PinFile {
+                path: std::path::absolute(path).unwrap_or_else(|_| path.clone()),
+            }),
+            Command::History => Some(Request::ShowHistory),
+            Command::OpenProject(path) => Some(Request::OpenProject {
+                path: std::path::absolute(path).unwrap_or_else(|_| path.clone()),
+            }),
+            Command::OpenImage(path) => Some(Request::OpenImage {
+                path: std::path::absolute(path).unwrap_or_else(|_| path.clone()),
+            }),
+            Command::ListPins => Some(Request::ListPins),
+            Command::ClosePins(ids) => Some(Request::ClosePins { ids: ids.clone() }),
+            Command::ReloadConfig => Some(Request::ReloadConfig),
+            Command::Quit => Some(Request::Quit),
+            // 导出和上传不需要界面，直接在当前进程完成
+            Command::Export { .. }
+            | Command::Upload(_)
+            | Command::Pdf { .. }
+            | Command::Daemon
+            | Command::Help => None,
+        }
+    }
+
+    // 只能由守护进程处理的命令
+    pub fn requires_daemon(&self) -> bool {
+        matches!(
+            self,
+            Command::ListPins | Command::ClosePins(_) | Command::ReloadConfig | Command::Quit
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(text: &str) -> Vec<String> {
+        text.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn parse_text(text: &str) -> Result<Command, String> {
+        parse(&args(text))
+    }
+
+    #[test]
+    fn region_is_parsed_with_spaces_and_negative_origin() {
+        assert_eq!(parse_region("10,20,300,200"), Ok((10, 20, 300, 200)));
+        assert_eq!(parse_region("-1920, 0, 800 ,600"), Ok((-1920, 0, 800, 600)));
+    }
+
+    #[test]
+    fn invalid_regions_are_rejected() {
+        for text in ["", "1,2,3", "1,2,3,4,5", "a,2,3,4", "1,2,-3,4", "1.5,2,3,4"] {
+            assert!(parse_region(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn capture_commands() {
+        assert_eq!(parse(&[]), Ok(Command::Capture));
+        assert_eq!(parse_text("capture"), Ok(Command::Capture));
+        assert_eq!(
+            parse_text("capture --region 1,2,3,4"),
+            Ok(Command::CaptureRegion {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4
+            })
+        );
+        assert_eq!(
+            parse_text("capture --fullscreen"),
+            Ok(Command::CaptureFullscreen)
+        );
+        assert_eq!(parse_text("capture --repeat"), Ok(Command::CaptureRepeat));
+        assert!(parse_text("capture --region").is_err());
+        assert!(parse_text("capture --window").is_err());
+    }
+
+    #[test]
+    fn file_commands() {
+        assert_eq!(parse_text("pin -c"), Ok(Command::PinClipboard));
+        assert_eq!(
+            parse_text("pin a.png"),
+            Ok(Command::PinFile(PathBuf::from("a.png")))
+        );
+        assert!(parse_text("pin").is_err());
+        assert!(parse_text("pin --file").is_err());
+        assert_eq!(
+            parse_text("open a.ssproj"),
+            Ok(Command::OpenProject(PathBuf::from("a.ssproj")))
+        );
+        assert_eq!(
+            parse_text("open --from-image b.png"),
+            Ok(Command::OpenImage(PathBuf::from("b.png")))
+        );
+        assert!(parse_text("open --from-image").is_err());
+        assert_eq!(
+            parse_text("export a.ssproj out.svg"),
+            Ok(Command::Export {
+                input: PathBuf::from("a.ssproj"),
+                output: PathBuf::from("out.svg"),
+            })
+        );
+        assert!(parse_text("export a.ssproj").is_err());
+        assert_eq!(
+            parse_text("upload a.png"),
+            Ok(Command::Upload(PathBuf::from("a.png")))
+        );
+        assert!(parse_text("upload").is_err());
+    }
+
+    #[test]
+    fn pdf_options_and_inputs() {
+        let args = vec![
+            "pdf".to_string(),
+            "out.pdf".to_string(),
+            "a.png".to_string(),
+            "--title".to_string(),
+            "周报 (草稿)".to_string(),
+            "1700000000".to_string(),
+            "--caption".to_string(),
+            "第一行".to_string(),
+            "--no-timestamp".to_string(),
+        ];
+        assert_eq!(
+            parse(&args),
+            Ok(Command::Pdf {
+                output: PathBuf::from("out.pdf"),
+                inputs: vec!["a.png".to_string(), "1700000000".to_string()],
+                title: Some("周报 (草稿)".to_string()),
+                caption: Some("第一行".to_string()),
+                timestamp: false,
+            })
+        );
+        assert_eq!(
+            parse_text("pdf out.pdf a.png"),
+            Ok(Command::Pdf {
+                output: PathBuf::from("out.pdf"),
+                inputs: vec!["a.png".to_string()],
+                title: None,
+                caption: None,
+                timestamp: true,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_pdf_arguments_are_rejected() {
+        for text in [
+            "pdf",
+            "pdf --title x out.pdf a.png",
+            "pdf out.pdf",
+            "pdf out.pdf a.png --title",
+            "pdf out.pdf a.png --landscape",
+        ] {
+            assert!(parse_text(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn close_pins_ids() {
+        assert_eq!(parse_text("close-pins"), Ok(Command::ClosePins(Vec::new())));
+        assert_eq!(
+            parse_text("close-pins 3 1"),
+            Ok(Command::ClosePins(vec![3, 1]))
+        );
+        assert!(parse_text("close-pins 1 x").is_err());
+        assert!(parse_text("close-pins -1").is_err());
+    }
+
+    #[test]
+    fn simple_commands() {
+        assert_eq!(parse_text("--help"), Ok(Command::Help));
+        assert_eq!(parse_text("history"), Ok(Command::History));
+        assert_eq!(parse_text("daemon"), Ok(Command::Daemon));
+        assert_eq!(parse_text("pins"), Ok(Command::ListPins));
+        assert_eq!(parse_text("reload-config"), Ok(Command::ReloadConfig));
+        assert_eq!(parse_text("quit"), Ok(Command::Quit));
+        assert!(parse_text("screenshot").is_err());
+    }
+
+    #[test]
+    fn requests_use_absolute_paths() {
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(
+            Command::PinFile(PathBuf::from("shots/a.png")).to_request(),
+            Some(Request::PinFile {
+                path: cwd.join("shots/a.png"),
+            })
+        );
+        assert_eq!(
+            Command::OpenProject(PathBuf::from("a.ssproj")).to_request(),
+            Some(Request::OpenProject {
+                path: cwd.join("a.ssproj"),
+            })
+        );
+        assert_eq!(
+            Command::OpenImage(PathBuf::from("/tmp/b.png")).to_request(),
+            Some(Request::OpenImage {
+                path: PathBuf::from("/tmp/b.png"),
+            })
+        );
+    }
+
+    #[test]
+    fn local_commands_are_not_forwarded() {
+        assert_eq!(
+            Command::ClosePins(vec![2]).to_request(),
+            Some(Request::ClosePins { ids: vec![2] })
+        );
+        for command in [
+            Command::Upload(PathBuf::from("a.png")),
+            Command::Daemon,
+            Command::Help,
+        ] {
+            assert_eq!(command.to_request(), None);
+            assert!(!command.requires_daemon());
+        }
+        assert!(Command::ListPins.requires_daemon());
+        assert!(Command::Quit.requires_daemon());
+        assert!(!Command::Capture.requires_daemon());
+    }
+}
@@ -0,0 +1,174 @@
+// 复制截图到剪贴板，支持多种格式
+//
+// 不同程序能接受的剪贴板内容不一样：图像编辑器要图像，终端和聊天工具往往只接受文字，
+// 网页编辑器更喜欢 HTML。每个操作在配置中指定一组格式，按顺序尝试，前一个被剪贴板拒绝时
+// 使用下一个。可用的格式：
+//   "image"     原始图像（arboard 按平台转换）
+//   "png"       编码后的 PNG（image/png），Linux 下通过 wl-copy 或 xclip 设置
+//   "path"      保存后的文件路径
+//   "uri"       file:// URI 列表（text/uri-list），可以粘贴到文件管理器
+//   "markdown"  ![](data:image/png;base64,…)
+//   "html"      <img src="data:image/png;base64,…"> 片段
+
+use crate::export;
+use arboard::Clipboard;
+use base64::Engine;
+use std::cell::OnceCell;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+// 要复制的截图。需要文件路径的格式在没有保存过时先保存到图片目录
+pub struct ClipboardImage<'a> {
+    data: &'a [u8],
+    width: u32,
+    height: u32,
+    path: OnceCell<PathBuf>,
+    png: OnceCell<Vec<u8>>,
+}
+
+impl<'a> ClipboardImage<'a> {
+    pub fn new(data: &'a [u8], width: u32, height: u32) -> Self {
+        Self {
+            data,
+            width,
+            height,
+            path: OnceCell::new(),
+            png: OnceCell::new(),
+        }
+    }
+
+    // 已经保存过的文件，复制路径时直接使用
+    pub fn with_path(self, path: &Path) -> Self {
+        let _ = self.path.set(path.to_path_buf());
+        self
+    }
+
+    fn png(&self) -> Result<&[u8], Box<dyn Error>> {
+        if let Some(png) = self.png.get() {
+            return Ok(png);
+        }
+        let png = export::png_bytes(self.data, self.width, self.height)?;
+        Ok(self.png.get_or_init(|| png))
+    }
+
+    fn path(&self) -> Result<&Path, Box<dyn Error>> {
+        if let Some(path) = self.path.get() {
+            return Ok(path);
+        }
+        let path = export::screenshot_path("png");
+        export::save_png(&path, self.data, self.width, self.height)?;
+        println!("已保存: {}", path.display());
+        Ok(self.path.get_or_init(|| path))
+    }
+
+    fn data_uri(&self) -> Result<String, Box<dyn Error>> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(self.png()?);
+        Ok(format!("data:image/png;base64,{}", encoded))
+    }
+}
+
+// 格式是否需要先把截图保存为文件
+pub fn needs_path(format: &str) -> bool {
+    matches!(format, "path" | "uri")
+}
+
+// 按顺序尝试 formats 中的格式，返回实际使用的格式
+pub fn copy(image: &ClipboardImage, formats: &[String]) -> Result<String, Box<dyn Error>> {
+    let mut errors = Vec::new();
+    for format in formats {
+        match copy_as(image, format) {
+            Ok(()) => return Ok(format.clone()),
+            Err(err) => {
+                println!("无法以 {} 格式复制: {}", format, err);
+                errors.push(format!("{}: {}", format, err));
+            }
+        }
+    }
+    if errors.is_empty() {
+        return Err("没有配置剪贴板格式".into());
+    }
+    Err(format!("复制到剪贴板失败（{}）", errors.join("；")).into())
+}
+
+fn copy_as(image: &ClipboardImage, format: &str) -> Result<(), Box<dyn Error>> {
+    match format {
+        "image" => {
+            Clipboard::new()?.set_image(arboard::ImageData {
+                width: image.width as usize,
+                height: image.height as usize,
+                bytes: image.data.into(),
+            })?;
+        }
+        "png" => set_with_tool("image/png", image.png()?)?,
+        "path" => Clipboard::new()?.set_text(image.path()?.display().to_string())?,
+        "uri" => {
+            let uri = file_uri(&std::path::absolute(image.path()?)?);
+            set_with_tool("text/uri-list", format!("{}\r\n", uri).as_bytes())?;
+        }
+        "markdown" => Clipboard::new()?.set_text(format!("![]({})", image.data_uri()?))?,
+        "html" => {
+            let html = format!(
+                r#"<img src="{}" width="{}" height="{}" alt="screenshot">"#,
+                image.data_uri()?,
+                image.width,
+                image.height
+            );
+            Clipboard::new()?.set_html(html, Some("screenshot".to_string()))?;
+        }
+        other => return Err(format!("未知的剪贴板格式: {}", other).into()),
+    }
+    Ok(())
+}
+
+// 把路径转换为 file:// URI，路径中的特殊字符按百分号编码
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+// arboard 只能设置文字、HTML 和图像，其他 MIME 类型交给系统的剪贴板工具。
+// 工具在后台持有剪贴板内容，程序退出后仍然可以粘贴
+#[cfg(target_os = "linux")]
+fn set_with_tool(mime: &str, content: &[u8]) -> Result<(), Box<dyn Error>> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut command = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        let mut command = Command::new("wl-copy");
+        command.args(["--type", mime]);
+        command
+    } else {
+        let mut command = Command::new("xclip");
+        command.args(["-selection", "clipboard", "-t", mime, "-i"]);
+        command
+    };
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("无法启动 {}: {}", program, err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(content)?;
+    }
+    // 两个工具都会在读完输入后转入后台，父进程很快退出
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("{} 退出码 {}", program, status).into());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_with_tool(mime: &str, _content: &[u8]) -> Result<(), Box<dyn Error>> {
+    Err(format!("当前平台不支持以 {} 格式复制", mime).into())
+}
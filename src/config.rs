@@ -0,0 +1,520 @@
+// 用户配置：读取 ~/.config/screenshot-slint/config.json，缺失的字段使用默认值
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub recording: RecordingConfig,
+    pub hotkeys: HotkeyConfig,
+    pub history: HistoryConfig,
+    pub clipboard: ClipboardConfig,
+    pub pipeline: PipelineConfig,
+    pub upload: UploadConfig,
+    pub share: ShareConfig,
+    pub hooks: HooksConfig,
+    pub ocr: OcrConfig,
+    pub redact: RedactConfig,
+    pub beautify: BeautifyConfig,
+    pub watermark: WatermarkConfig,
+    pub footer: FooterConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub format: String, // "gif"、"apng" 或 "webm"
+    pub fps: u32,
+    pub gif_colors: u32,             // GIF 调色板颜色数（2 - 256）
+    pub max_seconds: u32,            // 最长录制时间，防止占用过多内存
+    pub output_dir: Option<PathBuf>, // 为空时保存到系统的视频目录
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            format: "gif".to_string(),
+            fps: 10,
+            gif_colors: 256,
+            max_seconds: 60,
+            output_dir: None,
+        }
+    }
+}
+
+// 守护进程注册的全局快捷键，留空表示不注册
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HotkeyConfig {
+    pub region_capture: String,
+    pub fullscreen_capture: String,
+    pub repeat_last_region: String,
+    pub pin_clipboard: String,
+}
+
+impl Default for HotkeyConfig {
+    fn default() -> Self {
+        Self {
+            region_capture: "Ctrl+Alt+A".to_string(),
+            fullscreen_capture: "Ctrl+Alt+F".to_string(),
+            repeat_last_region: "Ctrl+Alt+R".to_string(),
+            pin_clipboard: "Ctrl+Alt+P".to_string(),
+        }
+    }
+}
+
+// 截图历史及保留策略，默认不保存，上限为 0 表示不限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub enabled: bool,
+    pub dir: Option<PathBuf>, // 为空时保存到数据目录下的 screenshot-slint/history
+    pub max_count: usize,
+    pub max_age_days: u64,
+    pub max_disk_mb: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: None,
+            max_count: 500,
+            max_age_days: 30,
+            max_disk_mb: 1024,
+        }
+    }
+}
+
+// 各操作复制到剪贴板的格式，按顺序尝试，前一个被剪贴板拒绝时使用下一个。
+// 可用格式见 clipboard.rs："image"、"png"、"path"、"uri"、"markdown"、"html"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    pub capture: Vec<String>,    // 标注界面完成选区、capture --region
+    pub fullscreen: Vec<String>, // capture --fullscreen
+    pub pin: Vec<String>,        // 钉图右键菜单中的复制
+    pub history: Vec<String>,    // 历史窗口中的复制
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        let formats = vec!["image".to_string(), "png".to_string(), "path".to_string()];
+        Self {
+            capture: formats.clone(),
+            fullscreen: formats.clone(),
+            pin: formats.clone(),
+            history: formats,
+        }
+    }
+}
+
+// 截图完成后按顺序执行的输出
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkConfig {
+    // formats 为空时使用 clipboard 中对应操作的格式
+    Clipboard {
+        #[serde(default)]
+        formats: Vec<String>,
+    },
+    // dir 为空时保存到系统图片目录
+    Save {
+        #[serde(default)]
+        dir: Option<PathBuf>,
+    },
+    Pin,
+    // 通过 sh -c 执行，文件路径作为 $1 和 SCREENSHOT_PATH 传入
+    Command {
+        command: String,
+    },
+    // 使用 upload 中的设置，url 不为空时覆盖其中的地址
+    Upload {
+        #[serde(default)]
+        url: Option<String>,
+    },
+    History,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    pub capture: Vec<SinkConfig>, // 标注界面完成选区、capture --region、滚动截图
+    pub fullscreen: Vec<SinkConfig>, // capture --fullscreen
+    pub notify: bool,             // 完成后用桌面通知汇报每个输出的结果
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        let clipboard = SinkConfig::Clipboard {
+            formats: Vec::new(),
+        };
+        Self {
+            capture: vec![clipboard.clone(), SinkConfig::Pin, SinkConfig::History],
+            fullscreen: vec![
+                SinkConfig::Save { dir: None },
+                clipboard,
+                SinkConfig::History,
+            ],
+            notify: true,
+        }
+    }
+}
+
+// 上传到图床
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UploadConfig {
+    pub url: String,
+    pub method: String,                    // "POST" 或 "PUT"
+    pub body: String,                      // "multipart" 或 "raw"（请求体直接是 PNG）
+    pub field: String,                     // multipart 中文件字段的名称
+    pub form: BTreeMap<String, String>,    // multipart 中附加的字段
+    pub headers: BTreeMap<String, String>, // 附加的请求头
+    pub token: Option<String>,             // 以 Authorization: Bearer 发送
+    pub url_path: String, // 从 JSON 响应中取出链接的路径，例如 $.data.url；为空时整个响应体就是链接
+    pub timeout_secs: u64,
+    pub retries: u32,   // 网络错误或 5xx 时的重试次数
+    pub copy_url: bool, // 上传成功后把链接复制到剪贴板
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            method: "POST".to_string(),
+            body: "multipart".to_string(),
+            field: "file".to_string(),
+            form: BTreeMap::new(),
+            headers: BTreeMap::new(),
+            token: None,
+            url_path: String::new(),
+            timeout_secs: 30,
+            retries: 2,
+            copy_url: true,
+        }
+    }
+}
+
+// 局域网分享，默认关闭
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShareConfig {
+    pub enabled: bool,
+    pub bind: Vec<String>, // 监听的地址，例如 "127.0.0.1" 或局域网网卡的地址
+    pub port: u16,
+    pub public_host: Option<String>, // 链接中使用的主机名，为空时使用第一个监听地址
+    pub expire_minutes: u64,
+    pub max_items: usize, // 同时分享的截图数量上限，超出时最早的链接失效
+}
+
+impl Default for ShareConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: vec!["127.0.0.1".to_string()],
+            port: 8765,
+            public_host: None,
+            expire_minutes: 60,
+            max_items: 20,
+        }
+    }
+}
+
+// 各事件执行的 shell 命令，见 hooks.rs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub before_capture: Vec<String>,
+    pub after_capture: Vec<String>,
+    pub after_save: Vec<String>,
+    pub on_cancel: Vec<String>,
+    pub timeout_secs: u64, // 单个命令的最长执行时间，超时后结束命令并视为失败；0 表示不限制
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            before_capture: Vec::new(),
+            after_capture: Vec::new(),
+            after_save: Vec::new(),
+            on_cancel: Vec::new(),
+            timeout_secs: 10,
+        }
+    }
+}
+
+// 文字识别，见 ocr.rs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OcrConfig {
+    pub tesseract: PathBuf, // tesseract 可执行文件，不是绝对路径时从 PATH 中查找
+    pub model: Option<PathBuf>, // 离线模型文件（*.traineddata），设置后忽略 languages
+    pub languages: String,  // 使用系统安装的语言包，例如 "chi_sim+eng"
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            tesseract: PathBuf::from("tesseract"),
+            model: None,
+            languages: "chi_sim+eng".to_string(),
+        }
+    }
+}
+
+// 敏感信息打码建议，见 redact.rs。patterns 会整体替换默认列表，只想增加规则时需要把默认规则一起写上
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedactConfig {
+    pub block_size: f32, // 打码方块的边长
+    pub patterns: Vec<RedactPattern>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactPattern {
+    pub name: String,
+    pub regex: String,
+    #[serde(default)]
+    pub luhn: bool, // 匹配到的数字还要通过 Luhn 校验，用于银行卡号
+}
+
+impl RedactPattern {
+    fn new(name: &str, regex: &str, luhn: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            regex: regex.to_string(),
+            luhn,
+        }
+    }
+}
+
+impl Default for RedactConfig {
+    fn default() -> Self {
+        Self {
+            block_size: 12.0,
+            // 靠前的规则优先，同一段文字只按第一个匹配的规则提示
+            patterns: vec![
+                RedactPattern::new(
+                    "JWT",
+                    r"\beyJ[A-Za-z0-9_-]+\.eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+",
+                    false,
+                ),
+                RedactPattern::new(
+                    "邮箱",
+                    r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}",
+                    false,
+                ),
+                RedactPattern::new(
+                    "IP 地址",
+                    r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b|\b(?:[0-9A-Fa-f]{1,4}:){7}[0-9A-Fa-f]{1,4}\b",
+                    false,
+                ),
+                RedactPattern::new("银行卡号", r"\b(?:\d[ -]?){12,18}\d\b", true),
+                RedactPattern::new(
+                    "API 密钥",
+                    r"\b(?:sk|pk|rk)[-_](?:live|test|proj)[-_][A-Za-z0-9_-]{16,}|\bAKIA[0-9A-Z]{16}\b|\bgh[pousr]_[A-Za-z0-9]{36,}|\bxox[abprs]-[A-Za-z0-9-]{10,}|\bAIza[0-9A-Za-z_-]{35}|\b[A-Za-z0-9_-]{32,}\b",
+                    false,
+                ),
+            ],
+        }
+    }
+}
+
+// 导出时的美化（边距、背景、圆角、阴影、窗口标题栏），见 beautify.rs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeautifyConfig {
+    pub preset: String, // 截图完成时使用的预设，为空时不美化
+    pub presets: BTreeMap<String, BeautifyPreset>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeautifyPreset {
+    pub padding: u32,
+    pub background: String, // "solid"、"gradient" 或 "wallpaper"
+    pub color: String,      // 纯色背景的颜色，也是渐变的起始颜色
+    pub gradient_end: String,
+    pub gradient_angle: f32, // 渐变方向，与 CSS 相同：0° 从下到上，90° 从左到右
+    pub wallpaper: Option<PathBuf>, // 背景图片，缩放后裁剪到铺满
+    pub corner_radius: f32,
+    pub shadow_blur: f32, // 为 0 时没有阴影
+    pub shadow_offset_y: f32,
+    pub shadow_color: String, // "#rrggbbaa"
+    pub title_bar: bool,      // 在截图上方加一条仿窗口标题栏
+    pub title: String,
+}
+
+impl Default for BeautifyPreset {
+    fn default() -> Self {
+        Self {
+            padding: 48,
+            background: "solid".to_string(),
+            color: "#f3f4f6".to_string(),
+            gradient_end: "#ffffff".to_string(),
+            gradient_angle: 135.0,
+            wallpaper: None,
+            corner_radius: 10.0,
+            shadow_blur: 24.0,
+            shadow_offset_y: 8.0,
+            shadow_color: "#00000059".to_string(),
+            title_bar: false,
+            title: String::new(),
+        }
+    }
+}
+
+impl Default for BeautifyConfig {
+    fn default() -> Self {
+        let gradient = BeautifyPreset {
+            padding: 64,
+            background: "gradient".to_string(),
+            color: "#667eea".to_string(),
+            gradient_end: "#764ba2".to_string(),
+            corner_radius: 12.0,
+            shadow_blur: 32.0,
+            shadow_offset_y: 12.0,
+            ..BeautifyPreset::default()
+        };
+        let window = BeautifyPreset {
+            color: "#89f7fe".to_string(),
+            gradient_end: "#66a6ff".to_string(),
+            title_bar: true,
+            ..gradient.clone()
+        };
+        Self {
+            preset: String::new(),
+            presets: BTreeMap::from([
+                ("简洁".to_string(), BeautifyPreset::default()),
+                ("渐变".to_string(), gradient),
+                ("窗口".to_string(), window),
+            ]),
+        }
+    }
+}
+
+// 导出时叠加的水印，见 watermark.rs。text 为空且没有设置 image 时不加水印
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatermarkConfig {
+    pub text: String,
+    pub image: Option<PathBuf>, // PNG 标志，设置后代替文字
+    pub position: String, // "top-left"、"top-right"、"bottom-left"、"bottom-right" 或 "center"
+    pub opacity: f32,     // 0 - 1
+    pub tile: bool,       // 平铺整张图，此时忽略 position
+    pub font_size: f32,
+    pub color: String,
+    pub margin: f32, // 与图像边缘的距离，平铺时是水印之间的间距
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            image: None,
+            position: "bottom-right".to_string(),
+            opacity: 0.5,
+            tile: false,
+            font_size: 20.0,
+            color: "#ffffff".to_string(),
+            margin: 16.0,
+        }
+    }
+}
+
+// 导出时在图像下方加一条页脚，记录截图时间、主机名和屏幕，见 watermark.rs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FooterConfig {
+    pub enabled: bool,
+    pub timestamp: bool,
+    pub hostname: bool,
+    pub screen: bool,
+    pub font_size: f32,
+    pub color: String,
+    pub background: String,
+}
+
+impl Default for FooterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timestamp: true,
+            hostname: true,
+            screen: true,
+            font_size: 13.0,
+            color: "#ffffff".to_string(),
+            background: "#333333".to_string(),
+        }
+    }
+}
+
+impl HistoryConfig {
+    pub fn dir(&self) -> Option<PathBuf> {
+        self.dir
+            .clone()
+            .or_else(|| dirs::data_dir().map(|dir| dir.join("screenshot-slint").join("history")))
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("screenshot-slint").join("config.json"))
+    }
+
+    // 读取配置；文件不存在时使用默认配置，格式错误时返回错误
+    pub fn try_load() -> Result<Self, String> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|err| format!("配置文件 {} 格式错误: {}", path.display(), err)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    // 读取配置；格式错误时使用默认配置
+    pub fn load() -> Self {
+        Self::try_load().unwrap_or_else(|err| {
+            println!("{}", err);
+            Self::default()
+        })
+    }
+}
+
+thread_local! {
+    // 当前生效的配置，守护进程收到重新加载请求时替换
+    static CURRENT: RefCell<Option<Config>> = const { RefCell::new(None) };
+}
+
+// 当前配置，第一次使用时从文件读取
+pub fn current() -> Config {
+    CURRENT.with(|current| {
+        current
+            .borrow_mut()
+            .get_or_insert_with(Config::load)
+            .clone()
+    })
+}
+
+// 重新读取配置文件；格式错误时保留原来的配置
+pub fn reload() -> Result<(), String> {
+    let config = Config::try_load()?;
+    CURRENT.with(|current| *current.borrow_mut() = Some(config));
+    Ok(())
+}
+
+impl RecordingConfig {
+    pub fn output_dir(&self) -> PathBuf {
+        self.output_dir
+            .clone()
+            .or_else(dirs::video_dir)
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+}
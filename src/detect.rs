@@ -0,0 +1,374 @@
+// 窗口 / 界面元素自动识别
+//
+// 在还没有选区时，根据截图内容推测鼠标下方可能的矩形区域（窗口、面板、按钮等）。
+// 检测函数只依赖 RGBA 缓冲区，不涉及任何 UI 状态。
+
+// 亮度差超过该值视为边缘像素
+const EDGE_THRESHOLD: u8 = 24;
+// 候选矩形的最小宽高
+const MIN_REGION_SIZE: u32 = 16;
+// 边缘像素覆盖包围盒周长的最低比例
+const MIN_BORDER_COVERAGE: f32 = 0.75;
+// 检查边框覆盖时允许的带宽（像素）
+const BORDER_BAND: u32 = 2;
+// 判定两个矩形重复的容差（像素）
+const DUPLICATE_TOLERANCE: f32 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectedRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl DetectedRect {
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
+    }
+
+    pub fn area(&self) -> f32 {
+        self.width * self.height
+    }
+
+    fn is_close_to(&self, other: &DetectedRect) -> bool {
+        (self.x - other.x).abs() <= DUPLICATE_TOLERANCE
+            && (self.y - other.y).abs() <= DUPLICATE_TOLERANCE
+            && (self.width - other.width).abs() <= DUPLICATE_TOLERANCE
+            && (self.height - other.height).abs() <= DUPLICATE_TOLERANCE
+    }
+}
+
+// 连通区域的统计信息
+struct Component {
+    min_x: u32,
+    min_y: u32,
+    max_x: u32,
+    max_y: u32,
+}
+
+// 计算每个像素的亮度，边缘吸附和条码识别也使用它
+pub fn luminance(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let pixel_count = (width * height) as usize;
+    let mut luma = vec![0u8; pixel_count];
+    for (i, value) in luma.iter_mut().enumerate() {
+        let idx = i * 4;
+        if idx + 2 < data.len() {
+            let r = data[idx] as u32;
+            let g = data[idx + 1] as u32;
+            let b = data[idx + 2] as u32;
+            *value = ((r * 299 + g * 587 + b * 114) / 1000) as u8;
+        }
+    }
+    luma
+}
+
+// 生成边缘图：与右侧或下方像素亮度差较大的像素标记为边缘
+fn edge_map(luma: &[u8], width: u32, height: u32) -> Vec<bool> {
+    let mut edges = vec![false; luma.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let idx = (y * width + x) as usize;
+            let current = luma[idx];
+            let right = if x + 1 < width {
+                luma[idx + 1]
+            } else {
+                current
+            };
+            let below = if y + 1 < height {
+                luma[idx + width as usize]
+            } else {
+                current
+            };
+            edges[idx] = current.abs_diff(right) >= EDGE_THRESHOLD
+                || current.abs_diff(below) >= EDGE_THRESHOLD;
+        }
+    }
+    edges
+}
+
+// 8 连通标记，返回每个像素所属的区域编号（0 表示非边缘）和区域列表
+fn label_components(edges: &[bool], width: u32, height: u32) -> (Vec<u32>, Vec<Component>) {
+    let mut labels = vec![0u32; edges.len()];
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+
+    for start in 0..edges.len() {
+        if !edges[start] || labels[start] != 0 {
+            continue;
+        }
+
+        let label = components.len() as u32 + 1;
+        let mut component = Component {
+            min_x: u32::MAX,
+            min_y: u32::MAX,
+            max_x: 0,
+            max_y: 0,
+        };
+
+        labels[start] = label;
+        stack.push(start);
+        while let Some(idx) = stack.pop() {
+            let x = idx as u32 % width;
+            let y = idx as u32 / width;
+            component.min_x = component.min_x.min(x);
+            component.min_y = component.min_y.min(y);
+            component.max_x = component.max_x.max(x);
+            component.max_y = component.max_y.max(y);
+
+            for dy in -1i32..=1 {
+                for dx in -1i32..=1 {
+                    let nx = x as i32 + dx;
+                    let ny = y as i32 + dy;
+                    if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                        continue;
+                    }
+                    let nidx = (ny as u32 * width + nx as u32) as usize;
+                    if edges[nidx] && labels[nidx] == 0 {
+                        labels[nidx] = label;
+                        stack.push(nidx);
+                    }
+                }
+            }
+        }
+
+        components.push(component);
+    }
+
+    (labels, components)
+}
+
+// 计算区域边缘像素对包围盒四条边的覆盖率
+fn border_coverage(labels: &[u32], width: u32, label: u32, component: &Component) -> f32 {
+    let has_label_in = |x0: u32, x1: u32, y0: u32, y1: u32| -> bool {
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                if labels[(y * width + x) as usize] == label {
+                    return true;
+                }
+            }
+        }
+        false
+    };
+
+    let band = BORDER_BAND;
+    let mut covered = 0u32;
+
+    // 上下两条边
+    for x in component.min_x..=component.max_x {
+        let top_end = (component.min_y + band).min(component.max_y);
+        let bottom_start = component.max_y.saturating_sub(band).max(component.min_y);
+        if has_label_in(x, x, component.min_y, top_end) {
+            covered += 1;
+        }
+        if has_label_in(x, x, bottom_start, component.max_y) {
+            covered += 1;
+        }
+    }
+
+    // 左右两条边
+    for y in component.min_y..=component.max_y {
+        let left_end = (component.min_x + band).min(component.max_x);
+        let right_start = component.max_x.saturating_sub(band).max(component.min_x);
+        if has_label_in(component.min_x, left_end, y, y) {
+            covered += 1;
+        }
+        if has_label_in(right_start, component.max_x, y, y) {
+            covered += 1;
+        }
+    }
+
+    let perimeter =
+        2 * (component.max_x - component.min_x + 1) + 2 * (component.max_y - component.min_y + 1);
+    covered as f32 / perimeter as f32
+}
+
+// 从 RGBA 截图中检测可能的矩形区域，结果按面积从小到大排列
+pub fn detect_regions(data: &[u8], width: u32, height: u32) -> Vec<DetectedRect> {
+    if width == 0 || height == 0 || data.len() < (width * height * 4) as usize {
+        return Vec::new();
+    }
+
+    let luma = luminance(data, width, height);
+    let edges = edge_map(&luma, width, height);
+    let (labels, components) = label_components(&edges, width, height);
+
+    let mut regions: Vec<DetectedRect> = Vec::new();
+    for (index, component) in components.iter().enumerate() {
+        let region_width = component.max_x - component.min_x + 1;
+        let region_height = component.max_y - component.min_y + 1;
+        if region_width < MIN_REGION_SIZE || region_height < MIN_REGION_SIZE {
+            continue;
+        }
+
+        let label = index as u32 + 1;
+        if border_coverage(&labels, width, label, component) < MIN_BORDER_COVERAGE {
+            continue;
+        }
+
+        push_unique(
+            &mut regions,
+            DetectedRect {
+                x: component.min_x as f32,
+                y: component.min_y as f32,
+                width: region_width as f32,
+                height: region_height as f32,
+            },
+        );
+    }
+
+    sort_by_area(&mut regions);
+    regions
+}
+
+// 合并其它来源（例如窗口树）的候选区域，去掉重复项
+pub fn merge_regions(regions: &mut Vec<DetectedRect>, extra: Vec<DetectedRect>) {
+    for region in extra {
+        push_unique(regions, region);
+    }
+    sort_by_area(regions);
+}
+
+// 查找包含指定点的最小区域
+pub fn region_at(regions: &[DetectedRect], x: f32, y: f32) -> Option<DetectedRect> {
+    regions
+        .iter()
+        .filter(|region| region.contains(x, y))
+        .min_by(|a, b| a.area().total_cmp(&b.area()))
+        .copied()
+}
+
+fn push_unique(regions: &mut Vec<DetectedRect>, region: DetectedRect) {
+    if region.width <= 0.0 || region.height <= 0.0 {
+        return;
+    }
+    if !regions.iter().any(|existing| existing.is_close_to(&region)) {
+        regions.push(region);
+    }
+}
+
+fn sort_by_area(regions: &mut [DetectedRect]) {
+    regions.sort_by(|a, b| a.area().total_cmp(&b.area()));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: [u8; 4] = [220, 220, 220, 255];
+
+    fn canvas(width: u32, height: u32) -> Vec<u8> {
+        BACKGROUND.repeat((width * height) as usize)
+    }
+
+    fn fill(data: &mut [u8], width: u32, (x, y, w, h): (u32, u32, u32, u32), color: [u8; 4]) {
+        for row in y..y + h {
+            for col in x..x + w {
+                let index = ((row * width + col) * 4) as usize;
+                data[index..index + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    // 检测结果与期望的矩形相差不超过 2 像素（边缘像素可能落在边界的任意一侧）
+    fn has_region(regions: &[DetectedRect], (x, y, w, h): (f32, f32, f32, f32)) -> bool {
+        regions.iter().any(|region| {
+            (region.x - x).abs() <= 2.0
+                && (region.y - y).abs() <= 2.0
+                && (region.width - w).abs() <= 2.0
+                && (region.height - h).abs() <= 2.0
+        })
+    }
+
+    #[test]
+    fn detects_solid_window_on_background() {
+        let (width, height) = (200, 150);
+        let mut data = canvas(width, height);
+        fill(&mut data, width, (40, 30, 100, 80), [40, 40, 40, 255]);
+
+        let regions = detect_regions(&data, width, height);
+        assert!(
+            has_region(&regions, (40.0, 30.0, 100.0, 80.0)),
+            "{:?}",
+            regions
+        );
+    }
+
+    #[test]
+    fn ignores_regions_smaller_than_minimum() {
+        let (width, height) = (100, 100);
+        let mut data = canvas(width, height);
+        fill(&mut data, width, (10, 10, 8, 8), [0, 0, 0, 255]);
+
+        assert!(detect_regions(&data, width, height).is_empty());
+    }
+
+    #[test]
+    fn detects_nested_regions_sorted_by_area() {
+        let (width, height) = (240, 200);
+        let mut data = canvas(width, height);
+        fill(&mut data, width, (20, 20, 200, 160), [40, 40, 40, 255]);
+        fill(&mut data, width, (60, 60, 60, 30), [200, 200, 90, 255]);
+
+        let regions = detect_regions(&data, width, height);
+        assert!(has_region(&regions, (20.0, 20.0, 200.0, 160.0)));
+        assert!(has_region(&regions, (60.0, 60.0, 60.0, 30.0)));
+        assert!(
+            regions
+                .windows(2)
+                .all(|pair| pair[0].area() <= pair[1].area())
+        );
+    }
+
+    #[test]
+    fn region_at_returns_smallest_containing_region() {
+        let outer = DetectedRect {
+            x: 0.0,
+            y: 0.0,
+            width: 200.0,
+            height: 100.0,
+        };
+        let inner = DetectedRect {
+            x: 50.0,
+            y: 20.0,
+            width: 40.0,
+            height: 30.0,
+        };
+        let regions = vec![outer, inner];
+
+        assert_eq!(region_at(&regions, 60.0, 30.0), Some(inner));
+        assert_eq!(region_at(&regions, 150.0, 80.0), Some(outer));
+        assert_eq!(region_at(&regions, 250.0, 80.0), None);
+    }
+
+    #[test]
+    fn merge_regions_skips_duplicates_and_keeps_order() {
+        let large = DetectedRect {
+            x: 0.0,
+            y: 0.0,
+            width: 300.0,
+            height: 200.0,
+        };
+        let small = DetectedRect {
+            x: 10.0,
+            y: 10.0,
+            width: 50.0,
+            height: 50.0,
+        };
+        let mut regions = vec![large];
+        let nearly_same = DetectedRect {
+            x: 1.0,
+            width: 301.0,
+            ..large
+        };
+        merge_regions(&mut regions, vec![nearly_same, small]);
+
+        assert_eq!(regions, vec![small, large]);
+    }
+
+    #[test]
+    fn luminance_weights_channels() {
+        let data = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255];
+        assert_eq!(luminance(&data, 3, 1), vec![76, 149, 29]);
+    }
+}
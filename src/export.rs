@@ -0,0 +1,136 @@
+// 导出图像文件
+
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 把 RGBA 数据保存为 PNG
+pub fn save_png(path: &Path, data: &[u8], width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+    write_png(path, data, width, height, None)
+}
+
+// 保存 PNG 并附带一个 iTXt 文本块
+pub fn save_png_with_text(
+    path: &Path,
+    data: &[u8],
+    (width, height): (u32, u32),
+    keyword: &str,
+    text: &str,
+) -> Result<(), Box<dyn Error>> {
+    write_png(path, data, width, height, Some((keyword, text)))
+}
+
+fn write_png(
+    path: &Path,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    text: Option<(&str, &str)>,
+) -> Result<(), Box<dyn Error>> {
+    encode_png(
+        BufWriter::new(File::create(path)?),
+        data,
+        (width, height),
+        text,
+    )
+}
+
+// 编码到内存，用于嵌入其他格式
+pub fn png_bytes(data: &[u8], width: u32, height: u32) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    encode_png(&mut bytes, data, (width, height), None)?;
+    Ok(bytes)
+}
+
+fn encode_png<W: Write>(
+    output: W,
+    data: &[u8],
+    (width, height): (u32, u32),
+    text: Option<(&str, &str)>,
+) -> Result<(), Box<dyn Error>> {
+    let mut encoder = png::Encoder::new(output, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    if let Some((keyword, text)) = text {
+        encoder.add_itxt_chunk(keyword.to_string(), text.to_string())?;
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+    writer.finish()?;
+    Ok(())
+}
+
+// 读取 PNG 中指定关键字的 iTXt 文本块，没有时返回 None
+pub fn read_png_text(path: &Path, keyword: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+    let reader = decoder.read_info()?;
+    for chunk in &reader.info().utf8_text {
+        if chunk.keyword == keyword {
+            return Ok(Some(chunk.get_text()?));
+        }
+    }
+    Ok(None)
+}
+
+// 截图默认保存位置：系统图片目录下以时间戳命名，extension 为文件扩展名
+pub fn screenshot_path(extension: &str) -> PathBuf {
+    let dir = dirs::picture_dir()
+        .or_else(dirs::home_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+    screenshot_path_in(&dir, extension)
+}
+
+// 与 screenshot_path 相同，但保存到指定目录
+pub fn screenshot_path_in(dir: &Path, extension: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    unique_path(dir, timestamp, extension)
+}
+
+// 同一秒内的多张截图加上序号区分，跳过已存在的文件，避免互相覆盖
+fn unique_path(dir: &Path, timestamp: u64, extension: &str) -> PathBuf {
+    // 上一次分配的 (时间戳, 序号)，文件还没有写入时也不会重复分配
+    static LAST_NAME: Mutex<(u64, u32)> = Mutex::new((0, 0));
+    let mut last = LAST_NAME.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut index = if last.0 == timestamp { last.1 + 1 } else { 0 };
+    loop {
+        let name = if index == 0 {
+            format!("screenshot-{}.{}", timestamp, extension)
+        } else {
+            format!("screenshot-{}-{}.{}", timestamp, index, extension)
+        };
+        let path = dir.join(name);
+        if !path.exists() {
+            *last = (timestamp, index);
+            return path;
+        }
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_in_the_same_second_are_unique() {
+        let dir = std::env::temp_dir().join(format!("export-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let timestamp = 1_000_000_001;
+        let first = unique_path(&dir, timestamp, "png");
+        let second = unique_path(&dir, timestamp, "png");
+        assert_eq!(first, dir.join("screenshot-1000000001.png"));
+        assert_eq!(second, dir.join("screenshot-1000000001-1.png"));
+
+        // 其他进程已经写入的文件也会跳过
+        std::fs::write(dir.join("screenshot-1000000002.png"), b"").unwrap();
+        let third = unique_path(&dir, 1_000_000_002, "png");
+        assert_eq!(third, dir.join("screenshot-1000000002-1.png"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
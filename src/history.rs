@@ -0,0 +1,461 @@
+// 截图历史
+//
+// 每次截图把原始图像保存为 PNG，并生成一张缩略图，index.json 记录时间、位置、尺寸、
+// 来源屏幕、标注和输出文件。标注单独保存，复制、钉图和重新编辑时再绘制到图像上。
+
+use crate::annotation::Annotation;
+use crate::config::HistoryConfig;
+use crate::pdf::PdfPage;
+use crate::{export, metadata, render};
+use chrono::{DateTime, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INDEX_FILE: &str = "index.json";
+const INDEX_VERSION: u32 = 1;
+// 缩略图的最大尺寸
+const THUMBNAIL_WIDTH: u32 = 160;
+const THUMBNAIL_HEIGHT: u32 = 120;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub id: String,
+    pub timestamp: u64, // Unix 时间（秒）
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub screen_id: u32,
+    // 原图内容的哈希，用于从导出的 PNG 找回原图
+    #[serde(default)]
+    pub hash: String,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[serde(default)]
+    pub outputs: Vec<PathBuf>,
+}
+
+impl HistoryEntry {
+    pub fn new(x: i32, y: i32, width: u32, height: u32, screen_id: u32) -> Self {
+        Self {
+            id: String::new(),
+            timestamp: now(),
+            x,
+            y,
+            width,
+            height,
+            screen_id,
+            hash: String::new(),
+            annotations: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    fn image_file(&self) -> String {
+        format!("{}.png", self.id)
+    }
+
+    fn thumbnail_file(&self) -> String {
+        format!("{}.thumb.png", self.id)
+    }
+
+    // "YYYY-MM-DD HH:MM"（本地时间），也用于按日期搜索
+    pub fn date_label(&self) -> String {
+        format_timestamp(self.timestamp)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    entries: Vec<HistoryEntry>,
+}
+
+pub struct History {
+    dir: PathBuf,
+    // 按时间从旧到新排列
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    // 打开历史目录；索引不存在或无法解析时从空历史开始
+    pub fn open(dir: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|text| serde_json::from_str::<Index>(&text).ok())
+            .map(|index| index.entries)
+            .unwrap_or_default();
+        Self { dir, entries }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&HistoryEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn find_by_hash(&self, hash: &str) -> Option<&HistoryEntry> {
+        self.entries.iter().rev().find(|entry| entry.hash == hash)
+    }
+
+    pub fn image_path(&self, entry: &HistoryEntry) -> PathBuf {
+        self.dir.join(entry.image_file())
+    }
+
+    pub fn thumbnail_path(&self, entry: &HistoryEntry) -> PathBuf {
+        self.dir.join(entry.thumbnail_file())
+    }
+
+    // 按日期搜索，结果从新到旧；query 为空时返回全部
+    pub fn search(&self, query: &str) -> Vec<&HistoryEntry> {
+        let query = query.trim();
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| query.is_empty() || entry.date_label().contains(query))
+            .collect()
+    }
+
+    // 历史文件占用的磁盘空间（字节）
+    pub fn disk_usage(&self) -> u64 {
+        self.entries
+            .iter()
+            .map(|entry| self.entry_size(entry))
+            .sum()
+    }
+
+    fn entry_size(&self, entry: &HistoryEntry) -> u64 {
+        [self.image_path(entry), self.thumbnail_path(entry)]
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    // 保存一次截图，返回分配的编号
+    pub fn add(&mut self, data: &[u8], mut entry: HistoryEntry) -> Result<String, Box<dyn Error>> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        // 同一秒内的多次截图加上序号区分
+        let base = entry.timestamp.to_string();
+        let mut id = base.clone();
+        let mut suffix = 1;
+        while self.get(&id).is_some() {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        entry.id = id.clone();
+        entry.hash = metadata::content_hash(data);
+
+        export::save_png(&self.image_path(&entry), data, entry.width, entry.height)?;
+        let image = image::RgbaImage::from_raw(entry.width, entry.height, data.to_vec())
+            .ok_or("图像数据与尺寸不符")?;
+        let scale = (THUMBNAIL_WIDTH as f32 / entry.width as f32)
+            .min(THUMBNAIL_HEIGHT as f32 / entry.height as f32)
+            .min(1.0);
+        let thumbnail = image::imageops::thumbnail(
+            &image,
+            ((entry.width as f32 * scale) as u32).max(1),
+            ((entry.height as f32 * scale) as u32).max(1),
+        );
+        export::save_png(
+            &self.thumbnail_path(&entry),
+            thumbnail.as_raw(),
+            thumbnail.width(),
+            thumbnail.height(),
+        )?;
+
+        self.entries.push(entry);
+        self.save_index()?;
+        Ok(id)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<(), Box<dyn Error>> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.id == id)
+            .ok_or_else(|| format!("没有编号为 {} 的历史记录", id))?;
+        let entry = self.entries.remove(index);
+        self.delete_files(&entry);
+        self.save_index()?;
+        Ok(())
+    }
+
+    // 按数量、时间和磁盘占用上限删除最旧的记录，返回删除的数量
+    pub fn apply_retention(&mut self, config: &HistoryConfig) -> Result<usize, Box<dyn Error>> {
+        let now = now();
+        let max_age = config.max_age_days * 86400;
+        let max_bytes = config.max_disk_mb * 1024 * 1024;
+        let mut total = self.disk_usage();
+        let mut removed = 0;
+
+        while let Some(oldest) = self.entries.first() {
+            let too_many = config.max_count > 0 && self.entries.len() > config.max_count;
+            let too_old = max_age > 0 && now.saturating_sub(oldest.timestamp) > max_age;
+            let too_large = max_bytes > 0 && total > max_bytes;
+            if !(too_many || too_old || too_large) {
+                break;
+            }
+            let entry = self.entries.remove(0);
+            total = total.saturating_sub(self.entry_size(&entry));
+            self.delete_files(&entry);
+            removed += 1;
+        }
+        if removed > 0 {
+            self.save_index()?;
+        }
+        Ok(removed)
+    }
+
+    // 读取没有绘制标注的原图
+    pub fn load_original(&self, entry: &HistoryEntry) -> Result<Vec<u8>, Box<dyn Error>> {
+        let image = image::open(self.image_path(entry))?.into_rgba8();
+        if image.dimensions() != (entry.width, entry.height) {
+            return Err(format!("历史记录 {} 的图像尺寸不符", entry.id).into());
+        }
+        Ok(image.into_raw())
+    }
+
+    // 读取截图并绘制标注
+    pub fn load_image(&self, entry: &HistoryEntry) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut data = self.load_original(entry)?;
+        render::draw_annotations(
+            &mut data,
+            entry.width,
+            entry.height,
+            entry.x as f32,
+            entry.y as f32,
+            &entry.annotations,
+        );
+        Ok(data)
+    }
+
+    // 导出 PDF 用的页面，标注保留为矢量，时间取截图时间
+    pub fn pdf_page(&self, entry: &HistoryEntry) -> Result<PdfPage, Box<dyn Error>> {
+        Ok(PdfPage {
+            data: self.load_original(entry)?,
+            width: entry.width,
+            height: entry.height,
+            origin: (entry.x as f32, entry.y as f32),
+            annotations: entry.annotations.clone(),
+            title: None,
+            timestamp: Some(entry.date_label()),
+            caption: None,
+        })
+    }
+
+    fn delete_files(&self, entry: &HistoryEntry) {
+        let _ = std::fs::remove_file(self.image_path(entry));
+        let _ = std::fs::remove_file(self.thumbnail_path(entry));
+    }
+
+    // 先写临时文件再改名，避免写到一半时索引损坏
+    fn save_index(&self) -> std::io::Result<()> {
+        let index = Index {
+            version: INDEX_VERSION,
+            entries: self.entries.clone(),
+        };
+        let temp = self.dir.join(format!("{}.tmp", INDEX_FILE));
+        std::fs::write(&temp, serde_json::to_string_pretty(&index)?)?;
+        std::fs::rename(&temp, self.dir.join(INDEX_FILE))
+    }
+}
+
+// 按配置打开历史目录，未启用时返回 None
+pub fn open_configured(config: &HistoryConfig) -> Option<History> {
+    if !config.enabled {
+        return None;
+    }
+    config.dir().map(History::open)
+}
+
+// 把 Unix 时间格式化为本地时间 "YYYY-MM-DD HH:MM"
+pub fn format_timestamp(timestamp: u64) -> String {
+    local_time(timestamp).format("%Y-%m-%d %H:%M").to_string()
+}
+
+// 精确到秒并带时区的本地时间 "YYYY-MM-DD HH:MM:SS +08:00"，用于页脚
+pub fn format_timestamp_with_zone(timestamp: u64) -> String {
+    local_time(timestamp)
+        .format("%Y-%m-%d %H:%M:%S %:z")
+        .to_string()
+}
+
+fn local_time(timestamp: u64) -> DateTime<Local> {
+    Local
+        .timestamp_opt(timestamp as i64, 0)
+        .earliest()
+        .unwrap_or_default()
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+// 文件的修改时间（Unix 时间，秒），取不到时返回 None
+pub fn modified_time(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 86400;
+
+    fn temp_history(name: &str) -> History {
+        let dir =
+            std::env::temp_dir().join(format!("history-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        History::open(dir)
+    }
+
+    // 添加一条 2×2 的记录，timestamp 为截图时间
+    fn add(history: &mut History, timestamp: u64) -> String {
+        let mut entry = HistoryEntry::new(0, 0, 2, 2, 0);
+        entry.timestamp = timestamp;
+        let data = [timestamp as u8, 0, 0, 255].repeat(4);
+        history.add(&data, entry).unwrap()
+    }
+
+    fn ids(entries: &[&HistoryEntry]) -> Vec<String> {
+        entries.iter().map(|entry| entry.id.clone()).collect()
+    }
+
+    fn config(max_count: usize, max_age_days: u64, max_disk_mb: u64) -> HistoryConfig {
+        HistoryConfig {
+            enabled: true,
+            max_count,
+            max_age_days,
+            max_disk_mb,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn history_is_disabled_by_default() {
+        assert!(!HistoryConfig::default().enabled);
+        assert!(open_configured(&HistoryConfig::default()).is_none());
+    }
+
+    #[test]
+    fn ids_in_the_same_second_are_unique() {
+        let mut history = temp_history("ids");
+        let first = add(&mut history, 1_000);
+        let second = add(&mut history, 1_000);
+        assert_eq!(first, "1000");
+        assert_eq!(second, "1000-1");
+
+        // 重新打开后索引仍然有效
+        let reopened = History::open(history.dir.clone());
+        assert!(reopened.get(&second).is_some());
+        std::fs::remove_dir_all(&history.dir).unwrap();
+    }
+
+    #[test]
+    fn search_matches_local_dates_newest_first() {
+        let mut history = temp_history("search");
+        let now = now();
+        let old = add(&mut history, now - 40 * DAY);
+        let recent = add(&mut history, now - DAY);
+        let latest = add(&mut history, now);
+
+        assert_eq!(
+            ids(&history.search("")),
+            vec![latest.clone(), recent, old.clone()]
+        );
+        let old_date = format_timestamp(now - 40 * DAY)[..10].to_string();
+        assert_eq!(ids(&history.search(&old_date)), vec![old]);
+        let label = history.get(&latest).unwrap().date_label();
+        assert_eq!(label, format_timestamp(now));
+        assert!(
+            history
+                .search(&format!(" {} ", label))
+                .iter()
+                .any(|entry| entry.id == latest)
+        );
+        assert!(history.search("1999-01-01").is_empty());
+        std::fs::remove_dir_all(&history.dir).unwrap();
+    }
+
+    #[test]
+    fn timestamps_use_local_time() {
+        let timestamp = 1_700_000_000;
+        let local = Local.timestamp_opt(timestamp as i64, 0).unwrap();
+        assert_eq!(
+            format_timestamp(timestamp),
+            local.format("%Y-%m-%d %H:%M").to_string()
+        );
+        assert!(format_timestamp_with_zone(timestamp).ends_with(&local.format("%:z").to_string()));
+    }
+
+    #[test]
+    fn retention_removes_oldest_beyond_count() {
+        let mut history = temp_history("count");
+        let now = now();
+        let ids: Vec<String> = (0..5)
+            .map(|index| add(&mut history, now - 5 + index))
+            .collect();
+
+        assert_eq!(history.apply_retention(&config(3, 0, 0)).unwrap(), 2);
+        let remaining: Vec<String> = history
+            .entries
+            .iter()
+            .map(|entry| entry.id.clone())
+            .collect();
+        assert_eq!(remaining, ids[2..].to_vec());
+        // 删除的记录连同文件一起删除
+        assert!(!history.dir.join(format!("{}.png", ids[0])).exists());
+        assert!(history.dir.join(format!("{}.png", ids[2])).exists());
+        assert_eq!(History::open(history.dir.clone()).entries.len(), 3);
+        std::fs::remove_dir_all(&history.dir).unwrap();
+    }
+
+    #[test]
+    fn retention_removes_entries_older_than_max_age() {
+        let mut history = temp_history("age");
+        let now = now();
+        add(&mut history, now - 10 * DAY);
+        add(&mut history, now - 8 * DAY);
+        let kept = add(&mut history, now - DAY);
+
+        assert_eq!(history.apply_retention(&config(0, 7, 0)).unwrap(), 2);
+        assert_eq!(ids(&history.search("")), vec![kept]);
+        // 没有超出上限时什么都不删
+        assert_eq!(history.apply_retention(&config(0, 7, 0)).unwrap(), 0);
+        std::fs::remove_dir_all(&history.dir).unwrap();
+    }
+
+    #[test]
+    fn retention_keeps_disk_usage_under_limit() {
+        let mut history = temp_history("disk");
+        let now = now();
+        // 噪声图像几乎无法压缩，每条约 1.4 MB
+        let mut seed = 1u32;
+        let mut added = Vec::new();
+        for index in 0..3 {
+            let data: Vec<u8> = (0..600 * 600 * 4)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                    (seed >> 16) as u8
+                })
+                .collect();
+            let mut entry = HistoryEntry::new(0, 0, 600, 600, 0);
+            entry.timestamp = now - 3 + index;
+            added.push(history.add(&data, entry).unwrap());
+        }
+        assert!(history.disk_usage() > 3 * 1024 * 1024);
+
+        // 上限 0 表示不限制
+        assert_eq!(history.apply_retention(&config(0, 0, 0)).unwrap(), 0);
+        assert_eq!(history.apply_retention(&config(0, 0, 2)).unwrap(), 2);
+        assert_eq!(ids(&history.search("")), vec![added[2].clone()]);
+        assert!(history.disk_usage() <= 2 * 1024 * 1024);
+        std::fs::remove_dir_all(&history.dir).unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+// 截图历史窗口：缩略图列表，按日期搜索，重新复制、钉图、编辑和删除，把搜索结果导出为 PDF
+
+use crate::history::{self, History, HistoryEntry};
+use crate::{
+    HistoryItem, HistoryWindow, SelectionArea, clipboard, config, export, lifecycle, pdf, pin,
+    watermark,
+};
+use screenshots::Screen;
+use slint::{CloseRequestResponse, ComponentHandle, ModelRc, Timer, VecModel};
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
+use std::time::Duration;
+
+thread_local! {
+    // 同一时间只打开一个历史窗口
+    static WINDOW: RefCell<Option<HistoryWindow>> = const { RefCell::new(None) };
+}
+
+fn open_history() -> Result<History, Box<dyn Error>> {
+    history::open_configured(&config::current().history).ok_or_else(|| "截图历史未启用".into())
+}
+
+// 读取历史记录的图像（已绘制标注）
+fn load_entry(id: &str) -> Result<(HistoryEntry, Vec<u8>), Box<dyn Error>> {
+    let history = open_history()?;
+    let entry = history
+        .get(id)
+        .ok_or_else(|| format!("没有编号为 {} 的历史记录", id))?
+        .clone();
+    let data = history.load_image(&entry)?;
+    Ok((entry, data))
+}
+
+fn refresh(window: &HistoryWindow, query: &str) {
+    let history = match open_history() {
+        Ok(history) => history,
+        Err(err) => {
+            window.set_status_text(err.to_string().into());
+            return;
+        }
+    };
+
+    let entries = history.search(query);
+    let items: Vec<HistoryItem> = entries
+        .iter()
+        .map(|entry| {
+            let mut label = format!(
+                "{}\n{} × {}  屏幕 {}",
+                entry.date_label(),
+                entry.width,
+                entry.height,
+                entry.screen_id
+            );
+            if !entry.annotations.is_empty() {
+                label.push_str(&format!("\n{} 个标注", entry.annotations.len()));
+            }
+            for output in &entry.outputs {
+                label.push_str(&format!("\n{}", output.display()));
+            }
+            HistoryItem {
+                id: entry.id.clone().into(),
+                thumbnail: slint::Image::load_from_path(&history.thumbnail_path(entry))
+                    .unwrap_or_default(),
+                label: label.into(),
+            }
+        })
+        .collect();
+    window.set_items(ModelRc::new(VecModel::from(items)));
+    window.set_status_text(
+        format!(
+            "共 {} 条，占用 {:.1} MB",
+            entries.len(),
+            history.disk_usage() as f64 / 1024.0 / 1024.0
+        )
+        .into(),
+    );
+}
+
+// 导出历史截图时页脚记录原来的截图时间和屏幕
+fn capture_info(entry: &HistoryEntry) -> watermark::CaptureInfo {
+    watermark::CaptureInfo {
+        timestamp: entry.timestamp,
+        screen_id: Some(entry.screen_id),
+    }
+}
+
+fn copy_entry(id: &str) -> Result<(), Box<dyn Error>> {
+    let (entry, data) = load_entry(id)?;
+    let config = config::current();
+    let (data, width, height) = watermark::decorate(
+        data,
+        entry.width,
+        entry.height,
+        &config.watermark,
+        &config.footer,
+        &capture_info(&entry),
+    );
+    let mut image = clipboard::ClipboardImage::new(&data, width, height);
+    // 截图保存过时复制已有的文件路径
+    if let Some(path) = entry.outputs.iter().find(|path| path.exists()) {
+        image = image.with_path(path);
+    }
+    clipboard::copy(&image, &config::current().clipboard.history)?;
+    Ok(())
+}
+
+fn pin_entry(id: &str) -> Result<(), Box<dyn Error>> {
+    let (entry, data) = load_entry(id)?;
+    pin::open_pin(
+        data,
+        entry.width,
+        entry.height,
+        entry.x as f32,
+        entry.y as f32,
+        "",
+        capture_info(&entry),
+    )?;
+    Ok(())
+}
+
+// 以历史截图为背景重新打开标注界面，选区覆盖整张图
+fn edit_entry(id: &str) -> Result<(), Box<dyn Error>> {
+    // 标注恢复为可编辑的图形，背景用未绘制标注的原图
+    let history = open_history()?;
+    let entry = history
+        .get(id)
+        .ok_or_else(|| format!("没有编号为 {} 的历史记录", id))?
+        .clone();
+    let data = history.load_original(&entry)?;
+    let screens = Screen::all()?;
+    let screen = screens
+        .iter()
+        .find(|screen| screen.display_info.id == entry.screen_id)
+        .or(screens.first())
+        .copied()
+        .ok_or("没有找到屏幕")?;
+    let area = SelectionArea {
+        x: 0.0,
+        y: 0.0,
+        width: entry.width as f32,
+        height: entry.height as f32,
+    };
+    // 标注以屏幕坐标保存，选区移到了原点，标注也跟着平移
+    let annotations = entry
+        .annotations
+        .iter()
+        .map(|annotation| annotation.translated(-entry.x as f32, -entry.y as f32))
+        .collect();
+    crate::open_annotation_overlay(
+        screen,
+        data,
+        entry.width,
+        entry.height,
+        crate::InitialState {
+            selection: Some(area),
+            annotations,
+            captured_at: Some(entry.timestamp),
+        },
+        false,
+    )
+}
+
+fn delete_entry(id: &str) -> Result<(), Box<dyn Error>> {
+    open_history()?.remove(id)
+}
+
+// 把当前搜索结果按时间顺序导出到一个 PDF，每条记录一页
+fn export_results(query: &str) -> Result<std::path::PathBuf, Box<dyn Error>> {
+    let history = open_history()?;
+    let mut entries = history.search(query);
+    entries.reverse();
+    let pages = entries
+        .into_iter()
+        .map(|entry| history.pdf_page(entry))
+        .collect::<Result<Vec<_>, _>>()?;
+    let path = export::screenshot_path("pdf");
+    pdf::write_pdf(&path, &pages)?;
+    Ok(path)
+}
+
+// 列表项按钮的处理：执行操作后刷新列表，结果显示在状态栏
+fn item_handler(
+    window: &HistoryWindow,
+    query: &Rc<RefCell<String>>,
+    action: fn(&str) -> Result<(), Box<dyn Error>>,
+    message: &'static str,
+) -> impl Fn(slint::SharedString) + 'static {
+    let window_weak = window.as_weak();
+    let query = query.clone();
+    move |id| {
+        let Some(window) = window_weak.upgrade() else {
+            return;
+        };
+        match action(&id) {
+            Ok(()) => {
+                refresh(&window, &query.borrow());
+                window.set_status_text(message.into());
+            }
+            Err(err) => window.set_status_text(format!("操作失败: {}", err).into()),
+        }
+    }
+}
+
+// 打开历史窗口；已经打开时刷新列表并显示到前面
+pub fn show() -> Result<(), Box<dyn Error>> {
+    let existing = WINDOW.with(|window| window.borrow().as_ref().map(|w| w.clone_strong()));
+    if let Some(window) = existing {
+        refresh(&window, "");
+        window.show()?;
+        return Ok(());
+    }
+
+    // 打开时顺便执行一次保留策略
+    let config = config::current().history;
+    let mut history = history::open_configured(&config).ok_or("截图历史未启用")?;
+    history.apply_retention(&config)?;
+
+    let window = HistoryWindow::new()?;
+    let query = Rc::new(RefCell::new(String::new()));
+    refresh(&window, "");
+
+    let window_weak = window.as_weak();
+    let query_clone = query.clone();
+    window.on_search(move |text| {
+        if let Some(window) = window_weak.upgrade() {
+            *query_clone.borrow_mut() = text.to_string();
+            refresh(&window, &text);
+        }
+    });
+
+    window.on_copy_item(item_handler(&window, &query, copy_entry, "已复制到剪贴板"));
+    window.on_pin_item(item_handler(&window, &query, pin_entry, "已钉到桌面"));
+    window.on_edit_item(item_handler(&window, &query, edit_entry, "已打开标注界面"));
+    window.on_delete_item(item_handler(&window, &query, delete_entry, "已删除"));
+
+    let window_weak = window.as_weak();
+    let query_clone = query.clone();
+    window.on_export_pdf(move || {
+        let Some(window) = window_weak.upgrade() else {
+            return;
+        };
+        let status = match export_results(&query_clone.borrow()) {
+            Ok(path) => format!("已导出: {}", path.display()),
+            Err(err) => format!("导出失败: {}", err),
+        };
+        window.set_status_text(status.into());
+    });
+
+    // 关闭时先隐藏，再在下一轮事件循环中释放窗口
+    window.window().on_close_requested(|| {
+        Timer::single_shot(Duration::ZERO, || {
+            WINDOW.with(|window| window.borrow_mut().take());
+            lifecycle::capture_ended();
+        });
+        CloseRequestResponse::HideWindow
+    });
+
+    window.show()?;
+    lifecycle::capture_started();
+    WINDOW.with(|slot| *slot.borrow_mut() = Some(window));
+    Ok(())
+}
@@ -0,0 +1,272 @@
+// 用户钩子：在截图流程的各个阶段执行配置的 shell 命令
+//
+// 事件：before-capture（截屏之前）、after-capture（截图完成、输出之前）、
+// after-save（截图保存为文件之后）、on-cancel（取消截图）。命令通过 sh -c 执行，
+// 截图信息放在环境变量中，有图像时 PNG 从标准输入传入。除 on-cancel 外，
+// 命令以非 0 状态退出或超时时中止后续步骤：before-capture 取消本次截图，
+// after-capture 和 after-save 跳过之后的输出。before-capture 在界面线程上执行，
+// 超时限制保证慢的钩子不会让程序一直无响应。
+
+use crate::config::HooksConfig;
+use screenshots::Screen;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+pub const BEFORE_CAPTURE: &str = "before-capture";
+pub const AFTER_CAPTURE: &str = "after-capture";
+pub const AFTER_SAVE: &str = "after-save";
+pub const ON_CANCEL: &str = "on-cancel";
+// 等待命令结束时检查的间隔
+const WAIT_INTERVAL: Duration = Duration::from_millis(20);
+
+// 传给钩子的截图信息，没有的项不设置对应的环境变量
+#[derive(Default)]
+pub struct HookContext<'a> {
+    pub path: Option<&'a Path>,
+    pub geometry: Option<(i32, i32, u32, u32)>, // x, y, 宽, 高
+    pub screen_id: Option<u32>,
+    pub png: Option<&'a [u8]>,
+}
+
+pub fn commands<'a>(config: &'a HooksConfig, event: &str) -> &'a [String] {
+    match event {
+        BEFORE_CAPTURE => &config.before_capture,
+        AFTER_CAPTURE => &config.after_capture,
+        AFTER_SAVE => &config.after_save,
+        ON_CANCEL => &config.on_cancel,
+        _ => &[],
+    }
+}
+
+// 依次执行事件对应的命令。某个命令失败或超时时不再执行后面的命令，返回错误说明
+pub fn run(config: &HooksConfig, event: &str, context: &HookContext) -> Result<(), String> {
+    let commands = commands(config, event);
+    if commands.is_empty() {
+        return Ok(());
+    }
+
+    // 屏幕序号与 Screen::all 的顺序一致
+    let screen_index = context.screen_id.and_then(|id| {
+        Screen::all()
+            .ok()?
+            .iter()
+            .position(|screen| screen.display_info.id == id)
+    });
+    let timeout = (config.timeout_secs > 0).then(|| Duration::from_secs(config.timeout_secs));
+
+    for command in commands {
+        let mut child = build_command(command, event, context, screen_index)
+            .spawn()
+            .map_err(|err| format!("无法执行钩子 {}: {}", command, err))?;
+        // PNG 较大时写入会阻塞到钩子读完为止，放到单独的线程，超时仍然有效
+        if let Some(mut stdin) = child.stdin.take() {
+            let png = context.png.unwrap_or_default().to_vec();
+            std::thread::spawn(move || {
+                // 钩子不读取标准输入时写入会失败，忽略即可
+                let _ = stdin.write_all(&png);
+            });
+        }
+        let status = wait(&mut child, timeout)
+            .map_err(|err| format!("钩子 {} 执行失败: {}", command, err))?;
+        match status {
+            Some(status) if status.success() => {}
+            Some(status) => return Err(format!("{} 钩子 {} 退出码 {}", event, command, status)),
+            None => {
+                return Err(format!(
+                    "{} 钩子 {} 超过 {} 秒没有结束",
+                    event, command, config.timeout_secs
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// 命令通过 sh -c 执行，截图信息放在环境变量中
+fn build_command(
+    command: &str,
+    event: &str,
+    context: &HookContext,
+    screen_index: Option<usize>,
+) -> Command {
+    let mut process = Command::new("sh");
+    process
+        .arg("-c")
+        .arg(command)
+        .env("SCREENSHOT_EVENT", event)
+        .stdin(Stdio::piped());
+    if let Some(path) = context.path {
+        process.env("SCREENSHOT_PATH", path);
+    }
+    if let Some((x, y, width, height)) = context.geometry {
+        process
+            .env("SCREENSHOT_X", x.to_string())
+            .env("SCREENSHOT_Y", y.to_string())
+            .env("SCREENSHOT_WIDTH", width.to_string())
+            .env("SCREENSHOT_HEIGHT", height.to_string());
+    }
+    if let Some(id) = context.screen_id {
+        process.env("SCREENSHOT_SCREEN_ID", id.to_string());
+    }
+    if let Some(index) = screen_index {
+        process.env("SCREENSHOT_SCREEN", index.to_string());
+    }
+    process
+}
+
+// 等待命令结束；超过 timeout 时结束命令并返回 None
+fn wait(child: &mut Child, timeout: Option<Duration>) -> std::io::Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Ok(None);
+        }
+        std::thread::sleep(WAIT_INTERVAL);
+    }
+}
+
+// 取消截图时在后台执行 on-cancel 钩子，不等待结果
+pub fn run_on_cancel(config: &HooksConfig) {
+    if config.on_cancel.is_empty() {
+        return;
+    }
+    let config = config.clone();
+    std::thread::spawn(move || {
+        if let Err(err) = run(&config, ON_CANCEL, &HookContext::default()) {
+            println!("{}", err);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::ffi::OsStr;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("hooks-test-{}-{}", std::process::id(), name))
+    }
+
+    fn config(before_capture: &[&str]) -> HooksConfig {
+        HooksConfig {
+            before_capture: before_capture
+                .iter()
+                .map(|command| command.to_string())
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn commands_are_looked_up_by_event() {
+        let config = HooksConfig {
+            before_capture: vec!["a".to_string()],
+            after_capture: vec!["b".to_string()],
+            after_save: vec!["c".to_string()],
+            on_cancel: vec!["d".to_string(), "e".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(commands(&config, BEFORE_CAPTURE), ["a"]);
+        assert_eq!(commands(&config, AFTER_CAPTURE), ["b"]);
+        assert_eq!(commands(&config, AFTER_SAVE), ["c"]);
+        assert_eq!(commands(&config, ON_CANCEL), ["d", "e"]);
+        assert!(commands(&config, "after-upload").is_empty());
+    }
+
+    #[test]
+    fn environment_describes_capture() {
+        let path = Path::new("/tmp/shot.png");
+        let context = HookContext {
+            path: Some(path),
+            geometry: Some((-10, 20, 300, 200)),
+            screen_id: Some(42),
+            png: None,
+        };
+        let command = build_command("true", AFTER_SAVE, &context, Some(1));
+        let envs: HashMap<&OsStr, Option<&OsStr>> = command.get_envs().collect();
+        let env = |name: &str| envs.get(OsStr::new(name)).copied().flatten();
+        assert_eq!(env("SCREENSHOT_EVENT"), Some(OsStr::new("after-save")));
+        assert_eq!(env("SCREENSHOT_PATH"), Some(path.as_os_str()));
+        assert_eq!(env("SCREENSHOT_X"), Some(OsStr::new("-10")));
+        assert_eq!(env("SCREENSHOT_Y"), Some(OsStr::new("20")));
+        assert_eq!(env("SCREENSHOT_WIDTH"), Some(OsStr::new("300")));
+        assert_eq!(env("SCREENSHOT_HEIGHT"), Some(OsStr::new("200")));
+        assert_eq!(env("SCREENSHOT_SCREEN_ID"), Some(OsStr::new("42")));
+        assert_eq!(env("SCREENSHOT_SCREEN"), Some(OsStr::new("1")));
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, ["-c", "true"]);
+    }
+
+    #[test]
+    fn missing_information_is_not_set() {
+        let command = build_command("true", ON_CANCEL, &HookContext::default(), None);
+        let names: Vec<&OsStr> = command.get_envs().map(|(name, _)| name).collect();
+        assert_eq!(names, [OsStr::new("SCREENSHOT_EVENT")]);
+    }
+
+    #[test]
+    fn hooks_receive_environment_and_png() {
+        let output = temp_path("env");
+        let command = format!(
+            "printf '%s %s ' \"$SCREENSHOT_EVENT\" \"$SCREENSHOT_WIDTH\" > {0}; cat >> {0}",
+            output.display()
+        );
+        let context = HookContext {
+            geometry: Some((0, 0, 8, 6)),
+            png: Some(b"PNGDATA"),
+            ..Default::default()
+        };
+        run(&config(&[&command]), BEFORE_CAPTURE, &context).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "before-capture 8 PNGDATA"
+        );
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn failing_hook_aborts_remaining_commands() {
+        let marker = temp_path("marker");
+        let touch = format!("touch {}", marker.display());
+        let err = run(
+            &config(&["true", "exit 3", &touch]),
+            BEFORE_CAPTURE,
+            &HookContext::default(),
+        )
+        .unwrap_err();
+        assert!(err.contains("before-capture"));
+        assert!(!marker.exists());
+
+        run(
+            &config(&["true", &touch]),
+            BEFORE_CAPTURE,
+            &HookContext::default(),
+        )
+        .unwrap();
+        assert!(marker.exists());
+        std::fs::remove_file(&marker).unwrap();
+    }
+
+    #[test]
+    fn slow_hook_times_out() {
+        let config = HooksConfig {
+            timeout_secs: 1,
+            ..config(&["sleep 30"])
+        };
+        let started = Instant::now();
+        let err = run(&config, BEFORE_CAPTURE, &HookContext::default()).unwrap_err();
+        assert!(err.contains("没有结束"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
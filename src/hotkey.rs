@@ -0,0 +1,424 @@
+// 全局快捷键
+//
+// X11 下通过 GrabKey 在根窗口上抢占按键，快捷键已被其他程序占用时服务器返回 BadAccess，
+// 据此给出冲突提示。Wayland 不允许普通程序注册全局快捷键，只能在桌面环境中绑定命令行。
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HotkeyAction {
+    RegionCapture,
+    FullscreenCapture,
+    RepeatLastRegion,
+    PinClipboard,
+}
+
+impl HotkeyAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HotkeyAction::RegionCapture => "区域截图",
+            HotkeyAction::FullscreenCapture => "全屏截图",
+            HotkeyAction::RepeatLastRegion => "重复上次区域",
+            HotkeyAction::PinClipboard => "钉住剪贴板",
+        }
+    }
+
+    // 在桌面环境中绑定快捷键时使用的命令
+    pub fn command_line(&self) -> &'static str {
+        match self {
+            HotkeyAction::RegionCapture => "screenshot-slint capture",
+            HotkeyAction::FullscreenCapture => "screenshot-slint capture --fullscreen",
+            HotkeyAction::RepeatLastRegion => "screenshot-slint capture --repeat",
+            HotkeyAction::PinClipboard => "screenshot-slint pin --clipboard",
+        }
+    }
+}
+
+pub const ALL_ACTIONS: [HotkeyAction; 4] = [
+    HotkeyAction::RegionCapture,
+    HotkeyAction::FullscreenCapture,
+    HotkeyAction::RepeatLastRegion,
+    HotkeyAction::PinClipboard,
+];
+
+// 修饰键掩码，与 X11 的 ModMask 取值一致
+pub const MOD_SHIFT: u16 = 1 << 0;
+pub const MOD_CONTROL: u16 = 1 << 2;
+pub const MOD_ALT: u16 = 1 << 3;
+pub const MOD_SUPER: u16 = 1 << 6;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accelerator {
+    pub modifiers: u16,
+    pub keysym: u32,
+}
+
+// 按键名到 X11 keysym
+fn keysym_from_name(name: &str) -> Option<u32> {
+    let lower = name.to_ascii_lowercase();
+    // 字母和数字的 keysym 就是对应的 ASCII 码
+    let mut chars = lower.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next())
+        && (ch.is_ascii_lowercase() || ch.is_ascii_digit())
+    {
+        return Some(ch as u32);
+    }
+    if let Some(number) = lower
+        .strip_prefix('f')
+        .and_then(|n| n.parse::<u32>().ok())
+        .filter(|n| (1..=12).contains(n))
+    {
+        return Some(0xffbe + number - 1);
+    }
+    let keysym = match lower.as_str() {
+        "print" | "printscreen" | "prtsc" => 0xff61,
+        "space" => 0x0020,
+        "escape" | "esc" => 0xff1b,
+        "insert" => 0xff63,
+        "delete" => 0xffff,
+        "home" => 0xff50,
+        "end" => 0xff57,
+        "pageup" => 0xff55,
+        "pagedown" => 0xff56,
+        _ => return None,
+    };
+    Some(keysym)
+}
+
+// 解析 "Ctrl+Alt+A" 形式的快捷键
+pub fn parse_accelerator(text: &str) -> Result<Accelerator, String> {
+    let mut modifiers = 0;
+    let mut keysym = None;
+    for part in text.split('+').map(str::trim) {
+        match part.to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= MOD_CONTROL,
+            "alt" => modifiers |= MOD_ALT,
+            "shift" => modifiers |= MOD_SHIFT,
+            "super" | "win" | "meta" => modifiers |= MOD_SUPER,
+            _ => {
+                if keysym.is_some() {
+                    return Err(format!("快捷键 {} 包含多个按键", text));
+                }
+                keysym = Some(
+                    keysym_from_name(part)
+                        .ok_or_else(|| format!("快捷键 {} 中的按键 {} 无法识别", text, part))?,
+                );
+            }
+        }
+    }
+    let keysym = keysym.ok_or_else(|| format!("快捷键 {} 缺少按键", text))?;
+    Ok(Accelerator { modifiers, keysym })
+}
+
+pub fn is_wayland_session() -> bool {
+    std::env::var("XDG_SESSION_TYPE").is_ok_and(|session| session == "wayland")
+        || (std::env::var_os("WAYLAND_DISPLAY").is_some() && std::env::var_os("DISPLAY").is_none())
+}
+
+// Wayland 下的说明：把命令绑定到桌面环境的自定义快捷键
+pub fn wayland_hint() -> String {
+    let mut hint =
+        "Wayland 下无法注册全局快捷键，请在桌面环境的快捷键设置中绑定以下命令:\n".to_string();
+    for action in ALL_ACTIONS {
+        hint.push_str(&format!(
+            "  {:<8} {}\n",
+            action.label(),
+            action.command_line()
+        ));
+    }
+    hint.push_str("守护进程运行时这些命令会直接转发给它");
+    hint
+}
+
+// 抢占按键时忽略 NumLock（Mod2）和 CapsLock（Lock）的状态
+#[cfg(target_os = "linux")]
+const IGNORED_MODIFIERS: [u16; 4] = [0, 1 << 1, 1 << 4, (1 << 1) | (1 << 4)];
+
+// 正在运行的监听线程。停止时通过它撤销抢占的按键，并向 wake_window 发一条消息让线程退出
+#[cfg(target_os = "linux")]
+struct Listener {
+    conn: std::sync::Arc<x11rb::rust_connection::RustConnection>,
+    root: u32,
+    wake_window: u32,
+    grabs: Vec<(u8, u16)>, // (keycode, 修饰键)
+}
+
+#[cfg(target_os = "linux")]
+static LISTENER: std::sync::Mutex<Option<Listener>> = std::sync::Mutex::new(None);
+
+// 注册快捷键并在后台线程监听。按下快捷键时在事件循环线程上调用 handler。
+// 返回无法注册的快捷键及原因，其余快捷键照常生效
+#[cfg(target_os = "linux")]
+pub fn start(bindings: Vec<(HotkeyAction, String)>, handler: fn(HotkeyAction)) -> Vec<String> {
+    use std::sync::{Arc, mpsc};
+    use x11rb::connection::Connection;
+    use x11rb::errors::ReplyError;
+    use x11rb::protocol::ErrorKind;
+    use x11rb::protocol::Event;
+    use x11rb::protocol::xproto::{ConnectionExt, CreateWindowAux, GrabMode, ModMask, WindowClass};
+
+    if is_wayland_session() {
+        return vec![wayland_hint()];
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let Ok((conn, screen_num)) = x11rb::connect(None) else {
+            let _ = sender.send(vec!["无法连接到 X11 显示服务器".to_string()]);
+            return;
+        };
+        let conn = Arc::new(conn);
+        let setup = conn.setup();
+        let root = setup.roots[screen_num].root;
+        let min_keycode = setup.min_keycode;
+        let max_keycode = setup.max_keycode;
+
+        let mapping = conn
+            .get_keyboard_mapping(min_keycode, max_keycode - min_keycode + 1)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok());
+        let keycode_for = |keysym: u32| -> Option<u8> {
+            let mapping = mapping.as_ref()?;
+            let per_keycode = mapping.keysyms_per_keycode as usize;
+            mapping
+                .keysyms
+                .chunks(per_keycode.max(1))
+                .position(|syms| syms.contains(&keysym))
+                .map(|index| min_keycode + index as u8)
+        };
+
+        let mut errors = Vec::new();
+        let mut grabbed: Vec<(u8, u16, HotkeyAction)> = Vec::new();
+        for (action, text) in bindings {
+            let accelerator = match parse_accelerator(&text) {
+                Ok(accelerator) => accelerator,
+                Err(err) => {
+                    errors.push(format!("{}: {}", action.label(), err));
+                    continue;
+                }
+            };
+            let Some(keycode) = keycode_for(accelerator.keysym) else {
+                errors.push(format!(
+                    "{}: 键盘上没有快捷键 {} 的按键",
+                    action.label(),
+                    text
+                ));
+                continue;
+            };
+            if grabbed
+                .iter()
+                .any(|&(code, mods, _)| code == keycode && mods == accelerator.modifiers)
+            {
+                errors.push(format!(
+                    "{}: 快捷键 {} 与其他功能重复",
+                    action.label(),
+                    text
+                ));
+                continue;
+            }
+
+            let mut conflict = false;
+            for ignored in IGNORED_MODIFIERS {
+                let result = conn
+                    .grab_key(
+                        false,
+                        root,
+                        ModMask::from(accelerator.modifiers | ignored),
+                        keycode,
+                        GrabMode::ASYNC,
+                        GrabMode::ASYNC,
+                    )
+                    .map_err(ReplyError::from)
+                    .and_then(|cookie| cookie.check());
+                if matches!(&result, Err(ReplyError::X11Error(err)) if err.error_kind == ErrorKind::Access)
+                {
+                    conflict = true;
+                    break;
+                }
+            }
+            if conflict {
+                // 撤销已经抢占的组合，避免只在部分锁定键状态下生效
+                for ignored in IGNORED_MODIFIERS {
+                    let _ = conn.ungrab_key(
+                        keycode,
+                        root,
+                        ModMask::from(accelerator.modifiers | ignored),
+                    );
+                }
+                errors.push(format!(
+                    "{}: 快捷键 {} 已被其他程序占用",
+                    action.label(),
+                    text
+                ));
+                continue;
+            }
+            grabbed.push((keycode, accelerator.modifiers, action));
+        }
+
+        // 不映射的窗口，只用来接收停止监听的消息
+        let wake_window = conn.generate_id().ok();
+        if let Some(window) = wake_window {
+            let _ = conn.create_window(
+                0,
+                window,
+                root,
+                0,
+                0,
+                1,
+                1,
+                0,
+                WindowClass::INPUT_ONLY,
+                0,
+                &CreateWindowAux::new(),
+            );
+        }
+        let _ = conn.flush();
+        if let Some(wake_window) = wake_window {
+            *LISTENER.lock().unwrap_or_else(|err| err.into_inner()) = Some(Listener {
+                conn: conn.clone(),
+                root,
+                wake_window,
+                grabs: grabbed
+                    .iter()
+                    .map(|&(keycode, modifiers, _)| (keycode, modifiers))
+                    .collect(),
+            });
+        }
+        let _ = sender.send(errors);
+
+        let relevant_modifiers = MOD_SHIFT | MOD_CONTROL | MOD_ALT | MOD_SUPER;
+        while let Ok(event) = conn.wait_for_event() {
+            if let Event::ClientMessage(message) = &event
+                && Some(message.window) == wake_window
+            {
+                break;
+            }
+            let Event::KeyPress(press) = event else {
+                continue;
+            };
+            let state = u16::from(press.state) & relevant_modifiers;
+            if let Some(&(_, _, action)) = grabbed
+                .iter()
+                .find(|&&(code, mods, _)| code == press.detail && mods == state)
+            {
+                let _ = slint::invoke_from_event_loop(move || handler(action));
+            }
+        }
+    });
+
+    receiver
+        .recv()
+        .unwrap_or_else(|_| vec!["快捷键线程意外退出".to_string()])
+}
+
+// 停止监听并撤销抢占的按键，之后可以用新的配置重新调用 start
+#[cfg(target_os = "linux")]
+pub fn stop() {
+    use x11rb::protocol::xproto::{ClientMessageEvent, ConnectionExt, EventMask, ModMask};
+
+    let Some(listener) = LISTENER
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .take()
+    else {
+        return;
+    };
+    let conn = &listener.conn;
+    for &(keycode, modifiers) in &listener.grabs {
+        for ignored in IGNORED_MODIFIERS {
+            let _ = conn.ungrab_key(keycode, listener.root, ModMask::from(modifiers | ignored));
+        }
+    }
+    // 事件掩码为空时事件发给创建窗口的连接，也就是监听线程自己
+    let message = ClientMessageEvent::new(32, listener.wake_window, 0u32, [0u32; 5]);
+    let _ = conn.send_event(false, listener.wake_window, EventMask::NO_EVENT, message);
+    // 等服务器处理完撤销请求，否则紧接着在新连接上抢占同一按键可能被判为冲突
+    let _ = conn.get_input_focus().map(|cookie| cookie.reply());
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn stop() {}
+
+#[cfg(not(target_os = "linux"))]
+pub fn start(_bindings: Vec<(HotkeyAction, String)>, _handler: fn(HotkeyAction)) -> Vec<String> {
+    vec!["当前平台暂不支持全局快捷键".to_string()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accelerator(modifiers: u16, keysym: u32) -> Result<Accelerator, String> {
+        Ok(Accelerator { modifiers, keysym })
+    }
+
+    #[test]
+    fn modifier_aliases() {
+        let expected = accelerator(MOD_CONTROL | MOD_ALT | MOD_SHIFT | MOD_SUPER, 'a' as u32);
+        assert_eq!(parse_accelerator("Ctrl+Alt+Shift+Super+A"), expected);
+        assert_eq!(
+            parse_accelerator("control + alt + SHIFT + win + a"),
+            expected
+        );
+        assert_eq!(parse_accelerator("Meta+Control+Shift+Alt+a"), expected);
+        // 重复的修饰键只算一次
+        assert_eq!(
+            parse_accelerator("Ctrl+Control+1"),
+            accelerator(MOD_CONTROL, '1' as u32)
+        );
+    }
+
+    #[test]
+    fn function_keys() {
+        assert_eq!(parse_accelerator("F1"), accelerator(0, 0xffbe));
+        assert_eq!(
+            parse_accelerator("shift+f12"),
+            accelerator(MOD_SHIFT, 0xffc9)
+        );
+        for number in 1..=12 {
+            assert_eq!(
+                keysym_from_name(&format!("F{}", number)),
+                Some(0xffbe + number - 1)
+            );
+        }
+        assert_eq!(keysym_from_name("F0"), None);
+        assert_eq!(keysym_from_name("F13"), None);
+    }
+
+    #[test]
+    fn named_keys() {
+        assert_eq!(parse_accelerator("Print"), accelerator(0, 0xff61));
+        assert_eq!(
+            parse_accelerator("Ctrl+PrtSc"),
+            accelerator(MOD_CONTROL, 0xff61)
+        );
+        assert_eq!(keysym_from_name("Esc"), keysym_from_name("escape"));
+        assert_eq!(keysym_from_name("Space"), Some(0x20));
+        assert_eq!(keysym_from_name("PageDown"), Some(0xff56));
+    }
+
+    #[test]
+    fn multiple_keys_are_rejected() {
+        let err = parse_accelerator("Ctrl+A+B").unwrap_err();
+        assert!(err.contains("多个按键"));
+        assert!(parse_accelerator("F1+F2").is_err());
+    }
+
+    #[test]
+    fn missing_key_is_rejected() {
+        for text in ["Ctrl+Shift", "Alt", ""] {
+            assert!(parse_accelerator(text).is_err(), "{}", text);
+        }
+        assert!(
+            parse_accelerator("Ctrl+Shift")
+                .unwrap_err()
+                .contains("缺少按键")
+        );
+    }
+
+    #[test]
+    fn unknown_key_is_rejected() {
+        for text in ["Ctrl+Foo", "Alt+ä", "Hyper+A", "Ctrl+AB"] {
+            let err = parse_accelerator(text).unwrap_err();
+            assert!(err.contains("无法识别"), "{}: {}", text, err);
+        }
+    }
+}
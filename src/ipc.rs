@@ -0,0 +1,423 @@
+// 守护进程的控制协议
+//
+// 客户端通过 Unix 域套接字连接守护进程，每条消息是一行 JSON：客户端发送一个 Request，
+// 守护进程回复一个 Response。协议的读写只依赖 Read / Write，可以直接用一对本地套接字驱动。
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    CaptureInteractive,
+    CaptureRegion {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+    CaptureFullscreen,
+    CaptureRepeat,
+    PinFile {
+        path: PathBuf,
+    },
+    PinClipboard,
+    ShowHistory,
+    OpenProject {
+        path: PathBuf,
+    },
+    OpenImage {
+        path: PathBuf,
+    },
+    ListPins,
+    // ids 为空时关闭全部钉图
+    ClosePins {
+        #[serde(default)]
+        ids: Vec<u32>,
+    },
+    ReloadConfig,
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinInfo {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pins: Vec<PinInfo>,
+}
+
+impl Response {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            ..Self::default()
+        }
+    }
+}
+
+// 写出一条消息（一行 JSON）
+pub fn write_message<W: Write, T: Serialize>(writer: &mut W, message: &T) -> io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+// 读取一条消息，对端关闭连接时返回 None
+pub fn read_message<R: BufRead, T: DeserializeOwned>(reader: &mut R) -> io::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let message = serde_json::from_str(line.trim_end())?;
+    Ok(Some(message))
+}
+
+// 处理一个连接上的所有请求，直到对端关闭。无法解析的请求回复错误后继续
+pub fn serve_connection<S: Read + Write>(
+    stream: S,
+    mut dispatch: impl FnMut(Request) -> Response,
+) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let response = match serde_json::from_str::<Request>(line.trim_end()) {
+            Ok(request) => dispatch(request),
+            Err(err) => Response::error(format!("无法解析请求: {}", err)),
+        };
+        write_message(reader.get_mut(), &response)?;
+    }
+}
+
+// 套接字路径：优先放在 XDG_RUNTIME_DIR，否则放在临时目录并带上用户名
+pub fn socket_path() -> PathBuf {
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("screenshot-slint.sock"),
+        None => {
+            let user = std::env::var("USER").unwrap_or_else(|_| "default".to_string());
+            std::env::temp_dir().join(format!("screenshot-slint-{}.sock", user))
+        }
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{send_request, start_server};
+
+#[cfg(unix)]
+mod unix {
+    use super::{Request, Response, read_message, serve_connection, write_message};
+    use std::io::{self, BufReader};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::Path;
+    use std::sync::mpsc;
+
+    // 向守护进程发送一个请求；没有守护进程在运行时返回连接错误
+    pub fn send_request(path: &Path, request: &Request) -> io::Result<Response> {
+        let mut stream = UnixStream::connect(path)?;
+        write_message(&mut stream, request)?;
+        let mut reader = BufReader::new(stream);
+        read_message(&mut reader)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "守护进程没有回复"))
+    }
+
+    // 在后台线程监听套接字。请求交给事件循环线程上的 handler 处理，处理完再把结果写回
+    pub fn start_server(path: &Path, handler: fn(Request) -> Response) -> io::Result<()> {
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("守护进程已在运行: {}", path.display()),
+                ));
+            }
+            // 上次异常退出留下的套接字文件
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                std::thread::spawn(move || {
+                    let _ = serve_connection(stream, |request| {
+                        let (sender, receiver) = mpsc::channel();
+                        let posted = slint::invoke_from_event_loop(move || {
+                            let _ = sender.send(handler(request));
+                        });
+                        if posted.is_err() {
+                            return Response::error("事件循环已退出");
+                        }
+                        receiver
+                            .recv()
+                            .unwrap_or_else(|_| Response::error("请求没有被处理"))
+                    });
+                });
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+pub fn send_request(_path: &std::path::Path, _request: &Request) -> io::Result<Response> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "当前平台不支持守护进程",
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn start_server(_path: &std::path::Path, _handler: fn(Request) -> Response) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "当前平台不支持守护进程",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn all_requests() -> Vec<Request> {
+        vec![
+            Request::CaptureInteractive,
+            Request::CaptureRegion {
+                x: -10,
+                y: 20,
+                width: 300,
+                height: 200,
+            },
+            Request::CaptureFullscreen,
+            Request::CaptureRepeat,
+            Request::PinFile {
+                path: PathBuf::from("/tmp/截图.png"),
+            },
+            Request::PinClipboard,
+            Request::ShowHistory,
+            Request::OpenProject {
+                path: PathBuf::from("/tmp/a.ssproj"),
+            },
+            Request::OpenImage {
+                path: PathBuf::from("/tmp/b.png"),
+            },
+            Request::ListPins,
+            Request::ClosePins { ids: vec![1, 3] },
+            Request::ReloadConfig,
+            Request::Quit,
+        ]
+    }
+
+    #[test]
+    fn every_request_round_trips_as_one_json_line() {
+        let mut buffer = Vec::new();
+        for request in all_requests() {
+            write_message(&mut buffer, &request).unwrap();
+        }
+        let text = String::from_utf8(buffer.clone()).unwrap();
+        assert_eq!(text.lines().count(), all_requests().len());
+
+        let mut reader = Cursor::new(buffer);
+        for expected in all_requests() {
+            let request: Request = read_message(&mut reader).unwrap().unwrap();
+            assert_eq!(request, expected);
+        }
+        assert_eq!(read_message::<_, Request>(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn request_json_format() {
+        let request: Request = serde_json::from_str(
+            r#"{"command":"capture_region","x":1,"y":2,"width":3,"height":4}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            request,
+            Request::CaptureRegion {
+                x: 1,
+                y: 2,
+                width: 3,
+                height: 4
+            }
+        );
+        // ids 可以省略，表示关闭全部钉图
+        let request: Request = serde_json::from_str(r#"{"command":"close_pins"}"#).unwrap();
+        assert_eq!(request, Request::ClosePins { ids: Vec::new() });
+    }
+
+    #[test]
+    fn response_round_trips() {
+        let responses = [
+            Response::ok(),
+            Response::error("出错了"),
+            Response {
+                pins: vec![PinInfo {
+                    id: 7,
+                    x: 1.0,
+                    y: 2.0,
+                    width: 30.0,
+                    height: 40.0,
+                }],
+                ..Response::ok()
+            },
+        ];
+        for response in responses {
+            let mut buffer = Vec::new();
+            write_message(&mut buffer, &response).unwrap();
+            let decoded: Response = read_message(&mut Cursor::new(buffer)).unwrap().unwrap();
+            assert_eq!(decoded, response);
+        }
+        // 没有错误和钉图时不输出这两个字段
+        assert_eq!(
+            serde_json::to_string(&Response::ok()).unwrap(),
+            r#"{"ok":true}"#
+        );
+    }
+
+    // 用内存中的输入输出模拟一个连接
+    struct FakeStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for FakeStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for FakeStream {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn malformed_request_gets_error_and_connection_continues() {
+        let input = b"not json\n{\"command\":\"unknown\"}\n{\"command\":\"list_pins\"}\n".to_vec();
+        let mut stream = FakeStream {
+            input: Cursor::new(input),
+            output: Vec::new(),
+        };
+        let mut handled = Vec::new();
+        serve_connection(&mut stream, |request| {
+            handled.push(request);
+            Response::ok()
+        })
+        .unwrap();
+
+        assert_eq!(handled, vec![Request::ListPins]);
+        let mut reader = Cursor::new(stream.output);
+        for _ in 0..2 {
+            let response: Response = read_message(&mut reader).unwrap().unwrap();
+            assert!(!response.ok);
+            assert!(response.error.unwrap().starts_with("无法解析请求"));
+        }
+        let response: Response = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(response, Response::ok());
+    }
+
+    #[cfg(unix)]
+    mod unix {
+        use super::super::*;
+        use std::os::unix::net::UnixListener;
+
+        fn temp_socket(name: &str) -> PathBuf {
+            let path = std::env::temp_dir().join(format!(
+                "screenshot-slint-test-{}-{}.sock",
+                std::process::id(),
+                name
+            ));
+            let _ = std::fs::remove_file(&path);
+            path
+        }
+
+        #[test]
+        fn client_and_server_exchange_over_socket() {
+            let path = temp_socket("exchange");
+            let listener = UnixListener::bind(&path).unwrap();
+            let server = std::thread::spawn(move || {
+                // 每个请求一个连接，处理两个连接后结束
+                for stream in listener.incoming().take(2) {
+                    serve_connection(stream.unwrap(), |request| match request {
+                        Request::ListPins => Response {
+                            pins: vec![PinInfo {
+                                id: 1,
+                                x: 0.0,
+                                y: 0.0,
+                                width: 10.0,
+                                height: 10.0,
+                            }],
+                            ..Response::ok()
+                        },
+                        other => Response::error(format!("{:?}", other)),
+                    })
+                    .unwrap();
+                }
+            });
+
+            let response = send_request(&path, &Request::ListPins).unwrap();
+            assert!(response.ok);
+            assert_eq!(response.pins.len(), 1);
+            let response = send_request(&path, &Request::Quit).unwrap();
+            assert_eq!(response.error.as_deref(), Some("Quit"));
+
+            server.join().unwrap();
+            let _ = std::fs::remove_file(&path);
+        }
+
+        #[test]
+        fn send_request_fails_without_server() {
+            let path = temp_socket("missing");
+            assert!(send_request(&path, &Request::Quit).is_err());
+        }
+
+        #[test]
+        fn start_server_refuses_running_daemon_and_replaces_stale_socket() {
+            fn handler(_: Request) -> Response {
+                Response::ok()
+            }
+
+            let path = temp_socket("server");
+            let listener = UnixListener::bind(&path).unwrap();
+            let err = start_server(&path, handler).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AddrInUse);
+
+            // 监听者退出后套接字文件还在，启动时应当替换它
+            drop(listener);
+            assert!(path.exists());
+            start_server(&path, handler).unwrap();
+            assert!(std::os::unix::net::UnixStream::connect(&path).is_ok());
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+// 进程生命周期
+//
+// 截图流程（遮罩、滚动截图、录制）或历史窗口打开时，或者还有钉图窗口打开时，事件循环保持运行；
+// 两者都结束后退出进程。守护进程模式下常驻，只有收到退出请求才结束。
+
+use crate::pin;
+use std::cell::Cell;
+
+thread_local! {
+    // 进行中的截图流程数量
+    static ACTIVE_CAPTURES: Cell<usize> = const { Cell::new(0) };
+    // 常驻模式（守护进程）
+    static RESIDENT: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set_resident(resident: bool) {
+    RESIDENT.with(|flag| flag.set(resident));
+}
+
+pub fn capture_started() {
+    ACTIVE_CAPTURES.with(|count| count.set(count.get() + 1));
+}
+
+pub fn capture_ended() {
+    ACTIVE_CAPTURES.with(|count| count.set(count.get().saturating_sub(1)));
+    quit_if_idle();
+}
+
+// 没有进行中的截图，也没有钉图
+pub fn is_idle() -> bool {
+    ACTIVE_CAPTURES.with(|count| count.get()) == 0 && pin::pin_count() == 0
+}
+
+pub fn quit_if_idle() {
+    if is_idle() && !RESIDENT.with(|flag| flag.get()) {
+        let _ = slint::quit_event_loop();
+    }
+}
@@ -1,15 +1,58 @@
 #![windows_subsystem = "windows"]
 
-use arboard::Clipboard;
+mod annotation;
+mod barcode;
+mod beautify;
+mod cli;
+mod clipboard;
+mod config;
+mod detect;
+mod export;
+mod history;
+mod history_window;
+mod hooks;
+mod hotkey;
+mod ipc;
+mod lifecycle;
+mod metadata;
+mod ocr;
+mod pdf;
+mod pin;
+mod pipeline;
+mod platform;
+mod preset;
+mod project;
+mod record_capture;
+mod recording;
+mod redact;
+mod render;
+mod scroll_capture;
+mod share;
+mod snap;
+mod stitch;
+mod svg;
+mod text;
+mod upload;
+mod watermark;
+
+use annotation::Annotation;
+use detect::DetectedRect;
+use preset::SelectionPreset;
 use screenshots::Screen;
-use slint::{LogicalPosition, ModelRc, VecModel};
+use slint::{LogicalPosition, Model, ModelRc, VecModel};
+use snap::Snapper;
 use std::cell::RefCell;
 use std::error::Error;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
 // 导入UI组件
 slint::include_modules!();
 
+// 标注对象的颜色和边框宽度，与界面中的 saved-object-color、object-border-width 一致
+const OBJECT_COLOR: &str = "#ff0044";
+const OBJECT_BORDER_WIDTH: f32 = 2.0;
+
 // 添加选区状态结构体
 #[derive(Debug, Clone)]
 struct SelectionState {
@@ -57,6 +100,19 @@ struct RustRectangleObject {
     end_x: f32,
     end_y: f32,
     selected: bool,
+    order: u64, // 创建顺序，导出和保存时按它排列标注
+}
+// 打码区域，accepted 为 false 时是还没有确认的建议，导出时不生效
+#[derive(Debug, Clone)]
+struct RustRedaction {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    block_size: f32,
+    label: String,
+    accepted: bool,
+    order: u64,
 }
 #[derive(Debug, Clone)]
 struct RustPenPoint {
@@ -78,6 +134,7 @@ struct RustPenPath {
     commands: String,
     // 添加缓存标志
     commands_cached: bool,
+    order: u64,
 }
 #[derive(Debug, Clone)]
 struct RustArrowObject {
@@ -86,6 +143,7 @@ struct RustArrowObject {
     end_x: f32,
     end_y: f32,
     selected: bool,
+    order: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -99,9 +157,10 @@ struct RustCircleObject {
     end_x: f32,
     end_y: f32,
     selected: bool,
+    order: u64,
 }
 impl RustPenPath {
-    fn new(points: Vec<RustPenPoint>, color: String, width: f32) -> Self {
+    fn new(points: Vec<RustPenPoint>, color: String, width: f32, order: u64) -> Self {
         let (min_x, max_x, min_y, max_y) = if points.is_empty() {
             (0.0, 0.0, 0.0, 0.0)
         } else {
@@ -149,6 +208,7 @@ impl RustPenPath {
             max_y,
             commands,
             commands_cached: true,
+            order,
         }
     }
     fn recalculate_bounds_and_commands(&mut self) {
@@ -259,7 +319,23 @@ struct AppState {
     is_drawing_pen: bool,
     pen_color: String,
     pen_width: f32,
-    pen_path_count: u32, // 简单计数
+    pen_path_count: u32,         // 简单计数
+    pen_paths: Vec<RustPenPath>, // 已完成的画笔路径，用于导出时绘制
+    // 自动识别的候选区域（窗口、面板、按钮等）
+    detected_regions: Vec<DetectedRect>,
+    hover_region: Option<DetectedRect>,
+    // 边缘吸附相关
+    snapper: Option<Snapper>,
+    snap_disabled: bool, // 按住 Alt 时临时关闭吸附
+    snap_guide_x: Option<f32>,
+    snap_guide_y: Option<f32>,
+    // 比例锁定与尺寸预设
+    shift_pressed: bool, // 按住 Shift 时锁定为正方形
+    selection_preset: SelectionPreset,
+    show_preset_menu: bool,
+    redactions: Vec<RustRedaction>,
+    last_order: u64, // 最近分配的标注创建顺序
+    shared: bool,    // 已经分享，剪贴板里是分享链接
 }
 
 impl Default for AppState {
@@ -305,11 +381,30 @@ impl Default for AppState {
             pen_color: "#ff0044".to_string(),
             pen_width: 3.0,
             pen_path_count: 0,
+            pen_paths: Vec::new(),
+            detected_regions: Vec::new(),
+            hover_region: None,
+            snapper: None,
+            snap_disabled: false,
+            snap_guide_x: None,
+            snap_guide_y: None,
+            shift_pressed: false,
+            selection_preset: SelectionPreset::Free,
+            show_preset_menu: false,
+            redactions: Vec::new(),
+            last_order: 0,
+            shared: false,
         }
     }
 }
 
 impl AppState {
+    // 为新建的标注分配创建顺序
+    fn next_order(&mut self) -> u64 {
+        self.last_order += 1;
+        self.last_order
+    }
+
     fn update_current_pen_path(&mut self) -> (f32, f32, f32, f32, String) {
         if self.current_pen_points.is_empty() {
             return (0.0, 0.0, 0.0, 0.0, String::new());
@@ -380,13 +475,16 @@ impl AppState {
     // 创建独立的画笔路径组件
     fn finish_pen_drawing(&mut self) -> Option<RustPenPath> {
         if !self.current_pen_points.is_empty() && self.current_pen_points.len() > 1 {
+            let order = self.next_order();
             let path = RustPenPath::new(
                 self.current_pen_points.clone(),
                 self.pen_color.clone(),
                 self.pen_width,
+                order,
             );
 
             self.pen_path_count += 1;
+            self.pen_paths.push(path.clone());
 
             // 清理当前绘制状态
             self.current_pen_points.clear();
@@ -411,6 +509,7 @@ impl AppState {
         } else if self.pen_path_count > 0 {
             // 标记需要移除最后一个路径组件
             self.pen_path_count -= 1;
+            self.pen_paths.pop();
             println!("撤销了路径，剩余: {}", self.pen_path_count);
         }
     }
@@ -1095,6 +1194,7 @@ impl AppState {
                         self.rectangle_objects.push(obj_to_save);
                     }
 
+                    let order = self.next_order();
                     self.current_rectangle_object = Some(RustRectangleObject {
                         x,
                         y,
@@ -1105,6 +1205,7 @@ impl AppState {
                         end_x: x,
                         end_y: y,
                         selected: true,
+                        order,
                     });
                     self.is_drawing_rectangle = true;
                 }
@@ -1116,6 +1217,7 @@ impl AppState {
                         self.circle_objects.push(obj_to_save);
                     }
 
+                    let order = self.next_order();
                     self.current_circle_object = Some(RustCircleObject {
                         x,
                         y,
@@ -1126,6 +1228,7 @@ impl AppState {
                         end_x: x,
                         end_y: y,
                         selected: true,
+                        order,
                     });
                     self.is_drawing_circle = true;
                 }
@@ -1137,12 +1240,14 @@ impl AppState {
                         self.arrow_objects.push(obj_to_save);
                     }
 
+                    let order = self.next_order();
                     self.current_arrow_object = Some(RustArrowObject {
                         start_x: x,
                         start_y: y,
                         end_x: x,
                         end_y: y,
                         selected: true,
+                        order,
                     });
                     self.is_drawing_arrow = true;
                 }
@@ -1233,17 +1338,54 @@ impl AppState {
         } else if self.selection.is_dragging {
             self.handle_drag(x, y, screen_width, screen_height);
         } else if self.selection.is_drawing {
-            self.selection.current_x = x;
-            self.selection.current_y = y;
+            let (corner_x, corner_y) = preset::constrain_corner(
+                self.selection.start_x,
+                self.selection.start_y,
+                x,
+                y,
+                self.active_selection_preset(),
+                (0.0, 0.0, screen_width, screen_height),
+            );
+            self.selection.current_x = corner_x;
+            self.selection.current_y = corner_y;
         }
 
         if !self.selection.is_resizing && !self.selection.is_dragging && !self.selection.is_drawing
         {
             self.selection.current_handle = self.get_handle_at_point(x, y);
         }
+
+        // 还没有选区时，高亮鼠标下方的候选区域
+        if self.selection.is_drawing {
+            // 拖动超过阈值后视为手动框选
+            let width = (self.selection.current_x - self.selection.start_x).abs();
+            let height = (self.selection.current_y - self.selection.start_y).abs();
+            if width >= 5.0 || height >= 5.0 {
+                self.hover_region = None;
+            }
+        } else if self.selection.is_selecting {
+            self.hover_region = None;
+        } else {
+            self.hover_region = detect::region_at(&self.detected_regions, x, y);
+        }
+    }
+
+    // 绘制图形时的可用范围：有选区时限制在选区内
+    fn drawing_bounds(&self) -> preset::Bounds {
+        if self.selection.is_selecting {
+            (
+                self.selection.start_x.min(self.selection.current_x),
+                self.selection.start_y.min(self.selection.current_y),
+                self.selection.start_x.max(self.selection.current_x),
+                self.selection.start_y.max(self.selection.current_y),
+            )
+        } else {
+            (f32::MIN, f32::MIN, f32::MAX, f32::MAX)
+        }
     }
 
     fn handle_rectangle_drawing(&mut self, x: f32, y: f32) {
+        let bounds = self.drawing_bounds();
         if let Some(ref mut rect_obj) = self.current_rectangle_object {
             let clamped_x = if self.selection.is_selecting {
                 let min_x = self.selection.start_x.min(self.selection.current_x);
@@ -1261,6 +1403,20 @@ impl AppState {
                 y
             };
 
+            // 按住 Shift 绘制正方形
+            let (clamped_x, clamped_y) = if self.shift_pressed {
+                preset::constrain_corner(
+                    rect_obj.start_x,
+                    rect_obj.start_y,
+                    clamped_x,
+                    clamped_y,
+                    SelectionPreset::Ratio(1.0, 1.0),
+                    bounds,
+                )
+            } else {
+                (clamped_x, clamped_y)
+            };
+
             let raw_width = clamped_x - rect_obj.start_x;
             let raw_height = clamped_y - rect_obj.start_y;
 
@@ -1294,6 +1450,7 @@ impl AppState {
 
     // 新增：处理圆形绘制
     fn handle_circle_drawing(&mut self, x: f32, y: f32) {
+        let bounds = self.drawing_bounds();
         if let Some(ref mut circle_obj) = self.current_circle_object {
             let clamped_x = if self.selection.is_selecting {
                 let min_x = self.selection.start_x.min(self.selection.current_x);
@@ -1311,6 +1468,20 @@ impl AppState {
                 y
             };
 
+            // 按住 Shift 绘制正圆
+            let (clamped_x, clamped_y) = if self.shift_pressed {
+                preset::constrain_corner(
+                    circle_obj.start_x,
+                    circle_obj.start_y,
+                    clamped_x,
+                    clamped_y,
+                    SelectionPreset::Ratio(1.0, 1.0),
+                    bounds,
+                )
+            } else {
+                (clamped_x, clamped_y)
+            };
+
             let raw_width = clamped_x - circle_obj.start_x;
             let raw_height = clamped_y - circle_obj.start_y;
 
@@ -1384,8 +1555,111 @@ impl AppState {
         }
     }
 
+    // 收集吸附参考线：其它图形的边缘和中心、选区边缘以及屏幕中线
+    fn snap_targets(
+        &self,
+        exclude: &str,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> (Vec<f32>, Vec<f32>) {
+        fn push_box(xs: &mut Vec<f32>, ys: &mut Vec<f32>, x: f32, y: f32, width: f32, height: f32) {
+            xs.extend([x, x + width / 2.0, x + width]);
+            ys.extend([y, y + height / 2.0, y + height]);
+        }
+
+        let mut xs = vec![screen_width / 2.0];
+        let mut ys = vec![screen_height / 2.0];
+
+        for obj in &self.rectangle_objects {
+            push_box(&mut xs, &mut ys, obj.x, obj.y, obj.width, obj.height);
+        }
+        for obj in &self.circle_objects {
+            push_box(&mut xs, &mut ys, obj.x, obj.y, obj.width, obj.height);
+        }
+        for obj in &self.arrow_objects {
+            xs.extend([obj.start_x, obj.end_x]);
+            ys.extend([obj.start_y, obj.end_y]);
+        }
+        if exclude != "rectangle"
+            && let Some(obj) = &self.current_rectangle_object
+        {
+            push_box(&mut xs, &mut ys, obj.x, obj.y, obj.width, obj.height);
+        }
+        if exclude != "circle"
+            && let Some(obj) = &self.current_circle_object
+        {
+            push_box(&mut xs, &mut ys, obj.x, obj.y, obj.width, obj.height);
+        }
+        if let Some(ref obj) = self.current_arrow_object {
+            xs.extend([obj.start_x, obj.end_x]);
+            ys.extend([obj.start_y, obj.end_y]);
+        }
+        if exclude != "selection" && self.selection.is_selecting {
+            let min_x = self.selection.start_x.min(self.selection.current_x);
+            let min_y = self.selection.start_y.min(self.selection.current_y);
+            let width = (self.selection.current_x - self.selection.start_x).abs();
+            let height = (self.selection.current_y - self.selection.start_y).abs();
+            push_box(&mut xs, &mut ys, min_x, min_y, width, height);
+        }
+
+        (xs, ys)
+    }
+
+    // 根据正在拖动的控制柄吸附鼠标位置，并记录需要显示的参考线
+    fn snap_handle_point(
+        &mut self,
+        handle: &str,
+        x: f32,
+        y: f32,
+        bounds: (f32, f32, f32, f32),
+        exclude: &str,
+        screen_size: (f32, f32),
+    ) -> (f32, f32) {
+        self.snap_guide_x = None;
+        self.snap_guide_y = None;
+        if self.snap_disabled {
+            return (x, y);
+        }
+        let Some(ref snapper) = self.snapper else {
+            return (x, y);
+        };
+
+        let (min_x, max_x, min_y, max_y) = bounds;
+        let (targets_x, targets_y) = self.snap_targets(exclude, screen_size.0, screen_size.1);
+
+        let mut guide_x = None;
+        let mut guide_y = None;
+        if handle.contains('w') || handle.contains('e') {
+            guide_x = snapper.snap_x(x, min_y, max_y, &targets_x);
+        }
+        if handle.contains('n') || handle.contains('s') {
+            guide_y = snapper.snap_y(y, min_x, max_x, &targets_y);
+        }
+
+        self.snap_guide_x = guide_x;
+        self.snap_guide_y = guide_y;
+        (guide_x.unwrap_or(x), guide_y.unwrap_or(y))
+    }
+
     // 新增：处理矩形调整大小
     fn handle_rectangle_resize(&mut self, x: f32, y: f32, screen_width: f32, screen_height: f32) {
+        let Some(bounds) = self
+            .current_rectangle_object
+            .as_ref()
+            .map(|obj| (obj.x, obj.x + obj.width, obj.y, obj.y + obj.height))
+        else {
+            return;
+        };
+        let mode = self.rectangle_resize_mode.clone();
+        let (x, y) = self.snap_handle_point(
+            &mode,
+            x,
+            y,
+            bounds,
+            "rectangle",
+            (screen_width, screen_height),
+        );
+
         if let Some(ref mut rect_obj) = self.current_rectangle_object {
             match self.rectangle_resize_mode.as_str() {
                 "nw" => {
@@ -1452,11 +1726,39 @@ impl AppState {
                 }
                 _ => {}
             }
+
+            // 按住 Shift 保持正方形
+            if self.shift_pressed {
+                let (new_x, new_y, new_width, new_height) = preset::constrain_box(
+                    rect_obj.x,
+                    rect_obj.y,
+                    rect_obj.width,
+                    rect_obj.height,
+                    &mode,
+                    1.0,
+                    (0.0, 0.0, screen_width, screen_height),
+                );
+                rect_obj.x = new_x;
+                rect_obj.y = new_y;
+                rect_obj.width = new_width;
+                rect_obj.height = new_height;
+            }
         }
     }
 
     // 新增：处理圆形调整大小
     fn handle_circle_resize(&mut self, x: f32, y: f32, screen_width: f32, screen_height: f32) {
+        let Some(bounds) = self
+            .current_circle_object
+            .as_ref()
+            .map(|obj| (obj.x, obj.x + obj.width, obj.y, obj.y + obj.height))
+        else {
+            return;
+        };
+        let mode = self.circle_resize_mode.clone();
+        let (x, y) =
+            self.snap_handle_point(&mode, x, y, bounds, "circle", (screen_width, screen_height));
+
         if let Some(ref mut circle_obj) = self.current_circle_object {
             match self.circle_resize_mode.as_str() {
                 "nw" => {
@@ -1523,12 +1825,109 @@ impl AppState {
                 }
                 _ => {}
             }
+
+            // 按住 Shift 保持正圆
+            if self.shift_pressed {
+                let (new_x, new_y, new_width, new_height) = preset::constrain_box(
+                    circle_obj.x,
+                    circle_obj.y,
+                    circle_obj.width,
+                    circle_obj.height,
+                    &mode,
+                    1.0,
+                    (0.0, 0.0, screen_width, screen_height),
+                );
+                circle_obj.x = new_x;
+                circle_obj.y = new_y;
+                circle_obj.width = new_width;
+                circle_obj.height = new_height;
+            }
         }
     }
     // 新增：处理绘制对象的调整大小
 
+    // 当前生效的选区约束：按住 Shift 时锁定正方形，否则使用所选预设
+    fn active_selection_preset(&self) -> SelectionPreset {
+        if self.shift_pressed {
+            SelectionPreset::Ratio(1.0, 1.0)
+        } else {
+            self.selection_preset
+        }
+    }
+
+    // 选择预设；已有选区时立即按预设调整，保持左上角不动
+    fn select_preset(&mut self, index: usize, screen_width: f32, screen_height: f32) {
+        let Some(entry) = preset::PRESETS.get(index) else {
+            return;
+        };
+        self.selection_preset = entry.preset;
+        self.show_preset_menu = false;
+
+        if !self.selection.is_selecting {
+            return;
+        }
+
+        let current_width = (self.selection.current_x - self.selection.start_x).abs();
+        let (width, height) = match entry.preset {
+            SelectionPreset::Free => return,
+            SelectionPreset::Ratio(..) => {
+                let ratio = entry.preset.ratio().unwrap_or(1.0);
+                (current_width, current_width / ratio)
+            }
+            SelectionPreset::Size(width, height) => (width, height),
+        };
+        // 放不下时按比例缩小到屏幕内，再移动选区使其完全在屏幕上
+        let (width, height) = preset::constrain_corner(
+            0.0,
+            0.0,
+            width,
+            height,
+            entry.preset,
+            (0.0, 0.0, screen_width, screen_height),
+        );
+        let x = self
+            .selection
+            .start_x
+            .min(self.selection.current_x)
+            .min(screen_width - width)
+            .max(0.0);
+        let y = self
+            .selection
+            .start_y
+            .min(self.selection.current_y)
+            .min(screen_height - height)
+            .max(0.0);
+
+        self.selection.start_x = x;
+        self.selection.start_y = y;
+        self.selection.current_x = x + width;
+        self.selection.current_y = y + height;
+    }
+
     // 处理调整大小
     fn handle_resize(&mut self, x: f32, y: f32, screen_width: f32, screen_height: f32) {
+        // 固定尺寸预设不允许调整大小
+        let constraint = self.active_selection_preset();
+        if let SelectionPreset::Size(..) = constraint {
+            return;
+        }
+
+        let bounds = (
+            self.selection.start_x.min(self.selection.current_x),
+            self.selection.start_x.max(self.selection.current_x),
+            self.selection.start_y.min(self.selection.current_y),
+            self.selection.start_y.max(self.selection.current_y),
+        );
+        let mode = self.selection.resize_mode.clone();
+        let (x, y) = self.snap_handle_point(
+            &mode,
+            x,
+            y,
+            bounds,
+            "selection",
+            (screen_width, screen_height),
+        );
+
         match self.selection.resize_mode.as_str() {
             "nw" => {
                 self.selection.start_x = (0.0_f32).max((x).min(self.selection.current_x - 10.0));
@@ -1563,6 +1962,23 @@ impl AppState {
             }
             _ => {}
         }
+
+        // 按比例修正选区
+        if let Some(ratio) = constraint.ratio() {
+            let (new_x, new_y, new_width, new_height) = preset::constrain_box(
+                self.selection.start_x.min(self.selection.current_x),
+                self.selection.start_y.min(self.selection.current_y),
+                (self.selection.current_x - self.selection.start_x).abs(),
+                (self.selection.current_y - self.selection.start_y).abs(),
+                &mode,
+                ratio,
+                (0.0, 0.0, screen_width, screen_height),
+            );
+            self.selection.start_x = new_x;
+            self.selection.start_y = new_y;
+            self.selection.current_x = new_x + new_width;
+            self.selection.current_y = new_y + new_height;
+        }
     }
 
     // 处理拖拽
@@ -1580,6 +1996,10 @@ impl AppState {
 
     // 处理鼠标释放事件
     fn handle_mouse_up(&mut self) {
+        // 松开鼠标后隐藏吸附参考线
+        self.snap_guide_x = None;
+        self.snap_guide_y = None;
+
         if self.is_drawing_mode {
             match self.drawing_mode.as_str() {
                 "rectangle" => {
@@ -1688,13 +2108,25 @@ impl AppState {
             let height = (self.selection.current_y - self.selection.start_y).abs();
 
             if width < 5.0 || height < 5.0 {
-                self.selection.is_selecting = false;
+                // 单击时直接选中高亮的候选区域
+                if let Some(region) = self.hover_region.take() {
+                    self.selection.start_x = region.x;
+                    self.selection.start_y = region.y;
+                    self.selection.current_x = region.x + region.width;
+                    self.selection.current_y = region.y + region.height;
+                } else {
+                    self.selection.is_selecting = false;
+                }
             }
         }
     }
     // 处理工具栏按钮点击
     fn handle_toolbar_click(&mut self, icon_name: &str) -> (Option<SelectionArea>, bool) {
-        self.selected_icon = icon_name.to_string();
+        // 预设菜单按钮只负责展开菜单，不改变当前工具
+        if icon_name != "ratio" {
+            self.selected_icon = icon_name.to_string();
+            self.show_preset_menu = false;
+        }
 
         match icon_name {
             "square" => {
@@ -1755,6 +2187,22 @@ impl AppState {
                 self.drawing_mode = "pen".to_string();
                 (None, false)
             }
+            "ratio" => {
+                self.show_preset_menu = !self.show_preset_menu;
+                (None, false)
+            }
+            "scroll" | "record" => {
+                if self.selection.is_selecting {
+                    let area = SelectionArea {
+                        x: self.selection.start_x.min(self.selection.current_x),
+                        y: self.selection.start_y.min(self.selection.current_y),
+                        width: (self.selection.current_x - self.selection.start_x).abs(),
+                        height: (self.selection.current_y - self.selection.start_y).abs(),
+                    };
+                    return (Some(area), false);
+                }
+                (None, false)
+            }
             "clipboard" => {
                 if self.selection.is_selecting {
                     let area = SelectionArea {
@@ -1834,7 +2282,7 @@ impl AppState {
                 }
                 (None, false)
             }
-            "download" => {
+            "download" | "share" | "qr" | "ocr" | "redact" => {
                 if self.selection.is_selecting {
                     let area = SelectionArea {
                         x: self.selection.start_x.min(self.selection.current_x),
@@ -1863,347 +2311,1232 @@ impl AppState {
         }
     }
 
+    // 按创建顺序收集当前所有标注（包括正在编辑的对象），用于绘制到导出的图像上。
+    // 后创建的标注画在上层，保存工程时也按这个顺序写入
+    fn annotations(&self) -> Vec<Annotation> {
+        let color = annotation::parse_hex_color(OBJECT_COLOR);
+        let mut annotations = Vec::new();
+
+        for redaction in self
+            .redactions
+            .iter()
+            .filter(|redaction| redaction.accepted)
+        {
+            annotations.push((
+                redaction.order,
+                Annotation::Pixelate {
+                    x: redaction.x,
+                    y: redaction.y,
+                    width: redaction.width,
+                    height: redaction.height,
+                    block_size: redaction.block_size,
+                },
+            ));
+        }
+        for obj in self
+            .rectangle_objects
+            .iter()
+            .chain(self.current_rectangle_object.iter())
+        {
+            annotations.push((
+                obj.order,
+                Annotation::Rectangle {
+                    x: obj.x,
+                    y: obj.y,
+                    width: obj.width,
+                    height: obj.height,
+                    color,
+                    stroke_width: OBJECT_BORDER_WIDTH,
+                },
+            ));
+        }
+        for obj in self
+            .circle_objects
+            .iter()
+            .chain(self.current_circle_object.iter())
+        {
+            annotations.push((
+                obj.order,
+                Annotation::Ellipse {
+                    x: obj.x,
+                    y: obj.y,
+                    width: obj.width,
+                    height: obj.height,
+                    color,
+                    stroke_width: OBJECT_BORDER_WIDTH,
+                },
+            ));
+        }
+        for obj in self
+            .arrow_objects
+            .iter()
+            .chain(self.current_arrow_object.iter())
+        {
+            annotations.push((
+                obj.order,
+                Annotation::Arrow {
+                    start_x: obj.start_x,
+                    start_y: obj.start_y,
+                    end_x: obj.end_x,
+                    end_y: obj.end_y,
+                    color,
+                    stroke_width: OBJECT_BORDER_WIDTH,
+                },
+            ));
+        }
+        for path in &self.pen_paths {
+            annotations.push((
+                path.order,
+                Annotation::Pen {
+                    points: path.points.iter().map(|point| (point.x, point.y)).collect(),
+                    color: annotation::parse_hex_color(&path.color),
+                    stroke_width: path.width,
+                },
+            ));
+        }
+        annotations.sort_by_key(|(order, _)| *order);
+        annotations
+            .into_iter()
+            .map(|(_, annotation)| annotation)
+            .collect()
+    }
+
+    fn selection_area(&self) -> Option<SelectionArea> {
+        self.selection.is_selecting.then(|| SelectionArea {
+            x: self.selection.start_x.min(self.selection.current_x),
+            y: self.selection.start_y.min(self.selection.current_y),
+            width: (self.selection.current_x - self.selection.start_x).abs(),
+            height: (self.selection.current_y - self.selection.start_y).abs(),
+        })
+    }
+
+    // 从工程恢复标注。矩形、椭圆和箭头在界面上统一使用 OBJECT_COLOR 绘制，
+    // 画笔保留各自的颜色和粗细
+    fn restore_annotations(&mut self, annotations: &[Annotation]) {
+        for annotation in annotations {
+            let order = self.next_order();
+            match annotation {
+                Annotation::Rectangle {
+                    x,
+                    y,
+                    width,
+                    height,
+                    ..
+                } => self.rectangle_objects.push(RustRectangleObject {
+                    x: *x,
+                    y: *y,
+                    width: *width,
+                    height: *height,
+                    start_x: *x,
+                    start_y: *y,
+                    end_x: x + width,
+                    end_y: y + height,
+                    selected: false,
+                    order,
+                }),
+                Annotation::Ellipse {
+                    x,
+                    y,
+                    width,
+                    height,
+                    ..
+                } => self.circle_objects.push(RustCircleObject {
+                    x: *x,
+                    y: *y,
+                    width: *width,
+                    height: *height,
+                    start_x: *x,
+                    start_y: *y,
+                    end_x: x + width,
+                    end_y: y + height,
+                    selected: false,
+                    order,
+                }),
+                Annotation::Arrow {
+                    start_x,
+                    start_y,
+                    end_x,
+                    end_y,
+                    ..
+                } => self.arrow_objects.push(RustArrowObject {
+                    start_x: *start_x,
+                    start_y: *start_y,
+                    end_x: *end_x,
+                    end_y: *end_y,
+                    selected: false,
+                    order,
+                }),
+                Annotation::Pen {
+                    points,
+                    color,
+                    stroke_width,
+                } => {
+                    let points = points.iter().map(|&(x, y)| RustPenPoint { x, y }).collect();
+                    let color = format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]);
+                    self.pen_paths
+                        .push(RustPenPath::new(points, color, *stroke_width, order));
+                    self.pen_path_count += 1;
+                }
+                Annotation::Pixelate {
+                    x,
+                    y,
+                    width,
+                    height,
+                    block_size,
+                } => self.redactions.push(RustRedaction {
+                    x: *x,
+                    y: *y,
+                    width: *width,
+                    height: *height,
+                    block_size: *block_size,
+                    label: "打码".to_string(),
+                    accepted: true,
+                    order,
+                }),
+                // 标注界面还没有文字工具，文字只在导出时绘制
+                Annotation::Text { .. } => {}
+            }
+        }
+    }
+
     // 获取鼠标光标类型
 }
 
-// 添加预览窗口状态结构体
-struct PreviewWindowState {
-    window: Rc<PreviewWindow>,
+// 标注界面打开时预先恢复的内容
+#[derive(Default)]
+struct InitialState {
+    selection: Option<SelectionArea>,
+    annotations: Vec<Annotation>,
+    captured_at: Option<u64>, // 重新编辑时原来的截图时间，为 None 时取打开标注界面的时间
 }
 
-impl PreviewWindowState {
-    fn new(image_data: Vec<u8>, width: u32, height: u32) -> Rc<RefCell<Self>> {
-        let window = Rc::new(PreviewWindow::new().unwrap());
-
-        // 创建slint图像
-        let mut pixel_buffer = slint::SharedPixelBuffer::<slint::Rgba8Pixel>::new(width, height);
-        let buffer = pixel_buffer.make_mut_bytes();
+// 把已完成的画笔路径同步到界面，界面只用到边界框和绘制命令
+fn sync_pen_paths(app: &AppWindow, state: &AppState) {
+    let paths: Vec<_> = state
+        .pen_paths
+        .iter()
+        .map(|path| slint_generatedAppWindow::PenPath {
+            points: ModelRc::default(),
+            color: slint::Color::from_argb_encoded(
+                u32::from_str_radix(path.color.trim_start_matches('#'), 16).unwrap_or(0xff0044)
+                    | 0xff000000,
+            ),
+            width: path.width,
+            min_x: path.min_x,
+            max_x: path.max_x,
+            min_y: path.min_y,
+            max_y: path.max_y,
+            commands: path.commands.clone().into(),
+        })
+        .collect();
+    app.set_pen_paths(ModelRc::new(VecModel::from(paths)));
+}
 
-        // 直接复制数据
-        buffer.copy_from_slice(&image_data);
+// 把保存的矩形、圆形和箭头同步到界面
+fn sync_saved_objects(app: &AppWindow, state: &AppState) {
+    let saved_rectangles: Vec<_> = state
+        .rectangle_objects
+        .iter()
+        .map(|obj| slint_generatedAppWindow::RectangleObject {
+            x: obj.x,
+            y: obj.y,
+            width: obj.width,
+            height: obj.height,
+            selected: obj.selected,
+        })
+        .collect();
+    app.set_saved_rectangles(saved_rectangles.as_slice().into());
+
+    let saved_circles: Vec<_> = state
+        .circle_objects
+        .iter()
+        .map(|obj| slint_generatedAppWindow::CircleObject {
+            x: obj.x,
+            y: obj.y,
+            width: obj.width,
+            height: obj.height,
+            selected: obj.selected,
+        })
+        .collect();
+    app.set_saved_circles(saved_circles.as_slice().into());
+
+    // 更新保存的箭头列表
+    let saved_arrows: Vec<_> = state
+        .arrow_objects
+        .iter()
+        .map(|obj| slint_generatedAppWindow::ArrowObject {
+            start_x: obj.start_x,
+            start_y: obj.start_y,
+            end_x: obj.end_x,
+            end_y: obj.end_y,
+            selected: obj.selected,
+        })
+        .collect();
+    app.set_saved_arrows(saved_arrows.as_slice().into());
+}
 
-        let slint_image = slint::Image::from_rgba8(pixel_buffer);
-        window.set_screenshot(slint_image);
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match cli::parse(&args) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("{}\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if command == cli::Command::Help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
 
-        let instance = Rc::new(RefCell::new(Self {
-            window: window.clone(),
-        }));
+    // 有守护进程在运行时只作为客户端转发命令
+    let socket_path = ipc::socket_path();
+    if let Some(request) = command.to_request() {
+        match ipc::send_request(&socket_path, &request) {
+            Ok(response) => {
+                print_response(&response);
+                std::process::exit(if response.ok { 0 } else { 1 });
+            }
+            Err(err) if command.requires_daemon() => {
+                eprintln!("没有正在运行的守护进程: {}", err);
+                std::process::exit(1);
+            }
+            Err(_) => {}
+        }
+    }
 
-        let window_weak = window.as_weak();
-        window.on_close_window(move || {
-            if let Some(window) = window_weak.upgrade() {
-                window.hide().unwrap();
+    match &command {
+        cli::Command::Daemon => {
+            ipc::start_server(&socket_path, handle_ipc_request)?;
+            lifecycle::set_resident(true);
+            println!("守护进程已启动: {}", socket_path.display());
+            for err in hotkey::start(hotkey_bindings(&config::current()), handle_hotkey) {
+                eprintln!("{}", err);
             }
-        });
+        }
+        cli::Command::Capture => open_capture_overlay()?,
+        cli::Command::CaptureRegion {
+            x,
+            y,
+            width,
+            height,
+        } => capture_region(*x, *y, *width, *height)?,
+        cli::Command::CaptureFullscreen => capture_fullscreen()?,
+        cli::Command::CaptureRepeat => capture_repeat()?,
+        cli::Command::PinClipboard => pin::pin_clipboard()?,
+        cli::Command::PinFile(path) => pin::pin_file(path)?,
+        cli::Command::History => history_window::show()?,
+        cli::Command::OpenProject(path) => open_project(path)?,
+        cli::Command::OpenImage(path) => open_annotated_image(path)?,
+        cli::Command::Export { input, output } => export_file(input, output)?,
+        cli::Command::Upload(path) => upload_file(path)?,
+        cli::Command::Pdf {
+            output,
+            inputs,
+            title,
+            caption,
+            timestamp,
+        } => export_pdf(
+            output,
+            inputs,
+            title.as_deref(),
+            caption.as_deref(),
+            *timestamp,
+        )?,
+        _ => return Ok(()),
+    }
+    if command != cli::Command::Daemon && lifecycle::is_idle() {
+        // 没能截取屏幕，遮罩没有打开
+        return Ok(());
+    }
+    // 由 lifecycle 决定何时退出，隐藏所有窗口不会自动结束事件循环
+    slint::run_event_loop_until_quit()?;
 
-        let window_weak = window.as_weak();
-        window.on_move_window(move |offset_x, offset_y| {
-            if let Some(window) = window_weak.upgrade() {
-                let pos = window.window().position();
-                let scale = window.window().scale_factor();
-                let logical_pos = pos.to_logical(scale);
-                window.window().set_position(slint::LogicalPosition::new(
-                    logical_pos.x + offset_x,
-                    logical_pos.y + offset_y,
-                ));
+    if command == cli::Command::Daemon {
+        let _ = std::fs::remove_file(&socket_path);
+    }
+    Ok(())
+}
+
+fn print_response(response: &ipc::Response) {
+    if let Some(error) = &response.error {
+        eprintln!("{}", error);
+    }
+    for pin in &response.pins {
+        println!(
+            "{}\t{},{}\t{}x{}",
+            pin.id, pin.x as i32, pin.y as i32, pin.width as u32, pin.height as u32
+        );
+    }
+}
+
+// 处理守护进程收到的请求，在事件循环线程上执行
+fn handle_ipc_request(request: ipc::Request) -> ipc::Response {
+    let result = match request {
+        ipc::Request::CaptureInteractive => open_capture_overlay(),
+        ipc::Request::CaptureRegion {
+            x,
+            y,
+            width,
+            height,
+        } => capture_region(x, y, width, height),
+        ipc::Request::CaptureFullscreen => capture_fullscreen(),
+        ipc::Request::CaptureRepeat => capture_repeat(),
+        ipc::Request::PinFile { path } => pin::pin_file(&path),
+        ipc::Request::PinClipboard => pin::pin_clipboard(),
+        ipc::Request::ShowHistory => history_window::show(),
+        ipc::Request::OpenProject { path } => open_project(&path),
+        ipc::Request::OpenImage { path } => open_annotated_image(&path),
+        ipc::Request::ListPins => {
+            return ipc::Response {
+                pins: pin::list_pins(),
+                ..ipc::Response::ok()
+            };
+        }
+        ipc::Request::ClosePins { ids } => {
+            if ids.is_empty() {
+                pin::close_all();
+            } else if pin::close_pins(&ids) == 0 {
+                return ipc::Response::error("没有找到指定的钉图");
             }
-        });
-        instance
+            Ok(())
+        }
+        ipc::Request::ReloadConfig => config::reload()
+            .map(|()| restart_hotkeys())
+            .map_err(Into::into),
+        ipc::Request::Quit => {
+            let _ = slint::quit_event_loop();
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => ipc::Response::ok(),
+        Err(err) => ipc::Response::error(err.to_string()),
     }
+}
 
-    fn show(&self) {
-        self.window.show().unwrap();
+// 配置中留空的快捷键不注册
+fn hotkey_bindings(config: &config::Config) -> Vec<(hotkey::HotkeyAction, String)> {
+    let hotkeys = &config.hotkeys;
+    [
+        (hotkey::HotkeyAction::RegionCapture, &hotkeys.region_capture),
+        (
+            hotkey::HotkeyAction::FullscreenCapture,
+            &hotkeys.fullscreen_capture,
+        ),
+        (
+            hotkey::HotkeyAction::RepeatLastRegion,
+            &hotkeys.repeat_last_region,
+        ),
+        (hotkey::HotkeyAction::PinClipboard, &hotkeys.pin_clipboard),
+    ]
+    .into_iter()
+    .filter(|(_, accelerator)| !accelerator.trim().is_empty())
+    .map(|(action, accelerator)| (action, accelerator.clone()))
+    .collect()
+}
+
+// 重新加载配置后按新的设置重新注册快捷键
+fn restart_hotkeys() {
+    hotkey::stop();
+    for err in hotkey::start(hotkey_bindings(&config::current()), handle_hotkey) {
+        eprintln!("{}", err);
     }
 }
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // 直接显示截图窗口
-    show_screenshot_window()?;
+// 处理全局快捷键，在事件循环线程上执行
+fn handle_hotkey(action: hotkey::HotkeyAction) {
+    let result = match action {
+        hotkey::HotkeyAction::RegionCapture => open_capture_overlay(),
+        hotkey::HotkeyAction::FullscreenCapture => capture_fullscreen(),
+        hotkey::HotkeyAction::RepeatLastRegion => capture_repeat(),
+        hotkey::HotkeyAction::PinClipboard => pin::pin_clipboard(),
+    };
+    if let Err(err) = result {
+        println!("{}失败: {}", action.label(), err);
+    }
+}
+
+// 上次截图的区域保存在缓存目录，供「重复上次区域」使用
+fn last_region_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("screenshot-slint").join("last_region"))
+}
+
+fn save_last_region(x: i32, y: i32, width: u32, height: u32) {
+    let Some(path) = last_region_path() else {
+        return;
+    };
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    let _ = std::fs::write(path, format!("{},{},{},{}", x, y, width, height));
+}
+
+fn load_last_region() -> Option<(i32, i32, u32, u32)> {
+    let text = std::fs::read_to_string(last_region_path()?).ok()?;
+    cli::parse_region(text.trim()).ok()
+}
+
+fn capture_repeat() -> Result<(), Box<dyn Error>> {
+    let (x, y, width, height) = load_last_region().ok_or("还没有可以重复的截图区域")?;
+    capture_region(x, y, width, height)
+}
+
+// 截屏之前执行 before-capture 钩子，钩子失败时取消本次截图
+fn run_before_capture_hooks(context: hooks::HookContext) -> Result<(), Box<dyn Error>> {
+    hooks::run(&config::current().hooks, hooks::BEFORE_CAPTURE, &context)
+        .map_err(|err| format!("截图已取消: {}", err).into())
+}
+
+// 截取整个屏幕，交给全屏截图的输出流程（默认保存、复制并写入历史）
+fn capture_fullscreen() -> Result<(), Box<dyn Error>> {
+    let screen = Screen::all()?.into_iter().next().ok_or("没有找到屏幕")?;
+    run_before_capture_hooks(hooks::HookContext {
+        screen_id: Some(screen.display_info.id),
+        ..Default::default()
+    })?;
+    let image = screen.capture()?;
+    let (width, height) = (image.width(), image.height());
+    pipeline::run_fullscreen(pipeline::Capture::new(
+        image.into_raw(),
+        (width, height),
+        (0, 0),
+        screen.display_info.id,
+        Vec::new(),
+    ));
     Ok(())
 }
 
-fn show_screenshot_window() -> Result<(), Box<dyn Error>> {
+// 不经过标注界面，直接截取屏幕上的指定区域并钉到桌面
+fn capture_region(x: i32, y: i32, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+    let screen = Screen::all()?.into_iter().next().ok_or("没有找到屏幕")?;
+    run_before_capture_hooks(hooks::HookContext {
+        geometry: Some((x, y, width, height)),
+        screen_id: Some(screen.display_info.id),
+        ..Default::default()
+    })?;
+    let image = screen.capture_area(x, y, width, height)?;
+    save_last_region(x, y, width, height);
+    let (image_width, image_height) = (image.width(), image.height());
+    pipeline::run_capture(pipeline::Capture::new(
+        image.into_raw(),
+        (image_width, image_height),
+        (x, y),
+        screen.display_info.id,
+        Vec::new(),
+    ));
+    Ok(())
+}
+
+// 打开工程，恢复背景、选区和标注
+fn open_project(path: &Path) -> Result<(), Box<dyn Error>> {
+    let project = project::Project::load(path)?;
+    let screens = Screen::all()?;
+    let screen = screens
+        .iter()
+        .find(|screen| screen.display_info.id == project.screen_id)
+        .or(screens.first())
+        .copied()
+        .ok_or("没有找到屏幕")?;
+    open_annotation_overlay(
+        screen,
+        project.background,
+        project.width,
+        project.height,
+        InitialState {
+            selection: project.selection.map(|selection| SelectionArea {
+                x: selection.x,
+                y: selection.y,
+                width: selection.width,
+                height: selection.height,
+            }),
+            annotations: project.annotations,
+            // 旧版本的工程没有记录截图时间，用工程目录的修改时间代替
+            captured_at: project.captured_at.or_else(|| history::modified_time(path)),
+        },
+        false,
+    )
+}
+
+// 截取整个屏幕并打开标注界面
+fn open_capture_overlay() -> Result<(), Box<dyn Error>> {
     if let Ok(screens) = Screen::all() {
         if let Some(screen) = screens.first() {
+            run_before_capture_hooks(hooks::HookContext {
+                screen_id: Some(screen.display_info.id),
+                ..Default::default()
+            })?;
             if let Ok(image) = screen.capture() {
                 let width = image.width();
                 let height = image.height();
-                let background_data = image.into_raw();
-
-                let app = AppWindow::new()?;
-                let app_state = Rc::new(RefCell::new(AppState::default()));
+                open_annotation_overlay(
+                    *screen,
+                    image.into_raw(),
+                    width,
+                    height,
+                    InitialState::default(),
+                    true,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
 
-                // 设置背景图像
-                let mut pixel_buffer =
-                    slint::SharedPixelBuffer::<slint::Rgba8Pixel>::new(width, height);
-                let buffer = pixel_buffer.make_mut_bytes();
-                buffer.copy_from_slice(&background_data);
-
-                app.window()
-                    .set_position(LogicalPosition::new(0 as f32, 0 as f32));
-                let background_image = slint::Image::from_rgba8(pixel_buffer);
-                app.set_background_screenshot(background_image);
-                app.set_show_mask(true);
-
-                // 处理鼠标事件
-                let app_weak = app.as_weak();
-                let app_state_clone = app_state.clone();
-                app.on_mouse_event(move |event_type, x, y| {
-                    if let Some(app) = app_weak.upgrade() {
-                        let mut state = app_state_clone.borrow_mut();
-
-                        // 用于处理画笔路径创建的标志
-                        let mut should_create_pen_path = false;
-                        let mut pen_path_data = None;
-
-                        match event_type.as_str() {
-                            "down" => state.handle_mouse_down(x, y),
-                            "move" => {
-                                // 针对画笔模式优化鼠标移动处理
-                                if state.drawing_mode == "pen" && state.is_drawing_pen {
-                                    // 画笔模式下直接处理，减少延迟
-                                    state.handle_pen_drawing(x, y);
-                                } else {
-                                    state.handle_mouse_move(x, y, width as f32, height as f32);
-                                }
-                            }
-                            "up" => {
-                                // 检查是否需要创建画笔路径
-                                if state.drawing_mode == "pen" && state.is_drawing_pen {
-                                    if let Some(path) = state.finish_pen_drawing() {
-                                        should_create_pen_path = true;
-                                        pen_path_data = Some((
-                                            path.min_x,
-                                            path.max_x,
-                                            path.min_y,
-                                            path.max_y,
-                                            path.commands.clone(),
-                                            state.pen_color.clone(),
-                                            path.width,
-                                        ));
-                                    }
-                                } else {
-                                    state.handle_mouse_up();
-                                }
-                            }
-                            _ => {}
-                        }
+// 以 background_data 为背景打开标注界面。live 为 false 时背景是历史截图或工程而不是当前屏幕，
+// 不合并窗口位置，也不能滚动截图或录制
+fn open_annotation_overlay(
+    screen: Screen,
+    background_data: Vec<u8>,
+    width: u32,
+    height: u32,
+    initial: InitialState,
+    live: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config = config::current();
+    // 背景在打开标注界面之前截取，页脚记录的是这个时间而不是完成选区的时间
+    let captured_at = initial.captured_at.unwrap_or_else(history::now);
+    let app = AppWindow::new()?;
+    let app_state = Rc::new(RefCell::new(AppState::default()));
+
+    // 预先识别截图中的候选区域，并合并窗口树提供的窗口位置
+    {
+        let mut state = app_state.borrow_mut();
+        state.detected_regions = detect::detect_regions(&background_data, width, height);
+        state.snapper = Some(Snapper::new(&background_data, width, height));
+        if live {
+            detect::merge_regions(&mut state.detected_regions, platform::window_regions());
+        }
+    }
 
-                        // 如果需要创建画笔路径，调用回调
-                        if should_create_pen_path {
-                            if let Some((min_x, max_x, min_y, max_y, commands, color, width)) =
-                                pen_path_data
-                            {
-                                app.invoke_create_pen_path_component(
-                                    min_x,
-                                    max_x,
-                                    min_y,
-                                    max_y,
-                                    commands.into(),
-                                    slint::Color::from_argb_encoded(
-                                        u32::from_str_radix(&color[1..], 16).unwrap_or(0xff0044)
-                                            | 0xff000000,
-                                    ),
-                                    width,
-                                );
-                            }
+    // 设置背景图像
+    let mut pixel_buffer = slint::SharedPixelBuffer::<slint::Rgba8Pixel>::new(width, height);
+    let buffer = pixel_buffer.make_mut_bytes();
+    buffer.copy_from_slice(&background_data);
+
+    app.window()
+        .set_position(LogicalPosition::new(0 as f32, 0 as f32));
+    let background_image = slint::Image::from_rgba8(pixel_buffer);
+    app.set_background_screenshot(background_image);
+    app.set_show_mask(true);
+
+    // 处理鼠标事件
+    let app_weak = app.as_weak();
+    let app_state_clone = app_state.clone();
+    app.on_mouse_event(move |event_type, x, y, alt, shift| {
+        if let Some(app) = app_weak.upgrade() {
+            let mut state = app_state_clone.borrow_mut();
+            state.snap_disabled = alt;
+            state.shift_pressed = shift;
+
+            // 用于处理画笔路径创建的标志
+            let mut should_create_pen_path = false;
+            let mut pen_path_data = None;
+
+            match event_type.as_str() {
+                "down" => state.handle_mouse_down(x, y),
+                "move" => {
+                    // 针对画笔模式优化鼠标移动处理
+                    if state.drawing_mode == "pen" && state.is_drawing_pen {
+                        // 画笔模式下直接处理，减少延迟
+                        state.handle_pen_drawing(x, y);
+                    } else {
+                        state.handle_mouse_move(x, y, width as f32, height as f32);
+                    }
+                }
+                "up" => {
+                    // 检查是否需要创建画笔路径
+                    if state.drawing_mode == "pen" && state.is_drawing_pen {
+                        if let Some(path) = state.finish_pen_drawing() {
+                            should_create_pen_path = true;
+                            pen_path_data = Some((
+                                path.min_x,
+                                path.max_x,
+                                path.min_y,
+                                path.max_y,
+                                path.commands.clone(),
+                                state.pen_color.clone(),
+                                path.width,
+                            ));
                         }
+                    } else {
+                        state.handle_mouse_up();
+                    }
+                }
+                _ => {}
+            }
 
-                        // 更新UI状态
-                        app.set_start_x(state.selection.start_x);
-                        app.set_start_y(state.selection.start_y);
-                        app.set_current_x(state.selection.current_x);
-                        app.set_current_y(state.selection.current_y);
-                        app.set_is_selecting(state.selection.is_selecting);
-                        app.set_cursor_type(state.get_mouse_cursor_string(x, y).into());
-
-                        // 更新画框模式状态
-                        app.set_is_drawing_mode(state.is_drawing_mode);
-
-                        // 只在画笔模式下更新画笔相关状态
-                        if state.drawing_mode == "pen" {
-                            app.set_show_current_pen_path(!state.current_pen_points.is_empty());
-
-                            let (min_x, max_x, min_y, max_y, commands) =
-                                state.update_current_pen_path();
-                            app.set_current_pen_min_x(min_x);
-                            app.set_current_pen_max_x(max_x);
-                            app.set_current_pen_min_y(min_y);
-                            app.set_current_pen_max_y(max_y);
-                            app.set_current_pen_path_commands(commands.into());
-                        } else {
-                            app.set_show_current_pen_path(false);
-                        }
+            // 如果需要创建画笔路径，调用回调
+            if should_create_pen_path {
+                if let Some((min_x, max_x, min_y, max_y, commands, color, width)) = pen_path_data {
+                    app.invoke_create_pen_path_component(
+                        min_x,
+                        max_x,
+                        min_y,
+                        max_y,
+                        commands.into(),
+                        slint::Color::from_argb_encoded(
+                            u32::from_str_radix(&color[1..], 16).unwrap_or(0xff0044) | 0xff000000,
+                        ),
+                        width,
+                    );
+                }
+                sync_pen_paths(&app, &state);
+            }
 
-                        // 分别更新保存的对象列表
-                        let saved_rectangles: Vec<_> = state
-                            .rectangle_objects
-                            .iter()
-                            .map(|obj| slint_generatedAppWindow::RectangleObject {
-                                x: obj.x,
-                                y: obj.y,
-                                width: obj.width,
-                                height: obj.height,
-                                selected: obj.selected,
-                            })
-                            .collect();
-                        app.set_saved_rectangles(saved_rectangles.as_slice().into());
-
-                        let saved_circles: Vec<_> = state
-                            .circle_objects
-                            .iter()
-                            .map(|obj| slint_generatedAppWindow::CircleObject {
-                                x: obj.x,
-                                y: obj.y,
-                                width: obj.width,
-                                height: obj.height,
-                                selected: obj.selected,
-                            })
-                            .collect();
-                        app.set_saved_circles(saved_circles.as_slice().into());
-
-                        // 更新保存的箭头列表
-                        let saved_arrows: Vec<_> = state
-                            .arrow_objects
-                            .iter()
-                            .map(|obj| slint_generatedAppWindow::ArrowObject {
-                                start_x: obj.start_x,
-                                start_y: obj.start_y,
-                                end_x: obj.end_x,
-                                end_y: obj.end_y,
-                                selected: obj.selected,
-                            })
-                            .collect();
-                        app.set_saved_arrows(saved_arrows.as_slice().into());
-
-                        // 更新当前矩形对象状态
-                        if let Some(ref rect_obj) = state.current_rectangle_object {
-                            app.set_show_current_rectangle(true);
-                            app.set_is_drawing_current_rectangle(state.is_drawing_rectangle);
-                            app.set_current_rectangle_x(rect_obj.x);
-                            app.set_current_rectangle_y(rect_obj.y);
-                            app.set_current_rectangle_width(rect_obj.width);
-                            app.set_current_rectangle_height(rect_obj.height);
-                            app.set_current_rectangle_selected(rect_obj.selected);
-                        } else {
-                            app.set_show_current_rectangle(false);
-                            app.set_is_drawing_current_rectangle(false);
-                        }
+            // 更新UI状态
+            app.set_start_x(state.selection.start_x);
+            app.set_start_y(state.selection.start_y);
+            app.set_current_x(state.selection.current_x);
+            app.set_current_y(state.selection.current_y);
+            app.set_is_selecting(state.selection.is_selecting);
+            app.set_cursor_type(state.get_mouse_cursor_string(x, y).into());
+
+            // 更新候选区域高亮
+            if let Some(region) = state.hover_region {
+                app.set_show_hover_region(true);
+                app.set_hover_region_x(region.x);
+                app.set_hover_region_y(region.y);
+                app.set_hover_region_width(region.width);
+                app.set_hover_region_height(region.height);
+            } else {
+                app.set_show_hover_region(false);
+            }
 
-                        // 更新当前圆形对象状态
-                        if let Some(ref circle_obj) = state.current_circle_object {
-                            app.set_show_current_circle(true);
-                            app.set_is_drawing_current_circle(state.is_drawing_circle);
-                            app.set_current_circle_x(circle_obj.x);
-                            app.set_current_circle_y(circle_obj.y);
-                            app.set_current_circle_width(circle_obj.width);
-                            app.set_current_circle_height(circle_obj.height);
-                            app.set_current_circle_selected(circle_obj.selected);
-                        } else {
-                            app.set_show_current_circle(false);
-                            app.set_is_drawing_current_circle(false);
-                        }
+            // 更新吸附参考线
+            app.set_show_snap_guide_x(state.snap_guide_x.is_some());
+            app.set_snap_guide_x(state.snap_guide_x.unwrap_or(0.0));
+            app.set_show_snap_guide_y(state.snap_guide_y.is_some());
+            app.set_snap_guide_y(state.snap_guide_y.unwrap_or(0.0));
+
+            // 更新画框模式状态
+            app.set_is_drawing_mode(state.is_drawing_mode);
+
+            // 只在画笔模式下更新画笔相关状态
+            if state.drawing_mode == "pen" {
+                app.set_show_current_pen_path(!state.current_pen_points.is_empty());
+
+                let (min_x, max_x, min_y, max_y, commands) = state.update_current_pen_path();
+                app.set_current_pen_min_x(min_x);
+                app.set_current_pen_max_x(max_x);
+                app.set_current_pen_min_y(min_y);
+                app.set_current_pen_max_y(max_y);
+                app.set_current_pen_path_commands(commands.into());
+            } else {
+                app.set_show_current_pen_path(false);
+            }
 
-                        // 更新当前箭头对象状态
-                        if let Some(ref arrow_obj) = state.current_arrow_object {
-                            app.set_show_current_arrow(true);
-                            app.set_is_drawing_current_arrow(state.is_drawing_arrow);
-                            app.set_current_arrow_start_x(arrow_obj.start_x);
-                            app.set_current_arrow_start_y(arrow_obj.start_y);
-                            app.set_current_arrow_end_x(arrow_obj.end_x);
-                            app.set_current_arrow_end_y(arrow_obj.end_y);
-                            app.set_current_arrow_selected(arrow_obj.selected);
-                        } else {
-                            app.set_show_current_arrow(false);
-                            app.set_is_drawing_current_arrow(false);
-                        }
-                    }
-                });
+            // 分别更新保存的对象列表
+            sync_saved_objects(&app, &state);
+
+            // 更新当前矩形对象状态
+            if let Some(ref rect_obj) = state.current_rectangle_object {
+                app.set_show_current_rectangle(true);
+                app.set_is_drawing_current_rectangle(state.is_drawing_rectangle);
+                app.set_current_rectangle_x(rect_obj.x);
+                app.set_current_rectangle_y(rect_obj.y);
+                app.set_current_rectangle_width(rect_obj.width);
+                app.set_current_rectangle_height(rect_obj.height);
+                app.set_current_rectangle_selected(rect_obj.selected);
+            } else {
+                app.set_show_current_rectangle(false);
+                app.set_is_drawing_current_rectangle(false);
+            }
 
-                // 修复：处理工具栏点击 - 恢复完整逻辑
-                let app_weak = app.as_weak();
-                let app_state_clone = app_state.clone();
-                app.on_toolbar_clicked(move |icon_name| {
-                    if let Some(app) = app_weak.upgrade() {
-                        let mut state = app_state_clone.borrow_mut();
-
-                        let (area_option, should_cancel) = state.handle_toolbar_click(&icon_name);
-
-                        if should_cancel {
-                            // 取消按钮被点击
-                            app.invoke_cancel_capture();
-                        } else if let Some(area) = area_option {
-                            // 确认、复制或下载按钮被点击，且有有效选区
-                            app.invoke_selection_complete(area);
-                        }
+            // 更新当前圆形对象状态
+            if let Some(ref circle_obj) = state.current_circle_object {
+                app.set_show_current_circle(true);
+                app.set_is_drawing_current_circle(state.is_drawing_circle);
+                app.set_current_circle_x(circle_obj.x);
+                app.set_current_circle_y(circle_obj.y);
+                app.set_current_circle_width(circle_obj.width);
+                app.set_current_circle_height(circle_obj.height);
+                app.set_current_circle_selected(circle_obj.selected);
+            } else {
+                app.set_show_current_circle(false);
+                app.set_is_drawing_current_circle(false);
+            }
 
-                        app.set_selected_icon(state.selected_icon.clone().into());
+            // 更新当前箭头对象状态
+            if let Some(ref arrow_obj) = state.current_arrow_object {
+                app.set_show_current_arrow(true);
+                app.set_is_drawing_current_arrow(state.is_drawing_arrow);
+                app.set_current_arrow_start_x(arrow_obj.start_x);
+                app.set_current_arrow_start_y(arrow_obj.start_y);
+                app.set_current_arrow_end_x(arrow_obj.end_x);
+                app.set_current_arrow_end_y(arrow_obj.end_y);
+                app.set_current_arrow_selected(arrow_obj.selected);
+            } else {
+                app.set_show_current_arrow(false);
+                app.set_is_drawing_current_arrow(false);
+            }
+        }
+    });
+
+    // 修复：处理工具栏点击 - 恢复完整逻辑
+    let app_weak = app.as_weak();
+    let app_state_clone = app_state.clone();
+    let capture_screen = screen;
+    let recording_config = config.recording.clone();
+    let toolbar_background = background_data.clone();
+    // 下载和分享的图像与输出管道一样加上水印和页脚
+    let capture_info = watermark::CaptureInfo {
+        timestamp: captured_at,
+        screen_id: Some(screen.display_info.id),
+    };
+    app.on_toolbar_clicked(move |icon_name| {
+        if let Some(app) = app_weak.upgrade() {
+            let mut state = app_state_clone.borrow_mut();
+            // 选区完成的回调会读取状态，等释放借用后再触发
+            let mut completed_area = None;
+
+            let (area_option, should_cancel) = state.handle_toolbar_click(&icon_name);
+            // 识别结果只保留到下一次点击工具栏
+            app.set_detected_codes(ModelRc::default());
+            app.set_ocr_words(ModelRc::default());
+
+            if should_cancel {
+                // 取消按钮被点击
+                app.invoke_cancel_capture();
+            } else if let Some(area) = area_option {
+                if !live && (icon_name == "scroll" || icon_name == "record") {
+                    println!("编辑历史截图时不能滚动截图或录制");
+                } else if icon_name == "scroll" {
+                    // 滚动截图：隐藏遮罩，由控制窗口接管后续截取
+                    let result = scroll_capture::start_scroll_capture(
+                        capture_screen,
+                        area.clone(),
+                        move |data, stitched_width, stitched_height| {
+                            pipeline::run_capture(pipeline::Capture::new(
+                                data,
+                                (stitched_width, stitched_height),
+                                (area.x as i32, area.y as i32),
+                                capture_screen.display_info.id,
+                                Vec::new(),
+                            ));
+                            lifecycle::capture_ended();
+                        },
+                        || {
+                            hooks::run_on_cancel(&config::current().hooks);
+                            lifecycle::capture_ended();
+                        },
+                    );
+                    if result.is_ok() {
+                        let _ = app.hide();
                     }
-                });
-
-                // 处理选区完成
-                let background_data_clone = background_data.clone();
-                app.on_selection_complete(move |area| {
-                    let capture_x = area.x as u32;
-                    let capture_y = area.y as u32;
-                    let capture_width = area.width as u32;
-                    let capture_height = area.height as u32;
-
-                    if let Ok(()) = extract_selection_from_background(
-                        &background_data_clone,
-                        width,
-                        height,
-                        capture_x,
-                        capture_y,
-                        capture_width,
-                        capture_height,
-                        area.x as i32,
-                        area.y as i32,
+                } else if icon_name == "record" {
+                    // 区域录制：标注会叠加到每一帧上
+                    let result = record_capture::start_recording(
+                        capture_screen,
+                        area,
+                        state.annotations(),
+                        recording_config.clone(),
+                        |result| {
+                            match result {
+                                Ok(path) => println!("录制已保存: {}", path.display()),
+                                Err(err) => println!("录制失败: {}", err),
+                            }
+                            lifecycle::capture_ended();
+                        },
+                        || {
+                            println!("取消录制");
+                            hooks::run_on_cancel(&config::current().hooks);
+                            lifecycle::capture_ended();
+                        },
+                    );
+                    if result.is_ok() {
+                        let _ = app.hide();
+                    }
+                } else if icon_name == "qr" {
+                    // 识别选区中的二维码和条码，结果显示在选区上，不结束截图
+                    let codes = scan_codes(&toolbar_background, (width, height), &area);
+                    if codes.is_empty() {
+                        println!("选区中没有识别到二维码或条码");
+                    }
+                    app.set_detected_codes(ModelRc::new(VecModel::from(codes)));
+                } else if icon_name == "ocr" || icon_name == "redact" {
+                    // 文字识别：复制识别出的文字，每个词的位置显示在选区上；
+                    // 打码：查找敏感信息，作为待确认的打码建议显示在选区上。都不结束截图
+                    match ocr::recognizer(&config::current().ocr) {
+                        Ok(recognizer) if icon_name == "ocr" => recognize_text(
+                            &app,
+                            recognizer,
+                            &toolbar_background,
+                            (width, height),
+                            &area,
+                        ),
+                        Ok(recognizer) => suggest_redactions(
+                            &app,
+                            recognizer,
+                            &toolbar_background,
+                            (width, height),
+                            &area,
+                        ),
+                        Err(err) => println!("文字识别失败: {}", err),
+                    }
+                } else if icon_name == "share" {
+                    // 分享：把绘制了标注的选区放到本地分享服务器，链接复制到剪贴板；
+                    // 分享失败时留在标注界面
+                    match share_selection(
+                        &toolbar_background,
+                        (width, height),
+                        &area,
+                        &state.annotations(),
+                        &capture_info,
                     ) {
-                        println!("截图完成");
-                    } else {
-                        println!("截图失败");
+                        Ok(url) => {
+                            println!("分享链接已复制: {}", url);
+                            state.shared = true;
+                            completed_area = Some(area);
+                        }
+                        Err(err) => println!("分享失败: {}", err),
+                    }
+                } else {
+                    // 下载：另外导出一份绘制了标注的 PNG
+                    if icon_name == "download" {
+                        match export_annotated_png(
+                            &toolbar_background,
+                            (width, height),
+                            &area,
+                            state.annotations(),
+                            &capture_info,
+                        ) {
+                            Ok(path) => println!("已导出: {}", path.display()),
+                            Err(err) => println!("导出失败: {}", err),
+                        }
                     }
+                    // 确认、复制或下载按钮被点击，且有有效选区
+                    completed_area = Some(area);
+                }
+            }
 
-                    std::process::exit(0);
-                });
+            app.set_selected_icon(state.selected_icon.clone().into());
+            app.set_show_preset_menu(state.show_preset_menu);
+            sync_pen_paths(&app, &state);
+            drop(state);
+            if let Some(area) = completed_area {
+                app.invoke_selection_complete(area);
+            }
+        }
+    });
+
+    // 识别出的二维码和条码：复制内容、打开链接
+    let app_weak = app.as_weak();
+    app.on_copy_code(move |index| {
+        let Some(code) = app_weak
+            .upgrade()
+            .and_then(|app| app.get_detected_codes().row_data(index as usize))
+        else {
+            return;
+        };
+        copy_text(&code.text);
+    });
+
+    let app_weak = app.as_weak();
+    app.on_open_code(move |index| {
+        let Some(code) = app_weak
+            .upgrade()
+            .and_then(|app| app.get_detected_codes().row_data(index as usize))
+        else {
+            return;
+        };
+        if let Err(err) = open::that(code.text.as_str()) {
+            println!("无法打开 {}: {}", code.text, err);
+        }
+    });
 
-                // 处理取消截图
-                app.on_cancel_capture(move || {
-                    println!("取消截图");
-                    std::process::exit(0);
+    // 点击识别出的词：切换选中状态，复制选中的词
+    let app_weak = app.as_weak();
+    app.on_ocr_word_clicked(move |index| {
+        let Some(app) = app_weak.upgrade() else {
+            return;
+        };
+        let words = app.get_ocr_words();
+        let Some(mut word) = words.row_data(index as usize) else {
+            return;
+        };
+        word.selected = !word.selected;
+        words.set_row_data(index as usize, word);
+
+        let selected: Vec<ocr::Word> = words
+            .iter()
+            .filter(|word| word.selected)
+            .map(|word| ocr::Word {
+                text: word.text.to_string(),
+                x: word.x,
+                y: word.y,
+                width: word.width,
+                height: word.height,
+                line: word.line as u32,
+            })
+            .collect();
+        if !selected.is_empty() {
+            copy_text(&ocr::join(&selected));
+        }
+    });
+
+    // 打码建议：收到识别结果、确认或改回建议、忽略
+    let app_weak = app.as_weak();
+    let app_state_clone = app_state.clone();
+    let redaction_background = background_data.clone();
+    app.on_redactions_found(move |found| {
+        let Some(app) = app_weak.upgrade() else {
+            return;
+        };
+        let mut state = app_state_clone.borrow_mut();
+        let block_size = config::current().redact.block_size;
+        for redaction in found.iter() {
+            // 再次识别时不重复添加同一个位置
+            let exists = state.redactions.iter().any(|other| {
+                (other.x - redaction.x).abs() < 1.0
+                    && (other.y - redaction.y).abs() < 1.0
+                    && (other.width - redaction.width).abs() < 1.0
+                    && (other.height - redaction.height).abs() < 1.0
+            });
+            if !exists {
+                let order = state.next_order();
+                state.redactions.push(RustRedaction {
+                    x: redaction.x,
+                    y: redaction.y,
+                    width: redaction.width,
+                    height: redaction.height,
+                    block_size,
+                    label: redaction.label.to_string(),
+                    accepted: false,
+                    order,
                 });
+            }
+        }
+        sync_redactions(&app, &state, &redaction_background, (width, height));
+    });
+
+    let app_weak = app.as_weak();
+    let app_state_clone = app_state.clone();
+    let redaction_background = background_data.clone();
+    app.on_toggle_redaction(move |index| {
+        let Some(app) = app_weak.upgrade() else {
+            return;
+        };
+        let mut state = app_state_clone.borrow_mut();
+        // 确认建议相当于新建一个打码区域，排在已有标注之后
+        let order = state.next_order();
+        if let Some(redaction) = state.redactions.get_mut(index as usize) {
+            redaction.accepted = !redaction.accepted;
+            redaction.order = order;
+        }
+        sync_redactions(&app, &state, &redaction_background, (width, height));
+    });
+
+    let app_weak = app.as_weak();
+    let app_state_clone = app_state.clone();
+    let redaction_background = background_data.clone();
+    app.on_dismiss_redaction(move |index| {
+        let Some(app) = app_weak.upgrade() else {
+            return;
+        };
+        let mut state = app_state_clone.borrow_mut();
+        if (index as usize) < state.redactions.len() {
+            state.redactions.remove(index as usize);
+        }
+        sync_redactions(&app, &state, &redaction_background, (width, height));
+    });
+
+    // 处理选区预设
+    let preset_labels: Vec<slint::SharedString> = preset::PRESETS
+        .iter()
+        .map(|entry| entry.label.into())
+        .collect();
+    app.set_preset_labels(ModelRc::new(VecModel::from(preset_labels)));
+
+    let app_weak = app.as_weak();
+    let app_state_clone = app_state.clone();
+    app.on_preset_selected(move |index| {
+        if let Some(app) = app_weak.upgrade() {
+            let mut state = app_state_clone.borrow_mut();
+            state.select_preset(index as usize, width as f32, height as f32);
+
+            app.set_selected_preset(index);
+            app.set_show_preset_menu(state.show_preset_menu);
+            app.set_start_x(state.selection.start_x);
+            app.set_start_y(state.selection.start_y);
+            app.set_current_x(state.selection.current_x);
+            app.set_current_y(state.selection.current_y);
+        }
+    });
+
+    // 处理选区完成：交给输出流程（默认复制、钉图并写入历史）后关闭遮罩
+    let app_weak = app.as_weak();
+    let app_state_clone = app_state.clone();
+    let background_data_clone = background_data.clone();
+    app.on_selection_complete(move |area| {
+        let capture_x = area.x as u32;
+        let capture_y = area.y as u32;
+        let capture_width = area.width as u32;
+        let capture_height = area.height as u32;
+        if live {
+            save_last_region(area.x as i32, area.y as i32, capture_width, capture_height);
+        }
 
-                // 调试日志
-                app.on_debug_log(move |message| {
-                    println!("Debug: {}", message);
-                });
+        let pending = app_state_clone
+            .borrow()
+            .redactions
+            .iter()
+            .filter(|redaction| !redaction.accepted)
+            .count();
+        if pending > 0 {
+            println!("还有 {} 处打码建议没有确认，这些位置没有打码", pending);
+        }
 
-                app.show()?;
-                app.run()?;
+        if capture_width > 0 && capture_height > 0 {
+            let selection = crop_rgba(
+                &background_data_clone,
+                (width, height),
+                (capture_x, capture_y, capture_width, capture_height),
+            );
+            // 剪贴板、钉图等输出在后台按配置执行
+            let mut capture = pipeline::Capture::new(
+                selection,
+                (capture_width, capture_height),
+                (area.x as i32, area.y as i32),
+                screen.display_info.id,
+                app_state_clone.borrow().annotations(),
+            );
+            capture.captured_at = captured_at;
+            // 分享完成时不要用图像覆盖剪贴板中的链接
+            if app_state_clone.borrow().shared {
+                pipeline::run_shared_capture(capture);
+            } else {
+                pipeline::run_capture(capture);
             }
         }
+
+        if let Some(app) = app_weak.upgrade() {
+            let _ = app.hide();
+        }
+        lifecycle::capture_ended();
+    });
+
+    // 处理取消截图
+    let app_weak = app.as_weak();
+    app.on_cancel_capture(move || {
+        hooks::run_on_cancel(&config::current().hooks);
+        if let Some(app) = app_weak.upgrade() {
+            let _ = app.hide();
+        }
+        lifecycle::capture_ended();
+    });
+
+    // F3：把剪贴板内容钉到桌面并退出截图
+    let app_weak = app.as_weak();
+    app.on_pin_clipboard(move || {
+        if let Err(err) = pin::pin_clipboard() {
+            println!("钉图失败: {}", err);
+            return;
+        }
+        if let Some(app) = app_weak.upgrade() {
+            app.invoke_cancel_capture();
+        }
+    });
+
+    // 调试日志
+    app.on_debug_log(move |message| {
+        println!("Debug: {}", message);
+    });
+
+    // Ctrl+S：保存为可再次编辑的工程
+    let app_state_clone = app_state.clone();
+    let background_data_clone = background_data.clone();
+    app.on_save_project(move || {
+        let state = app_state_clone.borrow();
+        let project = project::Project {
+            width,
+            height,
+            screen_id: screen.display_info.id,
+            background: background_data_clone.clone(),
+            selection: state.selection_area().map(|area| project::Selection {
+                x: area.x,
+                y: area.y,
+                width: area.width,
+                height: area.height,
+            }),
+            annotations: state.annotations(),
+            captured_at: Some(captured_at),
+        };
+        let path = export::screenshot_path(project::PROJECT_EXTENSION);
+        match project.save(&path) {
+            Ok(()) => println!("工程已保存: {}", path.display()),
+            Err(err) => println!("保存工程失败: {}", err),
+        }
+    });
+
+    // 恢复工程中的标注
+    if !initial.annotations.is_empty() {
+        let mut state = app_state.borrow_mut();
+        state.restore_annotations(&initial.annotations);
+        sync_saved_objects(&app, &state);
+        sync_pen_paths(&app, &state);
+        sync_redactions(&app, &state, &background_data, (width, height));
+    }
+
+    // 预先设置选区（例如重新标注钉图或打开工程时）
+    if let Some(area) = initial.selection {
+        let mut state = app_state.borrow_mut();
+        state.selection.start_x = area.x;
+        state.selection.start_y = area.y;
+        state.selection.current_x = area.x + area.width;
+        state.selection.current_y = area.y + area.height;
+        state.selection.is_selecting = true;
+        app.set_start_x(state.selection.start_x);
+        app.set_start_y(state.selection.start_y);
+        app.set_current_x(state.selection.current_x);
+        app.set_current_y(state.selection.current_y);
+        app.set_is_selecting(true);
     }
+
+    app.show()?;
+    lifecycle::capture_started();
     Ok(())
 }
 
-// 直接从背景二进制数据提取选区
-fn extract_selection_from_background(
-    background_data: &[u8],
-    bg_width: u32,
-    bg_height: u32,
-    sel_x: u32,
-    sel_y: u32,
-    sel_width: u32,
-    sel_height: u32,
-    window_x: i32,
-    window_y: i32,
-) -> Result<(), Box<dyn Error>> {
-    if sel_width == 0 || sel_height == 0 {
-        return Ok(());
+// 滚动截图和录屏控制窗口与选区的间距
+const CONTROL_MARGIN: f32 = 12.0;
+
+// 控制窗口的位置：选区右侧，放不下时放在左侧，再放不下则放在选区上方或下方。
+// 控制窗口不能出现在选区内，否则会被截进画面。选区占满整个屏幕时无处可放，贴在屏幕底部
+fn control_position(
+    area: &SelectionArea,
+    (control_width, control_height): (f32, f32),
+    (screen_width, screen_height): (f32, f32),
+) -> LogicalPosition {
+    let right = area.x + area.width + CONTROL_MARGIN;
+    let left = area.x - control_width - CONTROL_MARGIN;
+    let side_y = area.y.min(screen_height - control_height).max(0.0);
+    if right + control_width <= screen_width {
+        return LogicalPosition::new(right, side_y);
+    }
+    if left >= 0.0 {
+        return LogicalPosition::new(left, side_y);
     }
 
-    // 直接创建选区的二进制数据
+    let x = area.x.min(screen_width - control_width).max(0.0);
+    let above = area.y - control_height - CONTROL_MARGIN;
+    let below = area.y + area.height + CONTROL_MARGIN;
+    let y = if above >= 0.0 {
+        above
+    } else if below + control_height <= screen_height {
+        below
+    } else {
+        (screen_height - control_height).max(0.0)
+    };
+    LogicalPosition::new(x, y)
+}
+
+// 从背景二进制数据中裁出选区
+fn crop_rgba(
+    background_data: &[u8],
+    (bg_width, bg_height): (u32, u32),
+    (sel_x, sel_y, sel_width, sel_height): (u32, u32, u32, u32),
+) -> Vec<u8> {
     let mut selection_data = vec![0u8; (sel_width * sel_height * 4) as usize];
 
     for y in 0..sel_height {
@@ -2216,34 +3549,682 @@ fn extract_selection_from_background(
                 let dst_idx = ((y * sel_width + x) * 4) as usize;
 
                 if src_idx + 3 < background_data.len() && dst_idx + 3 < selection_data.len() {
-                    selection_data[dst_idx] = background_data[src_idx]; // R
-                    selection_data[dst_idx + 1] = background_data[src_idx + 1]; // G
-                    selection_data[dst_idx + 2] = background_data[src_idx + 2]; // B
-                    selection_data[dst_idx + 3] = background_data[src_idx + 3]; // A
+                    selection_data[dst_idx..dst_idx + 4]
+                        .copy_from_slice(&background_data[src_idx..src_idx + 4]);
                 }
             }
         }
     }
+    selection_data
+}
 
-    // 复制到剪贴板
-    let mut clipboard = Clipboard::new()?;
-    clipboard.set_image(arboard::ImageData {
-        width: sel_width as usize,
-        height: sel_height as usize,
-        bytes: selection_data.clone().into(),
-    })?;
+// 按配置给导出的图像叠加水印、加上页脚，返回 (RGBA 数据, 宽, 高)
+fn decorate_export(
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    info: &watermark::CaptureInfo,
+) -> (Vec<u8>, u32, u32) {
+    let config = config::current();
+    watermark::decorate(data, width, height, &config.watermark, &config.footer, info)
+}
 
-    // 创建预览窗口
-    let preview = PreviewWindowState::new(selection_data, sel_width, sel_height);
-    preview
-        .borrow()
-        .window
-        .window()
-        .set_position(slint::LogicalPosition::new(
-            window_x as f32,
-            window_y as f32,
-        ));
-    preview.borrow().show();
+// 导出绘制了标注的选区图像，并把标注、选区和原图哈希写入 iTXt 块，之后可以用 --from-image 恢复。
+// 加了页脚后图像与选区对不上，只保存图像本身
+fn export_annotated_png(
+    background_data: &[u8],
+    (bg_width, bg_height): (u32, u32),
+    area: &SelectionArea,
+    annotations: Vec<Annotation>,
+    info: &watermark::CaptureInfo,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let (width, height) = (area.width as u32, area.height as u32);
+    if width == 0 || height == 0 {
+        return Err("选区为空".into());
+    }
+    let original = crop_rgba(
+        background_data,
+        (bg_width, bg_height),
+        (area.x as u32, area.y as u32, width, height),
+    );
+    let mut data = original.clone();
+    render::draw_annotations(&mut data, width, height, area.x, area.y, &annotations);
+    let (data, output_width, output_height) = decorate_export(data, width, height, info);
+    let path = export::screenshot_path("png");
+    if (output_width, output_height) != (width, height) {
+        export::save_png(&path, &data, output_width, output_height)?;
+        return Ok(path);
+    }
+
+    let image_metadata = metadata::ImageMetadata {
+        crop: project::Selection {
+            x: area.x,
+            y: area.y,
+            width: area.width,
+            height: area.height,
+        },
+        annotations,
+        original_hash: metadata::content_hash(&original),
+        captured_at: Some(info.timestamp),
+    };
+    metadata::save_png(&path, &data, (width, height), &image_metadata)?;
+    Ok(path)
+}
+
+// 把绘制了标注的选区分享到本地服务器，并把链接复制到剪贴板
+fn share_selection(
+    background_data: &[u8],
+    (bg_width, bg_height): (u32, u32),
+    area: &SelectionArea,
+    annotations: &[Annotation],
+    info: &watermark::CaptureInfo,
+) -> Result<String, Box<dyn Error>> {
+    let (width, height) = (area.width as u32, area.height as u32);
+    let mut data = crop_rgba(
+        background_data,
+        (bg_width, bg_height),
+        (area.x as u32, area.y as u32, width, height),
+    );
+    render::draw_annotations(&mut data, width, height, area.x, area.y, annotations);
+    let (data, width, height) = decorate_export(data, width, height, info);
+    let url = share::share(
+        export::png_bytes(&data, width, height)?,
+        &config::current().share,
+    )?;
+    arboard::Clipboard::new()?.set_text(url.clone())?;
+    Ok(url)
+}
+
+// 识别选区中的二维码和条码，坐标换算为屏幕坐标
+fn scan_codes(
+    background_data: &[u8],
+    (bg_width, bg_height): (u32, u32),
+    area: &SelectionArea,
+) -> Vec<slint_generatedAppWindow::DetectedCode> {
+    let (width, height) = (area.width as u32, area.height as u32);
+    let data = crop_rgba(
+        background_data,
+        (bg_width, bg_height),
+        (area.x as u32, area.y as u32, width, height),
+    );
+    barcode::scan(&data, width, height)
+        .into_iter()
+        .map(|code| {
+            println!("识别到 {}: {}", code.kind, code.text);
+            slint_generatedAppWindow::DetectedCode {
+                x: area.x + code.x,
+                y: area.y + code.y,
+                width: code.width,
+                height: code.height,
+                kind: code.kind.into(),
+                is_url: code.text.starts_with("http://") || code.text.starts_with("https://"),
+                text: code.text.into(),
+            }
+        })
+        .collect()
+}
+
+// 在后台识别选区中的文字，完成后复制全部文字，并把每个词的位置显示在选区上
+fn recognize_text(
+    app: &AppWindow,
+    recognizer: Box<dyn ocr::Recognizer>,
+    background_data: &[u8],
+    (bg_width, bg_height): (u32, u32),
+    area: &SelectionArea,
+) {
+    let (width, height) = (area.width as u32, area.height as u32);
+    let data = crop_rgba(
+        background_data,
+        (bg_width, bg_height),
+        (area.x as u32, area.y as u32, width, height),
+    );
+    let (offset_x, offset_y) = (area.x, area.y);
+    let app_weak = app.as_weak();
+    println!("正在识别文字…");
+    std::thread::spawn(move || {
+        let result = ocr::read_text(recognizer.as_ref(), &data, width, height)
+            .map_err(|err| err.to_string());
+        let _ = app_weak.upgrade_in_event_loop(move |app| match result {
+            Ok((_, words)) if words.is_empty() => println!("选区中没有识别到文字"),
+            Ok((text, words)) => {
+                copy_text(&text);
+                let words: Vec<slint_generatedAppWindow::OcrWord> = words
+                    .into_iter()
+                    .map(|word| slint_generatedAppWindow::OcrWord {
+                        x: offset_x + word.x,
+                        y: offset_y + word.y,
+                        width: word.width,
+                        height: word.height,
+                        text: word.text.into(),
+                        line: word.line as i32,
+                        selected: false,
+                    })
+                    .collect();
+                app.set_ocr_words(ModelRc::new(VecModel::from(words)));
+            }
+            Err(err) => println!("文字识别失败: {}", err),
+        });
+    });
+}
+
+// 在后台识别选区中的文字并查找敏感信息，找到的位置作为打码建议交给界面
+fn suggest_redactions(
+    app: &AppWindow,
+    recognizer: Box<dyn ocr::Recognizer>,
+    background_data: &[u8],
+    (bg_width, bg_height): (u32, u32),
+    area: &SelectionArea,
+) {
+    let patterns = config::current().redact.patterns;
+    let (width, height) = (area.width as u32, area.height as u32);
+    let data = crop_rgba(
+        background_data,
+        (bg_width, bg_height),
+        (area.x as u32, area.y as u32, width, height),
+    );
+    let (offset_x, offset_y) = (area.x, area.y);
+    let app_weak = app.as_weak();
+    println!("正在查找敏感信息…");
+    std::thread::spawn(move || {
+        let result = redact::find(recognizer.as_ref(), &data, width, height, &patterns);
+        let _ = app_weak.upgrade_in_event_loop(move |app| match result {
+            Ok(suggestions) if suggestions.is_empty() => println!("选区中没有发现敏感信息"),
+            Ok(suggestions) => {
+                println!("发现 {} 处敏感信息，请确认是否打码", suggestions.len());
+                let found: Vec<slint_generatedAppWindow::Redaction> = suggestions
+                    .into_iter()
+                    .map(|suggestion| slint_generatedAppWindow::Redaction {
+                        x: offset_x + suggestion.x,
+                        y: offset_y + suggestion.y,
+                        width: suggestion.width,
+                        height: suggestion.height,
+                        label: format!("{}: {}", suggestion.name, suggestion.text).into(),
+                        accepted: false,
+                        preview: slint::Image::default(),
+                    })
+                    .collect();
+                app.invoke_redactions_found(ModelRc::new(VecModel::from(found)));
+            }
+            Err(err) => println!("查找敏感信息失败: {}", err),
+        });
+    });
+}
+
+// 把打码区域同步到界面，已确认的区域显示打码后的图像
+fn sync_redactions(
+    app: &AppWindow,
+    state: &AppState,
+    background_data: &[u8],
+    (bg_width, bg_height): (u32, u32),
+) {
+    let redactions: Vec<_> = state
+        .redactions
+        .iter()
+        .map(|redaction| {
+            let mut preview = slint::Image::default();
+            let (x, y) = (redaction.x.max(0.0) as u32, redaction.y.max(0.0) as u32);
+            let (width, height) = (
+                redaction.width.ceil() as u32,
+                redaction.height.ceil() as u32,
+            );
+            if redaction.accepted && width > 0 && height > 0 {
+                let mut data = crop_rgba(
+                    background_data,
+                    (bg_width, bg_height),
+                    (x, y, width, height),
+                );
+                render::draw_annotations(
+                    &mut data,
+                    width,
+                    height,
+                    x as f32,
+                    y as f32,
+                    &[Annotation::Pixelate {
+                        x: redaction.x,
+                        y: redaction.y,
+                        width: redaction.width,
+                        height: redaction.height,
+                        block_size: redaction.block_size,
+                    }],
+                );
+                let mut pixel_buffer =
+                    slint::SharedPixelBuffer::<slint::Rgba8Pixel>::new(width, height);
+                pixel_buffer.make_mut_bytes().copy_from_slice(&data);
+                preview = slint::Image::from_rgba8(pixel_buffer);
+            }
+            slint_generatedAppWindow::Redaction {
+                x: redaction.x,
+                y: redaction.y,
+                width: redaction.width,
+                height: redaction.height,
+                label: redaction.label.clone().into(),
+                accepted: redaction.accepted,
+                preview,
+            }
+        })
+        .collect();
+    app.set_redactions(ModelRc::new(VecModel::from(redactions)));
+}
+
+fn copy_text(text: &str) {
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text.to_string())) {
+        Ok(()) => println!("已复制: {}", text),
+        Err(err) => println!("复制失败: {}", err),
+    }
+}
+
+// 打开导出的 PNG 继续编辑。图像带有标注数据且在截图历史中找到原图时，以原图作为背景，
+// 标注恢复为可编辑的对象；找不到原图时只打开导出的图像
+fn open_annotated_image(path: &Path) -> Result<(), Box<dyn Error>> {
+    let image = image::open(path)?.into_rgba8();
+    let (width, height) = image.dimensions();
+    let mut background = image.into_raw();
+    let mut annotations = Vec::new();
+    let mut captured_at = None;
+
+    if let Some(image_metadata) = metadata::read(path)? {
+        captured_at = image_metadata.captured_at;
+        match metadata::find_original(&image_metadata.original_hash) {
+            Some(original) if original.len() == background.len() => {
+                background = original;
+                // 标注以屏幕坐标保存，换算到图像坐标
+                annotations = image_metadata
+                    .annotations
+                    .iter()
+                    .map(|annotation| {
+                        annotation.translated(-image_metadata.crop.x, -image_metadata.crop.y)
+                    })
+                    .collect();
+            }
+            // 导出的图像已经画上了标注，再恢复成可编辑的对象会画两遍
+            _ => println!("截图历史中没有找到原图，标注已画在图像上，不能再单独编辑"),
+        }
+    }
+
+    let screen = Screen::all()?.into_iter().next().ok_or("没有找到屏幕")?;
+    open_annotation_overlay(
+        screen,
+        background,
+        width,
+        height,
+        InitialState {
+            selection: Some(SelectionArea {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32,
+                height: height as f32,
+            }),
+            annotations,
+            // 旧版本导出的图像没有记录截图时间，用文件的修改时间代替
+            captured_at: captured_at.or_else(|| history::modified_time(path)),
+        },
+        false,
+    )
+}
+
+// 导出用的选区图像（未绘制标注）及其标注
+struct ExportSource {
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    origin: (f32, f32), // 图像左上角对应的屏幕位置
+    annotations: Vec<Annotation>,
+    // 导出时页脚使用的截图信息。图像已经是导出结果（可能已有水印和页脚）时为 None，不再重复添加
+    info: Option<watermark::CaptureInfo>,
+}
+
+// 读取工程目录或带标注数据的 PNG
+fn load_export_source(input: &Path) -> Result<ExportSource, Box<dyn Error>> {
+    if input.is_dir() {
+        let project = project::Project::load(input)?;
+        let crop = project.selection.unwrap_or(project::Selection {
+            x: 0.0,
+            y: 0.0,
+            width: project.width as f32,
+            height: project.height as f32,
+        });
+        let (width, height) = (crop.width as u32, crop.height as u32);
+        let data = crop_rgba(
+            &project.background,
+            (project.width, project.height),
+            (crop.x as u32, crop.y as u32, width, height),
+        );
+        return Ok(ExportSource {
+            data,
+            width,
+            height,
+            origin: (crop.x, crop.y),
+            annotations: project.annotations,
+            info: Some(watermark::CaptureInfo {
+                timestamp: project
+                    .captured_at
+                    .or_else(|| history::modified_time(input))
+                    .unwrap_or_else(history::now),
+                screen_id: Some(project.screen_id),
+            }),
+        });
+    }
 
+    let image = image::open(input)?.into_rgba8();
+    let (width, height) = image.dimensions();
+    let data = image.into_raw();
+    let source = |data, origin, annotations, info| ExportSource {
+        data,
+        width,
+        height,
+        origin,
+        annotations,
+        info,
+    };
+    let Some(image_metadata) = metadata::read(input)? else {
+        return Ok(source(data, (0.0, 0.0), Vec::new(), None));
+    };
+    match metadata::find_original(&image_metadata.original_hash) {
+        Some(original) if original.len() == data.len() => {
+            let info = watermark::CaptureInfo {
+                timestamp: image_metadata
+                    .captured_at
+                    .or_else(|| history::modified_time(input))
+                    .unwrap_or_else(history::now),
+                screen_id: None,
+            };
+            Ok(source(
+                original,
+                (image_metadata.crop.x, image_metadata.crop.y),
+                image_metadata.annotations,
+                Some(info),
+            ))
+        }
+        // 没有原图时标注已经画在图像上，不再重复输出
+        _ => {
+            println!("截图历史中没有找到原图，标注不会以矢量形式导出");
+            Ok(source(data, (0.0, 0.0), Vec::new(), None))
+        }
+    }
+}
+
+// 把工程或带标注数据的 PNG 导出为 SVG 或绘制了标注的 PNG，格式由输出文件的扩展名决定
+fn export_file(input: &Path, output: &Path) -> Result<(), Box<dyn Error>> {
+    let is_svg = output
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("svg"));
+    if is_svg {
+        // 标注以矢量形式保留，水印和页脚加在背景图上；页脚在下方，不影响标注的位置
+        let source = load_export_source(input)?;
+        let (data, width, height) = match &source.info {
+            Some(info) => decorate_export(source.data, source.width, source.height, info),
+            None => (source.data, source.width, source.height),
+        };
+        svg::save_svg(
+            output,
+            &data,
+            (width, height),
+            source.origin,
+            &source.annotations,
+        )?;
+    } else {
+        let (data, width, height) = render_export_source(input)?;
+        export::save_png(output, &data, width, height)?;
+    }
+    println!("已导出: {}", output.display());
     Ok(())
 }
+
+// 读取工程或图片文件，绘制标注，按配置加上水印和页脚，返回 (RGBA 数据, 宽, 高)
+fn render_export_source(input: &Path) -> Result<(Vec<u8>, u32, u32), Box<dyn Error>> {
+    let mut source = load_export_source(input)?;
+    render::draw_annotations(
+        &mut source.data,
+        source.width,
+        source.height,
+        source.origin.0,
+        source.origin.1,
+        &source.annotations,
+    );
+    Ok(match &source.info {
+        Some(info) => decorate_export(source.data, source.width, source.height, info),
+        None => (source.data, source.width, source.height),
+    })
+}
+
+// 按配置把图片文件或工程上传到图床，打印链接
+fn upload_file(path: &Path) -> Result<(), Box<dyn Error>> {
+    let (data, width, height) = render_export_source(path)?;
+    let png = export::png_bytes(&data, width, height)?;
+    let upload_config = config::current().upload;
+    let url = upload::upload(&upload_config, &png)?;
+    println!("{}", url);
+    if upload_config.copy_url {
+        arboard::Clipboard::new()?.set_text(url)?;
+    }
+    Ok(())
+}
+
+// 文件输入对应的 PDF 页面，时间取文件的修改时间
+fn file_pdf_page(input: &Path) -> Result<pdf::PdfPage, Box<dyn Error>> {
+    let source = load_export_source(input)?;
+    let timestamp = history::modified_time(input).map(history::format_timestamp);
+    Ok(pdf::PdfPage {
+        data: source.data,
+        width: source.width,
+        height: source.height,
+        origin: source.origin,
+        annotations: source.annotations,
+        title: None,
+        timestamp,
+        caption: None,
+    })
+}
+
+// 把多个输入合并导出为 PDF。输入是已存在的文件或工程路径，否则当作历史记录编号
+fn export_pdf(
+    output: &Path,
+    inputs: &[String],
+    title: Option<&str>,
+    caption: Option<&str>,
+    timestamp: bool,
+) -> Result<(), Box<dyn Error>> {
+    let history = history::open_configured(&config::current().history);
+    let mut pages = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let path = Path::new(input);
+        let mut page = if path.exists() {
+            file_pdf_page(path)?
+        } else {
+            let history = history.as_ref().ok_or("截图历史未启用")?;
+            let entry = history
+                .get(input)
+                .ok_or_else(|| format!("找不到文件或历史记录: {}", input))?;
+            history.pdf_page(entry)?
+        };
+        page.title = title.map(str::to_string);
+        page.caption = caption.map(str::to_string);
+        if !timestamp {
+            page.timestamp = None;
+        }
+        pages.push(page);
+    }
+    pdf::write_pdf(output, &pages)?;
+    println!("已导出: {}", output.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restored_annotations_keep_their_order() {
+        let color = annotation::parse_hex_color(OBJECT_COLOR);
+        let annotations = vec![
+            Annotation::Arrow {
+                start_x: 10.0,
+                start_y: 10.0,
+                end_x: 50.0,
+                end_y: 40.0,
+                color,
+                stroke_width: OBJECT_BORDER_WIDTH,
+            },
+            Annotation::Pixelate {
+                x: 20.0,
+                y: 20.0,
+                width: 30.0,
+                height: 10.0,
+                block_size: 8.0,
+            },
+            Annotation::Rectangle {
+                x: 5.0,
+                y: 5.0,
+                width: 60.0,
+                height: 40.0,
+                color,
+                stroke_width: OBJECT_BORDER_WIDTH,
+            },
+            Annotation::Pen {
+                points: vec![(1.0, 1.0), (2.0, 3.0)],
+                color: [0x12, 0x34, 0x56, 255],
+                stroke_width: 3.0,
+            },
+            Annotation::Ellipse {
+                x: 30.0,
+                y: 30.0,
+                width: 20.0,
+                height: 20.0,
+                color,
+                stroke_width: OBJECT_BORDER_WIDTH,
+            },
+        ];
+
+        let mut state = AppState::default();
+        state.restore_annotations(&annotations);
+        assert_eq!(state.annotations(), annotations);
+    }
+
+    #[test]
+    fn new_annotations_follow_restored_ones() {
+        let color = annotation::parse_hex_color(OBJECT_COLOR);
+        let rectangle = Annotation::Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 10.0,
+            color,
+            stroke_width: OBJECT_BORDER_WIDTH,
+        };
+        let mut state = AppState::default();
+        state.restore_annotations(std::slice::from_ref(&rectangle));
+
+        // 先画的箭头排在后加入的打码之前
+        let order = state.next_order();
+        state.arrow_objects.push(RustArrowObject {
+            start_x: 1.0,
+            start_y: 1.0,
+            end_x: 5.0,
+            end_y: 5.0,
+            selected: false,
+            order,
+        });
+        let order = state.next_order();
+        state.redactions.push(RustRedaction {
+            x: 2.0,
+            y: 2.0,
+            width: 4.0,
+            height: 4.0,
+            block_size: 2.0,
+            label: String::new(),
+            accepted: true,
+            order,
+        });
+
+        let annotations = state.annotations();
+        assert_eq!(annotations[0], rectangle);
+        assert!(matches!(annotations[1], Annotation::Arrow { .. }));
+        assert!(matches!(annotations[2], Annotation::Pixelate { .. }));
+    }
+
+    fn area(x: f32, y: f32, width: f32, height: f32) -> SelectionArea {
+        SelectionArea {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn control_window_prefers_right_then_left() {
+        let screen = (1920.0, 1080.0);
+        let position = control_position(&area(100.0, 200.0, 400.0, 300.0), (280.0, 80.0), screen);
+        assert_eq!((position.x, position.y), (512.0, 200.0));
+        let position = control_position(&area(1400.0, 200.0, 400.0, 300.0), (280.0, 80.0), screen);
+        assert_eq!((position.x, position.y), (1108.0, 200.0));
+    }
+
+    #[test]
+    fn control_window_never_covers_full_width_selection() {
+        let screen = (1920.0, 1080.0);
+        let control = (280.0, 80.0);
+        // 上方放得下时放在上方，否则放在下方
+        let position = control_position(&area(0.0, 300.0, 1920.0, 400.0), control, screen);
+        assert_eq!((position.x, position.y), (0.0, 208.0));
+        let position = control_position(&area(0.0, 0.0, 1920.0, 600.0), control, screen);
+        assert_eq!((position.x, position.y), (0.0, 612.0));
+        for selection in [
+            area(0.0, 300.0, 1920.0, 400.0),
+            area(0.0, 0.0, 1920.0, 600.0),
+        ] {
+            let position = control_position(&selection, control, screen);
+            let overlaps =
+                position.y < selection.y + selection.height && position.y + control.1 > selection.y;
+            assert!(!overlaps);
+        }
+    }
+
+    fn preset_index(preset: SelectionPreset) -> usize {
+        preset::PRESETS
+            .iter()
+            .position(|entry| entry.preset == preset)
+            .unwrap()
+    }
+
+    #[test]
+    fn ratio_preset_keeps_ratio_when_clamped_to_screen() {
+        let mut state = AppState::default();
+        state.selection.is_selecting = true;
+        // 宽选区换成 1:1 后高度超出屏幕，整体缩小而不是只截断高度
+        state.selection.start_x = 100.0;
+        state.selection.start_y = 50.0;
+        state.selection.current_x = 1100.0;
+        state.selection.current_y = 250.0;
+        state.select_preset(
+            preset_index(SelectionPreset::Ratio(1.0, 1.0)),
+            1920.0,
+            800.0,
+        );
+
+        let width = state.selection.current_x - state.selection.start_x;
+        let height = state.selection.current_y - state.selection.start_y;
+        assert_eq!((width, height), (800.0, 800.0));
+        assert_eq!(
+            (state.selection.start_x, state.selection.start_y),
+            (100.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn size_preset_shrinks_proportionally_on_small_screen() {
+        let mut state = AppState::default();
+        state.selection.is_selecting = true;
+        state.selection.current_x = 10.0;
+        state.selection.current_y = 10.0;
+        state.select_preset(
+            preset_index(SelectionPreset::Size(1920.0, 1080.0)),
+            1280.0,
+            1024.0,
+        );
+        let width = state.selection.current_x - state.selection.start_x;
+        let height = state.selection.current_y - state.selection.start_y;
+        assert_eq!(width, 1280.0);
+        assert!((width / height - 16.0 / 9.0).abs() < 1e-3);
+    }
+}
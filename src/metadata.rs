@@ -0,0 +1,183 @@
+// 导出 PNG 中嵌入的标注数据
+//
+// 导出的图像已经绘制了标注，另外在 iTXt 块中以 JSON 保存标注列表、选区和原图的哈希。
+// 用 --from-image 打开时按哈希在截图历史中查找未绘制标注的原图，标注恢复为可编辑的对象。
+
+use crate::annotation::Annotation;
+use crate::project::{self, Selection};
+use crate::{export, history};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+const METADATA_KEYWORD: &str = "screenshot-slint";
+const METADATA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct ImageMetadata {
+    pub crop: Selection,              // 选区，屏幕坐标
+    pub annotations: Vec<Annotation>, // 屏幕坐标
+    pub original_hash: String,        // 未绘制标注的选区图像的哈希
+    pub captured_at: Option<u64>,     // 截取屏幕的 Unix 时间（秒），旧版本导出的图像没有记录
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredMetadata {
+    version: u32,
+    crop: Selection,
+    #[serde(default)]
+    annotations: Vec<serde_json::Value>,
+    original_hash: String,
+    #[serde(default)]
+    captured_at: Option<u64>,
+}
+
+// 图像内容的 64 位 FNV-1a 哈希，十六进制表示
+pub fn content_hash(data: &[u8]) -> String {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    let hash = data.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    });
+    format!("{:016x}", hash)
+}
+
+pub fn save_png(
+    path: &Path,
+    data: &[u8],
+    (width, height): (u32, u32),
+    metadata: &ImageMetadata,
+) -> Result<(), Box<dyn Error>> {
+    let stored = StoredMetadata {
+        version: METADATA_VERSION,
+        crop: metadata.crop,
+        annotations: metadata
+            .annotations
+            .iter()
+            .map(serde_json::to_value)
+            .collect::<Result<_, _>>()?,
+        original_hash: metadata.original_hash.clone(),
+        captured_at: metadata.captured_at,
+    };
+    export::save_png_with_text(
+        path,
+        data,
+        (width, height),
+        METADATA_KEYWORD,
+        &serde_json::to_string(&stored)?,
+    )
+}
+
+// 读取 PNG 中的标注数据；不是本程序导出的图像时返回 None
+pub fn read(path: &Path) -> Result<Option<ImageMetadata>, Box<dyn Error>> {
+    let Some(text) = export::read_png_text(path, METADATA_KEYWORD)? else {
+        return Ok(None);
+    };
+    let stored: StoredMetadata = serde_json::from_str(&text)?;
+    if stored.version > METADATA_VERSION {
+        return Err(format!(
+            "图像中的标注数据由更新版本的程序写入（版本 {}）",
+            stored.version
+        )
+        .into());
+    }
+    Ok(Some(ImageMetadata {
+        crop: stored.crop,
+        annotations: project::parse_annotations(stored.annotations),
+        original_hash: stored.original_hash,
+        captured_at: stored.captured_at,
+    }))
+}
+
+// 在截图历史中查找原图
+pub fn find_original(hash: &str) -> Option<Vec<u8>> {
+    let history = history::open_configured(&crate::config::current().history)?;
+    let entry = history.find_by_hash(hash)?;
+    history.load_original(entry).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("metadata-test-{}-{}", std::process::id(), name))
+    }
+
+    fn sample_metadata() -> ImageMetadata {
+        ImageMetadata {
+            crop: Selection {
+                x: 100.0,
+                y: 50.0,
+                width: 4.0,
+                height: 3.0,
+            },
+            annotations: vec![
+                Annotation::Rectangle {
+                    x: 101.0,
+                    y: 51.0,
+                    width: 2.0,
+                    height: 1.0,
+                    color: [255, 0, 0, 255],
+                    stroke_width: 1.0,
+                },
+                Annotation::Text {
+                    x: 100.0,
+                    y: 50.0,
+                    text: "说明 (1)".to_string(),
+                    color: [0, 0, 0, 255],
+                    font_size: 12.0,
+                },
+            ],
+            original_hash: content_hash(&[1, 2, 3]),
+            captured_at: Some(1_700_000_000),
+        }
+    }
+
+    #[test]
+    fn metadata_round_trips_through_png() {
+        let path = temp_path("round-trip.png");
+        let data = vec![128u8; 4 * 4 * 3];
+        let metadata = sample_metadata();
+        save_png(&path, &data, (4, 3), &metadata).unwrap();
+
+        let read_back = read(&path).unwrap().unwrap();
+        assert_eq!(read_back.crop, metadata.crop);
+        assert_eq!(read_back.annotations, metadata.annotations);
+        assert_eq!(read_back.original_hash, metadata.original_hash);
+        assert_eq!(read_back.captured_at, metadata.captured_at);
+
+        // 图像本身不受影响
+        let image = image::open(&path).unwrap().into_rgba8();
+        assert_eq!(image.dimensions(), (4, 3));
+        assert_eq!(image.into_raw(), data);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn plain_png_has_no_metadata() {
+        let path = temp_path("plain.png");
+        export::save_png(&path, &[0u8; 4], 1, 1).unwrap();
+        assert!(read(&path).unwrap().is_none());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let path = temp_path("newer.png");
+        let text = format!(
+            r#"{{"version":{},"crop":{{"x":0,"y":0,"width":1,"height":1}},"original_hash":"0"}}"#,
+            METADATA_VERSION + 1
+        );
+        export::save_png_with_text(&path, &[0u8; 4], (1, 1), METADATA_KEYWORD, &text).unwrap();
+        assert!(read(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn content_hash_depends_on_every_byte() {
+        assert_eq!(content_hash(&[]), "cbf29ce484222325");
+        assert_ne!(content_hash(&[1, 2, 3]), content_hash(&[1, 2, 4]));
+        assert_eq!(content_hash(&[1, 2, 3]).len(), 16);
+    }
+}
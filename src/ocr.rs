@@ -0,0 +1,303 @@
+// 文字识别：识别选区中的文字，得到每个词的内容和位置
+//
+// 识别引擎实现 Recognizer，界面只依赖这个 trait。目前的实现调用本地的 tesseract：
+// 配置了离线模型文件时用它所在的目录作为 tessdata 目录、文件名作为语言，否则使用
+// 系统安装的语言包。图像以 PNG 从标准输入传入，结果按 TSV 格式解析。
+
+use crate::config::OcrConfig;
+use crate::export;
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+// 识别出的一个词，坐标相对于传入图像的左上角
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub line: u32, // 所在行的序号，同一行的词序号相同
+}
+
+pub trait Recognizer: Send {
+    // 识别 RGBA 图像中的文字，按阅读顺序返回
+    fn recognize(&self, data: &[u8], width: u32, height: u32) -> Result<Vec<Word>, Box<dyn Error>>;
+}
+
+pub struct Tesseract {
+    binary: PathBuf,
+    tessdata_dir: Option<PathBuf>,
+    languages: String,
+}
+
+impl Tesseract {
+    pub fn new(config: &OcrConfig) -> Result<Self, Box<dyn Error>> {
+        let (tessdata_dir, languages) = match &config.model {
+            Some(model) => {
+                if !model.is_file() {
+                    return Err(format!("模型文件不存在: {}", model.display()).into());
+                }
+                let language = model
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .ok_or("模型文件名无效")?;
+                (model.parent().map(PathBuf::from), language.to_string())
+            }
+            None => (None, config.languages.clone()),
+        };
+        Ok(Self {
+            binary: config.tesseract.clone(),
+            tessdata_dir,
+            languages,
+        })
+    }
+}
+
+impl Recognizer for Tesseract {
+    fn recognize(&self, data: &[u8], width: u32, height: u32) -> Result<Vec<Word>, Box<dyn Error>> {
+        let png = export::png_bytes(data, width, height)?;
+        let mut command = Command::new(&self.binary);
+        command
+            .arg("stdin")
+            .arg("stdout")
+            .arg("-l")
+            .arg(&self.languages);
+        if let Some(dir) = &self.tessdata_dir {
+            command.arg("--tessdata-dir").arg(dir);
+        }
+        let mut child = command
+            .arg("tsv")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                format!(
+                    "无法执行 {}（{}），请安装 tesseract 或在配置中设置 ocr.tesseract",
+                    self.binary.display(),
+                    err
+                )
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&png)?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let message = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("tesseract 识别失败: {}", message.trim()).into());
+        }
+        Ok(parse_tsv(&String::from_utf8_lossy(&output.stdout)))
+    }
+}
+
+// 按配置创建识别引擎
+pub fn recognizer(config: &OcrConfig) -> Result<Box<dyn Recognizer>, Box<dyn Error>> {
+    Ok(Box::new(Tesseract::new(config)?))
+}
+
+// 识别图像中的文字，返回拼接好的文字和每个词
+pub fn read_text(
+    recognizer: &dyn Recognizer,
+    data: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(String, Vec<Word>), Box<dyn Error>> {
+    let words = recognizer.recognize(data, width, height)?;
+    Ok((join(&words), words))
+}
+
+// 解析 tesseract 的 TSV 输出，只保留词（level 5）。列依次为
+// level page block par line word left top width height conf text
+fn parse_tsv(tsv: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current_line = None;
+    let mut line_index = 0;
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != "5" {
+            continue;
+        }
+        let text = columns[11].trim();
+        if text.is_empty() {
+            continue;
+        }
+        let number = |index: usize| columns[index].parse::<f32>().unwrap_or(0.0);
+        let key = (columns[1], columns[2], columns[3], columns[4]);
+        if current_line.is_some_and(|line| line != key) {
+            line_index += 1;
+        }
+        current_line = Some(key);
+        words.push(Word {
+            text: text.to_string(),
+            x: number(6),
+            y: number(7),
+            width: number(8),
+            height: number(9),
+            line: line_index,
+        });
+    }
+    words
+}
+
+// 把词拼成文字：同一行的词用空格隔开（中日韩文字之间不加空格），不同行换行
+pub fn join<'a>(words: impl IntoIterator<Item = &'a Word>) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&Word> = None;
+    for word in words {
+        if let Some(previous) = previous {
+            if previous.line != word.line {
+                text.push('\n');
+            } else if !(previous.text.chars().last().is_some_and(is_cjk)
+                && word.text.chars().next().is_some_and(is_cjk))
+            {
+                text.push(' ');
+            }
+        }
+        text.push_str(&word.text);
+        previous = Some(word);
+    }
+    text
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch as u32,
+        0x3000..=0x303F   // 中文标点
+        | 0x3040..=0x30FF // 假名
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xAC00..=0xD7AF // 韩文
+        | 0xF900..=0xFAFF
+        | 0xFF00..=0xFFEF // 全角字符
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const TSV: &str = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext
+1\t1\t0\t0\t0\t0\t0\t0\t200\t100\t-1\t
+4\t1\t1\t1\t1\t0\t10\t10\t100\t20\t-1\t
+5\t1\t1\t1\t1\t1\t10\t10\t40\t20\t96.5\tHello
+5\t1\t1\t1\t1\t2\t55\t10\t50\t20\t95.1\tworld
+5\t1\t1\t1\t1\t3\t110\t10\t5\t20\t-1\t 
+5\t1\t1\t1\t2\t1\t10\t40\t30\t20\t90\t截图
+5\t1\t1\t1\t2\t2\t42\t40\t30\t20\t90\t工具
+5\t1\t2\t1\t1\t1\t10\t80\tx\t20\t90\tnext
+5\t1\t2\t1\t1
+";
+
+    // 返回固定 TSV 解析结果的识别引擎，记录收到的图像尺寸
+    struct FakeRecognizer {
+        tsv: &'static str,
+        sizes: Mutex<Vec<(u32, u32)>>,
+    }
+
+    impl Recognizer for FakeRecognizer {
+        fn recognize(
+            &self,
+            _data: &[u8],
+            width: u32,
+            height: u32,
+        ) -> Result<Vec<Word>, Box<dyn Error>> {
+            self.sizes.lock().unwrap().push((width, height));
+            Ok(parse_tsv(self.tsv))
+        }
+    }
+
+    struct FailingRecognizer;
+
+    impl Recognizer for FailingRecognizer {
+        fn recognize(&self, _: &[u8], _: u32, _: u32) -> Result<Vec<Word>, Box<dyn Error>> {
+            Err("没有安装语言包".into())
+        }
+    }
+
+    fn word(text: &str, line: u32) -> Word {
+        Word {
+            text: text.to_string(),
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 10.0,
+            line,
+        }
+    }
+
+    #[test]
+    fn parse_tsv_keeps_words_and_groups_lines() {
+        let words = parse_tsv(TSV);
+        let summary: Vec<(&str, u32)> = words
+            .iter()
+            .map(|word| (word.text.as_str(), word.line))
+            .collect();
+        // 空白的词和不完整的行被跳过，换段落或换块也算新的一行
+        assert_eq!(
+            summary,
+            vec![
+                ("Hello", 0),
+                ("world", 0),
+                ("截图", 1),
+                ("工具", 1),
+                ("next", 2)
+            ]
+        );
+        assert_eq!(
+            words[1],
+            Word {
+                text: "world".to_string(),
+                x: 55.0,
+                y: 10.0,
+                width: 50.0,
+                height: 20.0,
+                line: 0,
+            }
+        );
+        // 无法解析的数字按 0 处理
+        assert_eq!(words[4].width, 0.0);
+    }
+
+    #[test]
+    fn parse_tsv_handles_empty_output() {
+        assert!(parse_tsv("").is_empty());
+        assert!(parse_tsv(TSV.lines().next().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn join_spaces_latin_words_but_not_cjk() {
+        let words = [
+            word("Hello", 0),
+            word("world", 0),
+            word("截图", 1),
+            word("工具", 1),
+            word("v2", 1),
+            word("（测试）", 2),
+            word("完成", 2),
+        ];
+        assert_eq!(join(&words), "Hello world\n截图工具 v2\n（测试）完成");
+        assert_eq!(join(&[]), "");
+    }
+
+    #[test]
+    fn read_text_uses_given_recognizer() {
+        let recognizer = FakeRecognizer {
+            tsv: TSV,
+            sizes: Mutex::new(Vec::new()),
+        };
+        let data = vec![0u8; 30 * 20 * 4];
+        let (text, words) = read_text(&recognizer, &data, 30, 20).unwrap();
+        assert_eq!(text, "Hello world\n截图工具\nnext");
+        assert_eq!(words.len(), 5);
+        assert_eq!(*recognizer.sizes.lock().unwrap(), vec![(30, 20)]);
+    }
+
+    #[test]
+    fn read_text_reports_recognizer_errors() {
+        let err = read_text(&FailingRecognizer, &[], 0, 0).unwrap_err();
+        assert_eq!(err.to_string(), "没有安装语言包");
+    }
+}
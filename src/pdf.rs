@@ -0,0 +1,669 @@
+// PDF 导出：每张截图一页，截图作为位图背景，标注以矢量路径绘制在上面
+//
+// 直接按 PDF 1.4 的格式写出对象和交叉引用表，图像和内容流用 Flate 压缩，不依赖外部工具。
+// 页面单位取 1pt = 1px。ASCII 文字使用内置的 Helvetica 字体；其他文字（例如中文）
+// 用系统字体渲染成带透明度的位图再放到页面上。
+
+use crate::annotation::{self, Annotation};
+use crate::{render, text};
+use ab_glyph::FontVec;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use std::cell::OnceCell;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+
+const MARGIN: f32 = 24.0;
+const TITLE_SIZE: f32 = 16.0;
+const INFO_SIZE: f32 = 10.0;
+const CAPTION_SIZE: f32 = 11.0;
+const LINE_SPACING: f32 = 1.4;
+const MIN_PAGE_WIDTH: f32 = 240.0;
+// 位图文字按页面尺寸的倍数渲染，放大查看时不会太模糊
+const TEXT_RASTER_SCALE: f32 = 2.0;
+// 用三次贝塞尔曲线近似四分之一椭圆的控制点系数
+const KAPPA: f32 = 0.552_284_8;
+
+pub struct PdfPage {
+    pub data: Vec<u8>, // 未绘制标注的 RGBA 截图
+    pub width: u32,
+    pub height: u32,
+    pub origin: (f32, f32), // 截图左上角对应的屏幕位置
+    pub annotations: Vec<Annotation>,
+    pub title: Option<String>,
+    pub timestamp: Option<String>,
+    pub caption: Option<String>,
+}
+
+// 按对象编号收集对象，最后统一写出交叉引用表
+struct PdfWriter {
+    objects: Vec<Vec<u8>>,
+    // 渲染非 ASCII 文字用的系统字体，整个文档只在第一次用到时加载一次
+    font: OnceCell<Option<FontVec>>,
+}
+
+impl PdfWriter {
+    fn system_font(&self) -> Option<&FontVec> {
+        self.font.get_or_init(text::load_system_font).as_ref()
+    }
+
+    // 预留一个对象编号，之后用 set 填入内容
+    fn reserve(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    fn set(&mut self, id: usize, body: String) {
+        self.objects[id - 1] = body.into_bytes();
+    }
+
+    fn add(&mut self, body: String) -> usize {
+        let id = self.reserve();
+        self.set(id, body);
+        id
+    }
+
+    // 压缩后写入流对象，dict 是除 /Length 和 /Filter 之外的字典内容
+    fn add_stream(&mut self, dict: &str, data: &[u8]) -> Result<usize, Box<dyn Error>> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+
+        let mut body = format!(
+            "<< {} /Filter /FlateDecode /Length {} >>\nstream\n",
+            dict,
+            compressed.len()
+        )
+        .into_bytes();
+        body.extend_from_slice(&compressed);
+        body.extend_from_slice(b"\nendstream");
+        self.objects.push(body);
+        Ok(self.objects.len())
+    }
+
+    // RGBA 图像。有透明像素时附带一个灰度 SMask
+    fn add_image(&mut self, data: &[u8], width: u32, height: u32) -> Result<usize, Box<dyn Error>> {
+        let rgb: Vec<u8> = data
+            .chunks_exact(4)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+            .collect();
+        let mut dict = format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8",
+            width, height
+        );
+        if data.chunks_exact(4).any(|pixel| pixel[3] < 255) {
+            let alpha: Vec<u8> = data.chunks_exact(4).map(|pixel| pixel[3]).collect();
+            let mask = self.add_stream(
+                &format!(
+                    "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceGray /BitsPerComponent 8",
+                    width, height
+                ),
+                &alpha,
+            )?;
+            dict.push_str(&format!(" /SMask {} 0 R", mask));
+        }
+        self.add_stream(&dict, &rgb)
+    }
+
+    fn finish(self, root: usize) -> Vec<u8> {
+        let mut output = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (index, body) in self.objects.iter().enumerate() {
+            offsets.push(output.len());
+            output.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            output.extend_from_slice(body);
+            output.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = output.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            root,
+            xref
+        );
+        output.extend_from_slice(table.as_bytes());
+        output
+    }
+}
+
+// 一页的资源和内容流
+struct PageContent {
+    commands: String,
+    images: Vec<usize>,
+    // 颜色透明度（千分比）到 ExtGState 名称
+    alpha_states: BTreeMap<u16, String>,
+}
+
+impl PageContent {
+    fn draw_image(&mut self, image: usize, (x, y): (f32, f32), (width, height): (f32, f32)) {
+        let name = format!("Im{}", self.images.len() + 1);
+        self.images.push(image);
+        let _ = writeln!(
+            self.commands,
+            "q {} 0 0 {} {} {} cm /{} Do Q",
+            width, height, x, y, name
+        );
+    }
+
+    fn set_color(&mut self, color: [u8; 4], stroke: bool) {
+        let [r, g, b] = [color[0], color[1], color[2]].map(|value| value as f32 / 255.0);
+        let operator = if stroke { "RG" } else { "rg" };
+        let _ = writeln!(self.commands, "{:.3} {:.3} {:.3} {}", r, g, b, operator);
+
+        let alpha = (color[3] as u32 * 1000 / 255) as u16;
+        let count = self.alpha_states.len();
+        let name = self
+            .alpha_states
+            .entry(alpha)
+            .or_insert_with(|| format!("GS{}", count + 1));
+        let _ = writeln!(self.commands, "/{} gs", name);
+    }
+}
+
+// 只包含 ASCII 可打印字符的文字可以直接用 Helvetica 输出
+fn is_plain_ascii(text: &str) -> bool {
+    text.chars().all(|ch| (' '..='~').contains(&ch))
+}
+
+fn escape_string(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)")
+}
+
+// 用系统字体把一行文字渲染成透明背景的位图
+fn rasterize_line(font: &FontVec, text: &str, size: f32, color: [u8; 4]) -> (Vec<u8>, u32, u32) {
+    let size = size * TEXT_RASTER_SCALE;
+    let width = text::text_width(font, size, text).ceil().max(1.0) as u32;
+    let height = text::line_height(font, size).ceil().max(1.0) as u32;
+    // 透明像素也填上文字颜色，避免边缘和 SMask 合成后发暗
+    let mut data = [color[0], color[1], color[2], 0].repeat((width * height) as usize);
+    text::draw_text(
+        &mut data,
+        (width, height),
+        font,
+        size,
+        (0.0, 0.0),
+        text,
+        color,
+    );
+    (data, width, height)
+}
+
+// 在页面上写一行文字，(x, top) 是文字左上角的页面坐标
+fn draw_text_line(
+    writer: &mut PdfWriter,
+    content: &mut PageContent,
+    text: &str,
+    size: f32,
+    (x, top): (f32, f32),
+    color: [u8; 4],
+) -> Result<(), Box<dyn Error>> {
+    if is_plain_ascii(text) {
+        content.set_color(color, false);
+        // Helvetica 的上伸部约为字号的 0.72
+        let _ = writeln!(
+            content.commands,
+            "BT /F1 {} Tf {} {} Td ({}) Tj ET",
+            size,
+            x,
+            top - size * 0.72,
+            escape_string(text)
+        );
+    } else if let Some((data, width, height)) = writer
+        .system_font()
+        .map(|font| rasterize_line(font, text, size, color))
+    {
+        let image = writer.add_image(&data, width, height)?;
+        // 位图已经带有透明度，恢复为不透明，避免叠加之前标注设置的透明度
+        content.set_color([0, 0, 0, 255], false);
+        let (width, height) = (
+            width as f32 / TEXT_RASTER_SCALE,
+            height as f32 / TEXT_RASTER_SCALE,
+        );
+        content.draw_image(image, (x, top - height), (width, height));
+    } else {
+        // 没有可用的字体时无法输出这行文字，不能悄悄丢掉
+        println!(
+            "没有找到可以显示非 ASCII 文字的系统字体，PDF 中缺少文字: {}",
+            text
+        );
+    }
+    Ok(())
+}
+
+// 在内容流中绘制标注。to_page 把屏幕坐标换算成页面坐标
+fn draw_annotations(
+    writer: &mut PdfWriter,
+    content: &mut PageContent,
+    annotations: &[Annotation],
+    to_page: impl Fn(f32, f32) -> (f32, f32),
+) -> Result<(), Box<dyn Error>> {
+    let polyline = |content: &mut PageContent, points: &[(f32, f32)], close: bool| {
+        for (index, &(x, y)) in points.iter().enumerate() {
+            let (px, py) = to_page(x, y);
+            let operator = if index == 0 { "m" } else { "l" };
+            let _ = writeln!(content.commands, "{:.2} {:.2} {}", px, py, operator);
+        }
+        let _ = writeln!(content.commands, "{}", if close { "s" } else { "S" });
+    };
+
+    for annotation in annotations {
+        match annotation {
+            Annotation::Rectangle {
+                x,
+                y,
+                width,
+                height,
+                color,
+                stroke_width,
+            } => {
+                content.set_color(*color, true);
+                let _ = writeln!(content.commands, "{} w", stroke_width);
+                // 边框画在矩形内侧，与界面一致
+                let inset = stroke_width / 2.0;
+                let (left, top, right, bottom) =
+                    (x + inset, y + inset, x + width - inset, y + height - inset);
+                polyline(
+                    content,
+                    &[(left, top), (right, top), (right, bottom), (left, bottom)],
+                    true,
+                );
+            }
+            Annotation::Ellipse {
+                x,
+                y,
+                width,
+                height,
+                color,
+                stroke_width,
+            } => {
+                content.set_color(*color, true);
+                let _ = writeln!(content.commands, "{} w", stroke_width);
+                let (cx, cy) = to_page(x + width / 2.0, y + height / 2.0);
+                let rx = ((width - stroke_width) / 2.0).max(0.0);
+                let ry = ((height - stroke_width) / 2.0).max(0.0);
+                let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+                let _ = writeln!(
+                    content.commands,
+                    "{:.2} {:.2} m\n\
+                     {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c\n\
+                     {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c\n\
+                     {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c\n\
+                     {:.2} {:.2} {:.2} {:.2} {:.2} {:.2} c\ns",
+                    cx + rx,
+                    cy,
+                    cx + rx,
+                    cy + ky,
+                    cx + kx,
+                    cy + ry,
+                    cx,
+                    cy + ry,
+                    cx - kx,
+                    cy + ry,
+                    cx - rx,
+                    cy + ky,
+                    cx - rx,
+                    cy,
+                    cx - rx,
+                    cy - ky,
+                    cx - kx,
+                    cy - ry,
+                    cx,
+                    cy - ry,
+                    cx + kx,
+                    cy - ry,
+                    cx + rx,
+                    cy - ky,
+                    cx + rx,
+                    cy
+                );
+            }
+            Annotation::Arrow {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                color,
+                stroke_width,
+            } => {
+                content.set_color(*color, true);
+                let _ = writeln!(content.commands, "{} w", stroke_width);
+                let [head_a, head_b] =
+                    annotation::arrow_head_points(*start_x, *start_y, *end_x, *end_y);
+                polyline(content, &[(*start_x, *start_y), (*end_x, *end_y)], false);
+                polyline(content, &[head_a, (*end_x, *end_y), head_b], false);
+            }
+            Annotation::Pen {
+                points,
+                color,
+                stroke_width,
+            } => {
+                content.set_color(*color, true);
+                let _ = writeln!(content.commands, "{} w", stroke_width);
+                polyline(content, points, false);
+            }
+            // 已经在嵌入的截图上完成
+            Annotation::Pixelate { .. } => {}
+            Annotation::Text {
+                x,
+                y,
+                text,
+                color,
+                font_size,
+            } => {
+                draw_text_line(writer, content, text, *font_size, to_page(*x, *y), *color)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn write_page(
+    writer: &mut PdfWriter,
+    page: &PdfPage,
+    pages_id: usize,
+    font_id: usize,
+) -> Result<usize, Box<dyn Error>> {
+    let text_color = [0x33, 0x33, 0x33, 0xff];
+    let title_height = page
+        .title
+        .as_ref()
+        .map_or(0.0, |_| TITLE_SIZE * LINE_SPACING);
+    let info_height = page
+        .timestamp
+        .as_ref()
+        .map_or(0.0, |_| INFO_SIZE * LINE_SPACING);
+    let caption_lines: Vec<&str> = page
+        .caption
+        .as_deref()
+        .map(|caption| caption.lines().collect())
+        .unwrap_or_default();
+    let caption_height = caption_lines.len() as f32 * CAPTION_SIZE * LINE_SPACING;
+
+    let (image_width, image_height) = (page.width as f32, page.height as f32);
+    let page_width = (image_width + MARGIN * 2.0).max(MIN_PAGE_WIDTH);
+    let page_height = image_height + MARGIN * 2.0 + title_height + info_height + caption_height;
+    let image_x = MARGIN;
+    let image_y = MARGIN + caption_height;
+
+    let mut content = PageContent {
+        commands: String::new(),
+        images: Vec::new(),
+        alpha_states: BTreeMap::new(),
+    };
+
+    // 标题和时间在截图上方，说明文字在下方
+    let mut top = page_height - MARGIN;
+    if let Some(title) = &page.title {
+        draw_text_line(
+            writer,
+            &mut content,
+            title,
+            TITLE_SIZE,
+            (MARGIN, top),
+            text_color,
+        )?;
+        top -= title_height;
+    }
+    if let Some(timestamp) = &page.timestamp {
+        draw_text_line(
+            writer,
+            &mut content,
+            timestamp,
+            INFO_SIZE,
+            (MARGIN, top),
+            text_color,
+        )?;
+    }
+    let mut top = image_y - (CAPTION_SIZE * (LINE_SPACING - 1.0));
+    for line in &caption_lines {
+        draw_text_line(
+            writer,
+            &mut content,
+            line,
+            CAPTION_SIZE,
+            (MARGIN, top),
+            text_color,
+        )?;
+        top -= CAPTION_SIZE * LINE_SPACING;
+    }
+
+    // 截图背景，忽略透明度。打码在嵌入之前完成
+    let (origin_x, origin_y) = page.origin;
+    let mut data = page.data.clone();
+    render::apply_pixelate(
+        &mut data,
+        page.width,
+        page.height,
+        origin_x,
+        origin_y,
+        &page.annotations,
+    );
+    let opaque: Vec<u8> = data
+        .chunks_exact(4)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    let background = writer.add_image(&opaque, page.width, page.height)?;
+    content.draw_image(background, (image_x, image_y), (image_width, image_height));
+
+    // 标注裁剪到截图范围内
+    let _ = writeln!(
+        content.commands,
+        "q {} {} {} {} re W n 1 J 1 j",
+        image_x, image_y, image_width, image_height
+    );
+    draw_annotations(writer, &mut content, &page.annotations, |x, y| {
+        (
+            image_x + (x - origin_x),
+            image_y + image_height - (y - origin_y),
+        )
+    })?;
+    content.commands.push_str("Q\n");
+
+    let contents_id = writer.add_stream("", content.commands.as_bytes())?;
+    let images: String = content
+        .images
+        .iter()
+        .enumerate()
+        .map(|(index, id)| format!("/Im{} {} 0 R ", index + 1, id))
+        .collect();
+    let states: String = content
+        .alpha_states
+        .iter()
+        .map(|(alpha, name)| {
+            let alpha = *alpha as f32 / 1000.0;
+            format!("/{} << /CA {:.3} /ca {:.3} >> ", name, alpha, alpha)
+        })
+        .collect();
+    Ok(writer.add(format!(
+        "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Contents {} 0 R \
+         /Resources << /Font << /F1 {} 0 R >> /XObject << {}>> /ExtGState << {}>> >> >>",
+        pages_id, page_width, page_height, contents_id, font_id, images, states
+    )))
+}
+
+// 写出多页 PDF
+pub fn write_pdf(path: &Path, pages: &[PdfPage]) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, render_pdf(pages)?)?;
+    Ok(())
+}
+
+fn render_pdf(pages: &[PdfPage]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if pages.is_empty() {
+        return Err("没有可以导出的截图".into());
+    }
+    let mut writer = PdfWriter {
+        objects: Vec::new(),
+        font: OnceCell::new(),
+    };
+    let catalog_id = writer.reserve();
+    let pages_id = writer.reserve();
+    let font_id = writer.add(
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    );
+
+    let page_ids = pages
+        .iter()
+        .map(|page| write_page(&mut writer, page, pages_id, font_id))
+        .collect::<Result<Vec<_>, _>>()?;
+    let kids: Vec<String> = page_ids.iter().map(|id| format!("{} 0 R", id)).collect();
+    writer.set(
+        pages_id,
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            page_ids.len()
+        ),
+    );
+    writer.set(
+        catalog_id,
+        format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id),
+    );
+
+    Ok(writer.finish(catalog_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(width: u32, height: u32) -> PdfPage {
+        PdfPage {
+            data: [0x10, 0x20, 0x30, 0xff].repeat((width * height) as usize),
+            width,
+            height,
+            origin: (0.0, 0.0),
+            annotations: vec![Annotation::Rectangle {
+                x: 1.0,
+                y: 1.0,
+                width: 4.0,
+                height: 3.0,
+                color: [255, 0, 0, 128],
+                stroke_width: 1.0,
+            }],
+            title: Some("Title (draft)".to_string()),
+            timestamp: Some("2024-01-01 12:00".to_string()),
+            caption: Some("line one\nline two".to_string()),
+        }
+    }
+
+    fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+        haystack[from..]
+            .windows(needle.len())
+            .position(|window| window == needle)
+            .map(|position| from + position)
+    }
+
+    fn count(haystack: &[u8], needle: &[u8]) -> usize {
+        haystack
+            .windows(needle.len())
+            .filter(|window| *window == needle)
+            .count()
+    }
+
+    // 交叉引用表之后的文本部分
+    fn trailer_text(pdf: &[u8]) -> String {
+        let start = find(pdf, b"startxref\n", 0).unwrap();
+        String::from_utf8(pdf[start..].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn xref_offsets_point_at_objects() {
+        let pdf = render_pdf(&[page(8, 6), page(12, 4)]).unwrap();
+        assert!(pdf.starts_with(b"%PDF-1.4\n"));
+        assert!(pdf.ends_with(b"%%EOF\n"));
+
+        let xref: usize = trailer_text(&pdf).lines().nth(1).unwrap().parse().unwrap();
+        assert!(pdf[xref..].starts_with(b"xref\n"));
+        let table = String::from_utf8_lossy(&pdf[xref..]).into_owned();
+        let mut lines = table.lines().skip(1);
+        let header: Vec<usize> = lines
+            .next()
+            .unwrap()
+            .split(' ')
+            .map(|value| value.parse().unwrap())
+            .collect();
+        assert_eq!(header[0], 0);
+        let size = header[1];
+        assert_eq!(lines.next(), Some("0000000000 65535 f "));
+
+        for id in 1..size {
+            let entry = lines.next().unwrap();
+            assert_eq!(entry.len(), 19);
+            assert!(entry.ends_with(" 00000 n "));
+            let offset: usize = entry[..10].parse().unwrap();
+            let expected = format!("{} 0 obj\n", id);
+            assert!(
+                pdf[offset..].starts_with(expected.as_bytes()),
+                "对象 {}",
+                id
+            );
+        }
+        assert_eq!(lines.next(), Some("trailer"));
+        assert!(table.contains(&format!("/Size {} /Root 1 0 R", size)));
+        // 之后的对象编号不存在
+        assert!(find(&pdf, format!("\n{} 0 obj", size).as_bytes(), 0).is_none());
+    }
+
+    #[test]
+    fn page_count_matches_input() {
+        let pdf = render_pdf(&[page(8, 6), page(12, 4), page(3, 3)]).unwrap();
+        assert!(find(&pdf, b"/Type /Pages /Kids [", 0).is_some());
+        assert!(find(&pdf, b"/Count 3 >>", 0).is_some());
+        assert_eq!(count(&pdf, b"/Type /Page /Parent 2 0 R"), 3);
+    }
+
+    #[test]
+    fn transparent_colors_use_ext_g_state() {
+        let pdf = render_pdf(&[page(8, 6)]).unwrap();
+        assert!(find(&pdf, b"/ExtGState << /GS", 0).is_some());
+        assert!(find(&pdf, b"/CA 0.501 /ca 0.501", 0).is_some());
+    }
+
+    #[test]
+    fn transparent_images_get_soft_mask() {
+        let mut writer = PdfWriter {
+            objects: Vec::new(),
+            font: OnceCell::new(),
+        };
+        let opaque = writer.add_image(&[1, 2, 3, 255], 1, 1).unwrap();
+        assert!(!String::from_utf8_lossy(&writer.objects[opaque - 1]).contains("/SMask"));
+        let image = writer.add_image(&[1, 2, 3, 128], 1, 1).unwrap();
+        let dict = String::from_utf8_lossy(&writer.objects[image - 1]).into_owned();
+        assert!(dict.contains(&format!("/SMask {} 0 R", image - 1)));
+        assert!(
+            String::from_utf8_lossy(&writer.objects[image - 2]).contains("/ColorSpace /DeviceGray")
+        );
+    }
+
+    #[test]
+    fn escape_string_escapes_delimiters() {
+        assert_eq!(escape_string("plain"), "plain");
+        assert_eq!(escape_string(r"a\b"), r"a\\b");
+        assert_eq!(escape_string("f(x)"), r"f\(x\)");
+        assert_eq!(escape_string(r"\)"), r"\\\)");
+    }
+
+    #[test]
+    fn plain_ascii_excludes_control_and_unicode() {
+        assert!(is_plain_ascii("Hello, world ~"));
+        assert!(!is_plain_ascii("截图"));
+        assert!(!is_plain_ascii("tab\there"));
+    }
+
+    #[test]
+    fn empty_document_is_an_error() {
+        assert!(render_pdf(&[]).is_err());
+    }
+}
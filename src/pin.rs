@@ -0,0 +1,526 @@
+// 钉在桌面上的截图窗口：滚轮缩放、Ctrl+滚轮调整不透明度、旋转翻转、美化预览、鼠标穿透和右键菜单
+
+use crate::config::BeautifyPreset;
+use crate::ipc::PinInfo;
+use crate::{
+    PreviewWindow, SelectionArea, beautify, clipboard, config, export, lifecycle, platform, share,
+    text, watermark,
+};
+use arboard::Clipboard;
+use screenshots::Screen;
+use slint::{ComponentHandle, ModelRc, VecModel};
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::path::Path;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 8.0;
+const ZOOM_STEP: f32 = 1.1;
+const MIN_OPACITY: f32 = 0.1;
+const OPACITY_STEP: f32 = 0.1;
+// 鼠标穿透时左上角保留的可点击区域，与界面中的按钮尺寸一致
+const CLICK_THROUGH_HANDLE_SIZE: f32 = 16.0;
+
+struct Pin {
+    id: u32,
+    window: PreviewWindow,
+    // 未美化的图像（已应用旋转和翻转）
+    source: Vec<u8>,
+    source_width: u32,
+    source_height: u32,
+    // 正在预览的美化预设
+    beautify: Option<BeautifyPreset>,
+    // 当前显示的图像
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    // 导出时页脚记录的截图信息
+    info: watermark::CaptureInfo,
+}
+
+// 所有打开的钉图窗口。窗口由登记表持有，关闭时从表中移除后才释放
+struct PinRegistry {
+    pins: Vec<Rc<RefCell<Pin>>>,
+}
+
+impl PinRegistry {
+    fn register(&mut self, pin: Rc<RefCell<Pin>>) {
+        self.pins.push(pin);
+    }
+
+    fn unregister(&mut self, pin: &Rc<RefCell<Pin>>) {
+        self.pins.retain(|other| !Rc::ptr_eq(other, pin));
+    }
+}
+
+thread_local! {
+    static PINS: RefCell<PinRegistry> = const { RefCell::new(PinRegistry { pins: Vec::new() }) };
+    static NEXT_PIN_ID: Cell<u32> = const { Cell::new(1) };
+}
+
+pub fn pin_count() -> usize {
+    PINS.with(|registry| registry.borrow().pins.len())
+}
+
+// 所有钉图的编号和位置
+pub fn list_pins() -> Vec<PinInfo> {
+    PINS.with(|registry| {
+        registry
+            .borrow()
+            .pins
+            .iter()
+            .map(|pin| {
+                let pin = pin.borrow();
+                let area = pin.screen_area();
+                PinInfo {
+                    id: pin.id,
+                    x: area.x,
+                    y: area.y,
+                    width: area.width,
+                    height: area.height,
+                }
+            })
+            .collect()
+    })
+}
+
+fn to_slint_image(data: &[u8], width: u32, height: u32) -> slint::Image {
+    let mut pixel_buffer = slint::SharedPixelBuffer::<slint::Rgba8Pixel>::new(width, height);
+    pixel_buffer.make_mut_bytes().copy_from_slice(data);
+    slint::Image::from_rgba8(pixel_buffer)
+}
+
+// 顺时针旋转 90°
+fn rotate_clockwise(data: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut rotated = vec![0u8; data.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let src = ((y * width + x) * 4) as usize;
+            let dst_x = height - 1 - y;
+            let dst_y = x;
+            let dst = ((dst_y * height + dst_x) * 4) as usize;
+            rotated[dst..dst + 4].copy_from_slice(&data[src..src + 4]);
+        }
+    }
+    rotated
+}
+
+fn flip(data: &mut [u8], width: u32, height: u32, horizontal: bool) {
+    let row_bytes = (width * 4) as usize;
+    if horizontal {
+        for row in data.chunks_exact_mut(row_bytes) {
+            for x in 0..(width / 2) as usize {
+                let mirror = width as usize - 1 - x;
+                for channel in 0..4 {
+                    row.swap(x * 4 + channel, mirror * 4 + channel);
+                }
+            }
+        }
+    } else {
+        for y in 0..(height / 2) as usize {
+            let mirror = height as usize - 1 - y;
+            let (top, bottom) = data.split_at_mut(mirror * row_bytes);
+            top[y * row_bytes..(y + 1) * row_bytes].swap_with_slice(&mut bottom[..row_bytes]);
+        }
+    }
+}
+
+impl Pin {
+    // 由原图和美化预设重新生成显示的图像
+    fn refresh_image(&mut self) {
+        (self.data, self.width, self.height) = match &self.beautify {
+            Some(preset) => {
+                beautify::beautify(&self.source, self.source_width, self.source_height, preset)
+            }
+            None => (self.source.clone(), self.source_width, self.source_height),
+        };
+        self.window
+            .set_screenshot(to_slint_image(&self.data, self.width, self.height));
+    }
+
+    fn rotate(&mut self, quarter_turns: i32) {
+        for _ in 0..quarter_turns.rem_euclid(4) {
+            self.source = rotate_clockwise(&self.source, self.source_width, self.source_height);
+            std::mem::swap(&mut self.source_width, &mut self.source_height);
+        }
+        self.refresh_image();
+    }
+
+    fn flip(&mut self, horizontal: bool) {
+        flip(
+            &mut self.source,
+            self.source_width,
+            self.source_height,
+            horizontal,
+        );
+        self.refresh_image();
+    }
+
+    // 预览美化效果，name 为空时恢复原图。移动窗口使原图保持在屏幕上的原位置
+    fn set_beautify(&mut self, name: &str) {
+        let preset = if name.is_empty() {
+            None
+        } else {
+            match config::current().beautify.presets.get(name) {
+                Some(preset) => Some(preset.clone()),
+                None => {
+                    println!("没有名为 {} 的美化预设", name);
+                    return;
+                }
+            }
+        };
+        let offset = |preset: &Option<BeautifyPreset>| {
+            preset.as_ref().map_or((0, 0), beautify::content_offset)
+        };
+        let (old_x, old_y) = offset(&self.beautify);
+        let (new_x, new_y) = offset(&preset);
+        self.beautify = preset;
+        self.window.set_beautify_preset(name.into());
+        self.refresh_image();
+
+        let zoom = self.window.get_zoom();
+        let window = self.window.window();
+        let position = window.position().to_logical(window.scale_factor());
+        window.set_position(slint::LogicalPosition::new(
+            position.x + (old_x as f32 - new_x as f32) * zoom,
+            position.y + (old_y as f32 - new_y as f32) * zoom,
+        ));
+    }
+
+    // 以鼠标位置为中心缩放，保持鼠标下的像素不动
+    fn zoom(&self, delta: f32, mouse_x: f32, mouse_y: f32) {
+        let old_zoom = self.window.get_zoom();
+        let new_zoom = if delta > 0.0 {
+            old_zoom * ZOOM_STEP
+        } else {
+            old_zoom / ZOOM_STEP
+        }
+        .clamp(MIN_ZOOM, MAX_ZOOM);
+        if new_zoom == old_zoom {
+            return;
+        }
+
+        let window = self.window.window();
+        let position = window.position().to_logical(window.scale_factor());
+        let factor = new_zoom / old_zoom;
+        window.set_position(slint::LogicalPosition::new(
+            position.x + mouse_x - mouse_x * factor,
+            position.y + mouse_y - mouse_y * factor,
+        ));
+        self.window.set_zoom(new_zoom);
+    }
+
+    fn adjust_opacity(&self, delta: f32) {
+        let step = if delta > 0.0 {
+            OPACITY_STEP
+        } else {
+            -OPACITY_STEP
+        };
+        let opacity = (self.window.get_image_opacity() + step).clamp(MIN_OPACITY, 1.0);
+        self.window.set_image_opacity(opacity);
+    }
+
+    fn toggle_click_through(&self) {
+        let enabled = !self.window.get_click_through();
+        let window = self.window.window();
+        let handle_size = (CLICK_THROUGH_HANDLE_SIZE * window.scale_factor()).ceil() as u16;
+        if platform::set_click_through(window, enabled, handle_size, handle_size) {
+            self.window.set_click_through(enabled);
+        } else {
+            println!("当前平台不支持鼠标穿透");
+        }
+    }
+
+    // 复制、分享和另存为使用的图像：在未美化的图像上叠加水印和页脚，再按当前预设美化
+    fn export_image(&self) -> (Vec<u8>, u32, u32) {
+        let config = config::current();
+        let (data, width, height) = watermark::decorate(
+            self.source.clone(),
+            self.source_width,
+            self.source_height,
+            &config.watermark,
+            &config.footer,
+            &self.info,
+        );
+        match &self.beautify {
+            Some(preset) => beautify::beautify(&data, width, height, preset),
+            None => (data, width, height),
+        }
+    }
+
+    fn copy_to_clipboard(&self) {
+        let (data, width, height) = self.export_image();
+        let image = clipboard::ClipboardImage::new(&data, width, height);
+        if let Err(err) = clipboard::copy(&image, &config::current().clipboard.pin) {
+            println!("复制失败: {}", err);
+        }
+    }
+
+    // 放到本地分享服务器，链接复制到剪贴板
+    fn copy_share_link(&self) {
+        let (data, width, height) = self.export_image();
+        let result = export::png_bytes(&data, width, height)
+            .and_then(|png| share::share(png, &config::current().share))
+            .and_then(|url| {
+                Clipboard::new()?.set_text(url.clone())?;
+                Ok(url)
+            });
+        match result {
+            Ok(url) => println!("分享链接已复制: {}", url),
+            Err(err) => println!("分享失败: {}", err),
+        }
+    }
+
+    fn save_as(&self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name("screenshot.png")
+            .add_filter("PNG", &["png"])
+            .save_file()
+        else {
+            return;
+        };
+        let (data, width, height) = self.export_image();
+        match export::save_png(&path, &data, width, height) {
+            Ok(()) => println!("已保存: {}", path.display()),
+            Err(err) => println!("保存失败: {}", err),
+        }
+    }
+
+    // 重新标注：以钉图的图像（已旋转、翻转，未美化）为背景打开标注界面，整张图作为选区。
+    // 不重新截屏，钉图窗口和缩放都不会影响结果；美化由输出管道按配置重新添加
+    fn annotate_again(&self) -> Result<(), Box<dyn Error>> {
+        let screen = Screen::all()?.into_iter().next().ok_or("没有找到屏幕")?;
+        crate::open_annotation_overlay(
+            screen,
+            self.source.clone(),
+            self.source_width,
+            self.source_height,
+            crate::InitialState {
+                selection: Some(SelectionArea {
+                    x: 0.0,
+                    y: 0.0,
+                    width: self.source_width as f32,
+                    height: self.source_height as f32,
+                }),
+                captured_at: Some(self.info.timestamp),
+                ..Default::default()
+            },
+            false,
+        )
+    }
+
+    // 窗口在屏幕上占据的区域
+    fn screen_area(&self) -> SelectionArea {
+        let window = self.window.window();
+        let scale = window.scale_factor();
+        let position = window.position().to_logical(scale);
+        let size = window.size().to_logical(scale);
+        SelectionArea {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        }
+    }
+}
+
+// 关闭一个钉图。在窗口自身的回调里调用时，窗口要等回调返回后再释放；
+// 最后一个钉图关闭后退出程序
+fn close_pin(pin: &Rc<RefCell<Pin>>) {
+    let _ = pin.borrow().window.hide();
+    let pin = pin.clone();
+    slint::Timer::single_shot(Duration::ZERO, move || {
+        PINS.with(|registry| registry.borrow_mut().unregister(&pin));
+        lifecycle::quit_if_idle();
+    });
+}
+
+pub fn close_all() {
+    let pins = PINS.with(|registry| registry.borrow().pins.clone());
+    for pin in &pins {
+        close_pin(pin);
+    }
+}
+
+// 按编号关闭钉图，返回找到并关闭的数量
+pub fn close_pins(ids: &[u32]) -> usize {
+    let pins: Vec<_> = PINS.with(|registry| {
+        registry
+            .borrow()
+            .pins
+            .iter()
+            .filter(|pin| ids.contains(&pin.borrow().id))
+            .cloned()
+            .collect()
+    });
+    for pin in &pins {
+        close_pin(pin);
+    }
+    pins.len()
+}
+
+// 为回调包装一层：窗口关闭后 Pin 已释放，回调直接忽略
+fn with_pin(pin: &Weak<RefCell<Pin>>, action: impl FnOnce(&Rc<RefCell<Pin>>)) {
+    if let Some(pin) = pin.upgrade() {
+        action(&pin);
+    }
+}
+
+// 在屏幕 (x, y) 处打开一个钉图窗口。data 是未美化的图像，beautify_preset 不为空时
+// 用这个预设美化显示，窗口向左上移动使原图仍在 (x, y)
+pub fn open_pin(
+    data: Vec<u8>,
+    width: u32,
+    height: u32,
+    x: f32,
+    y: f32,
+    beautify_preset: &str,
+    info: watermark::CaptureInfo,
+) -> Result<(), slint::PlatformError> {
+    let window = PreviewWindow::new()?;
+    let beautify_presets = config::current().beautify.presets;
+    let presets: Vec<slint::SharedString> =
+        beautify_presets.keys().map(|name| name.into()).collect();
+    window.set_beautify_presets(ModelRc::new(VecModel::from(presets)));
+    let preset = beautify_presets.get(beautify_preset).cloned();
+    if preset.is_some() {
+        window.set_beautify_preset(beautify_preset.into());
+    }
+    let (offset_x, offset_y) = preset.as_ref().map_or((0, 0), beautify::content_offset);
+    window.window().set_position(slint::LogicalPosition::new(
+        x - offset_x as f32,
+        y - offset_y as f32,
+    ));
+
+    let id = NEXT_PIN_ID.with(|next| {
+        let id = next.get();
+        next.set(id + 1);
+        id
+    });
+    let pin = Rc::new(RefCell::new(Pin {
+        id,
+        window: window.clone_strong(),
+        source: data,
+        source_width: width,
+        source_height: height,
+        beautify: preset,
+        data: Vec::new(),
+        width,
+        height,
+        info,
+    }));
+    pin.borrow_mut().refresh_image();
+    let weak = Rc::downgrade(&pin);
+
+    let pin_weak = weak.clone();
+    window.on_close_window(move || with_pin(&pin_weak, close_pin));
+
+    window.on_close_all(close_all);
+
+    let pin_weak = weak.clone();
+    window.on_move_window(move |offset_x, offset_y| {
+        with_pin(&pin_weak, |pin| {
+            let pin = pin.borrow();
+            let window = pin.window.window();
+            let pos = window.position().to_logical(window.scale_factor());
+            window.set_position(slint::LogicalPosition::new(
+                pos.x + offset_x,
+                pos.y + offset_y,
+            ));
+        })
+    });
+
+    let pin_weak = weak.clone();
+    window.on_wheel_zoom(move |delta, mouse_x, mouse_y| {
+        with_pin(&pin_weak, |pin| pin.borrow().zoom(delta, mouse_x, mouse_y))
+    });
+
+    let pin_weak = weak.clone();
+    window.on_wheel_opacity(move |delta| {
+        with_pin(&pin_weak, |pin| pin.borrow().adjust_opacity(delta))
+    });
+
+    let pin_weak = weak.clone();
+    window.on_rotate(move |quarter_turns| {
+        with_pin(&pin_weak, |pin| pin.borrow_mut().rotate(quarter_turns))
+    });
+
+    let pin_weak = weak.clone();
+    window.on_flip(move |horizontal| with_pin(&pin_weak, |pin| pin.borrow_mut().flip(horizontal)));
+
+    let pin_weak = weak.clone();
+    window.on_pin_to_desktop(move || {
+        with_pin(&pin_weak, |pin| {
+            let pin = pin.borrow();
+            pin.window.set_pinned(!pin.window.get_pinned());
+        })
+    });
+
+    let pin_weak = weak.clone();
+    window.on_toggle_click_through(move || {
+        with_pin(&pin_weak, |pin| pin.borrow().toggle_click_through())
+    });
+
+    let pin_weak = weak.clone();
+    window.on_copy_image(move || with_pin(&pin_weak, |pin| pin.borrow().copy_to_clipboard()));
+
+    let pin_weak = weak.clone();
+    window.on_copy_share_link(move || with_pin(&pin_weak, |pin| pin.borrow().copy_share_link()));
+
+    let pin_weak = weak.clone();
+    window.on_beautify(move |name| with_pin(&pin_weak, |pin| pin.borrow_mut().set_beautify(&name)));
+
+    let pin_weak = weak.clone();
+    window.on_save_image(move || with_pin(&pin_weak, |pin| pin.borrow().save_as()));
+
+    let pin_weak = weak.clone();
+    window.on_annotate_again(move || {
+        with_pin(&pin_weak, |pin| {
+            let result = pin.borrow().annotate_again();
+            match result {
+                Ok(()) => close_pin(pin),
+                Err(err) => println!("无法打开标注界面: {}", err),
+            }
+        })
+    });
+
+    window.show()?;
+    PINS.with(|registry| registry.borrow_mut().register(pin));
+    Ok(())
+}
+
+// 在鼠标位置打开钉图，取不到鼠标位置时放在屏幕左上角附近
+fn open_pin_at_cursor(data: Vec<u8>, width: u32, height: u32) -> Result<(), Box<dyn Error>> {
+    let (x, y) = platform::cursor_position().unwrap_or((100.0, 100.0));
+    let info = watermark::CaptureInfo {
+        timestamp: crate::history::now(),
+        screen_id: None,
+    };
+    open_pin(data, width, height, x, y, "", info)?;
+    Ok(())
+}
+
+// 把剪贴板中的图片钉到桌面；剪贴板里是文字时渲染成便签
+pub fn pin_clipboard() -> Result<(), Box<dyn Error>> {
+    let mut clipboard = Clipboard::new()?;
+    if let Ok(image) = clipboard.get_image() {
+        return open_pin_at_cursor(
+            image.bytes.into_owned(),
+            image.width as u32,
+            image.height as u32,
+        );
+    }
+
+    let text = clipboard.get_text().map_err(|_| "剪贴板中没有图片或文字")?;
+    let (data, width, height) = text::render_note(&text).ok_or("无法渲染剪贴板文字")?;
+    open_pin_at_cursor(data, width, height)
+}
+
+// 把图片文件钉到桌面
+pub fn pin_file(path: &Path) -> Result<(), Box<dyn Error>> {
+    let image = image::open(path)?.into_rgba8();
+    let (width, height) = image.dimensions();
+    open_pin_at_cursor(image.into_raw(), width, height)
+}
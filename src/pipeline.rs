@@ -0,0 +1,513 @@
+// 截图完成后的输出流程
+//
+// 截图完成后先叠加水印和页脚，再按配置的预设美化（加边距、背景、阴影等），然后按配置的顺序
+// 依次执行一组输出（Sink）：复制到剪贴板、保存文件、钉到桌面、执行外部命令、HTTP 上传、
+// 写入截图历史。输出之前执行 after-capture 钩子，截图第一次保存为文件后执行 after-save 钩子，
+// 钩子失败时跳过之后的输出。输出在后台线程中执行，不阻塞界面；需要界面的输出（钉图）通过
+// slint::invoke_from_event_loop 回到界面线程。每个输出的结果都会打印出来，并在全部完成后
+// 用一条桌面通知汇总。新的输出实现 Sink 即可接入。
+
+use crate::annotation::Annotation;
+use crate::config::{
+    self, BeautifyPreset, Config, FooterConfig, HistoryConfig, HooksConfig, SinkConfig,
+    UploadConfig, WatermarkConfig,
+};
+use crate::{
+    beautify, clipboard, export, history, hooks, lifecycle, metadata, pin, project, render, upload,
+    watermark,
+};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+// 一次截图及各输出之间共享的结果
+pub struct Capture {
+    pub original: Vec<u8>, // 未绘制标注的 RGBA
+    pub data: Vec<u8>,     // 绘制了标注的 RGBA，美化后是加上边框的图像
+    pub width: u32,
+    pub height: u32,
+    pub selection_size: (u32, u32), // 选区尺寸，与 original 对应
+    pub x: i32,                     // 截图左上角的屏幕位置
+    pub y: i32,
+    pub screen_id: u32,
+    pub captured_at: u64, // 截取屏幕的 Unix 时间（秒），默认为创建 Capture 的时间
+    pub annotations: Vec<Annotation>,
+    pub path: Option<PathBuf>, // 已保存的文件，由保存输出或需要文件的输出写入
+}
+
+impl Capture {
+    pub fn new(
+        original: Vec<u8>,
+        (width, height): (u32, u32),
+        (x, y): (i32, i32),
+        screen_id: u32,
+        annotations: Vec<Annotation>,
+    ) -> Self {
+        let mut capture = Self {
+            original,
+            data: Vec::new(),
+            width,
+            height,
+            selection_size: (width, height),
+            x,
+            y,
+            screen_id,
+            captured_at: history::now(),
+            annotations,
+            path: None,
+        };
+        capture.data = capture.annotated();
+        capture
+    }
+
+    // 绘制了标注、还没有加水印和美化的选区图像
+    fn annotated(&self) -> Vec<u8> {
+        let mut data = self.original.clone();
+        let (width, height) = self.selection_size;
+        render::draw_annotations(
+            &mut data,
+            width,
+            height,
+            self.x as f32,
+            self.y as f32,
+            &self.annotations,
+        );
+        data
+    }
+
+    // 叠加水印，在下方加上页脚。与美化一样只改变输出的图像
+    fn decorate(&mut self, watermark_config: &WatermarkConfig, footer_config: &FooterConfig) {
+        (self.data, self.width, self.height) = watermark::decorate(
+            std::mem::take(&mut self.data),
+            self.width,
+            self.height,
+            watermark_config,
+            footer_config,
+            &self.info(),
+        );
+    }
+
+    // 页脚和钉图导出时使用的截图信息
+    fn info(&self) -> watermark::CaptureInfo {
+        watermark::CaptureInfo {
+            timestamp: self.captured_at,
+            screen_id: Some(self.screen_id),
+        }
+    }
+
+    // 按预设美化输出的图像，原图和标注保持不变
+    fn beautify(&mut self, preset: &BeautifyPreset) {
+        let (data, width, height) = beautify::beautify(&self.data, self.width, self.height, preset);
+        self.data = data;
+        self.width = width;
+        self.height = height;
+    }
+
+    // 保存为带标注数据的 PNG，之后可以用 open --from-image 继续编辑。
+    // 加了页脚或美化后的图像与选区对不上，只保存图像本身
+    fn save_to(&mut self, path: PathBuf) -> Result<&Path, Box<dyn Error>> {
+        if self.selection_size != (self.width, self.height) {
+            export::save_png(&path, &self.data, self.width, self.height)?;
+            return Ok(self.path.insert(path).as_path());
+        }
+        let image_metadata = metadata::ImageMetadata {
+            crop: project::Selection {
+                x: self.x as f32,
+                y: self.y as f32,
+                width: self.selection_size.0 as f32,
+                height: self.selection_size.1 as f32,
+            },
+            annotations: self.annotations.clone(),
+            original_hash: metadata::content_hash(&self.original),
+            captured_at: Some(self.captured_at),
+        };
+        metadata::save_png(
+            &path,
+            &self.data,
+            (self.width, self.height),
+            &image_metadata,
+        )?;
+        Ok(self.path.insert(path).as_path())
+    }
+
+    // 已保存的文件；还没有保存过时先保存到系统图片目录
+    pub fn ensure_saved(&mut self) -> Result<&Path, Box<dyn Error>> {
+        match self.path.take() {
+            Some(path) => Ok(self.path.insert(path).as_path()),
+            None => self.save_to(export::screenshot_path("png")),
+        }
+    }
+}
+
+pub trait Sink: Send {
+    // 显示在结果中的名称
+    fn name(&self) -> &'static str;
+    // 执行输出，成功时返回显示给用户的说明
+    fn run(&self, capture: &mut Capture) -> Result<String, Box<dyn Error>>;
+}
+
+struct ClipboardSink {
+    formats: Vec<String>,
+}
+
+impl Sink for ClipboardSink {
+    fn name(&self) -> &'static str {
+        "剪贴板"
+    }
+
+    fn run(&self, capture: &mut Capture) -> Result<String, Box<dyn Error>> {
+        // 复制文件路径时使用与保存输出相同的文件，不另外保存一份
+        if self
+            .formats
+            .iter()
+            .any(|format| clipboard::needs_path(format))
+        {
+            capture.ensure_saved()?;
+        }
+        let mut image =
+            clipboard::ClipboardImage::new(&capture.data, capture.width, capture.height);
+        if let Some(path) = &capture.path {
+            image = image.with_path(path);
+        }
+        let format = clipboard::copy(&image, &self.formats)?;
+        Ok(format!("已复制（{}）", format))
+    }
+}
+
+struct SaveSink {
+    dir: Option<PathBuf>,
+}
+
+impl Sink for SaveSink {
+    fn name(&self) -> &'static str {
+        "保存"
+    }
+
+    fn run(&self, capture: &mut Capture) -> Result<String, Box<dyn Error>> {
+        // 剪贴板等输出已经保存过时沿用那个文件
+        if let Some(path) = &capture.path {
+            return Ok(format!("已保存: {}", path.display()));
+        }
+        let path = match &self.dir {
+            Some(dir) => {
+                std::fs::create_dir_all(dir)?;
+                export::screenshot_path_in(dir, "png")
+            }
+            None => export::screenshot_path("png"),
+        };
+        let path = capture.save_to(path)?;
+        Ok(format!("已保存: {}", path.display()))
+    }
+}
+
+struct PinSink {
+    beautify_preset: String,
+}
+
+impl Sink for PinSink {
+    fn name(&self) -> &'static str {
+        "钉图"
+    }
+
+    // 钉图窗口只能在界面线程创建，等待界面线程返回结果。钉图保留未美化的图像，
+    // 由钉图窗口按配置的预设美化，之后还可以换成别的预设
+    fn run(&self, capture: &mut Capture) -> Result<String, Box<dyn Error>> {
+        let (sender, receiver) = mpsc::channel();
+        let data = capture.annotated();
+        let (width, height) = capture.selection_size;
+        let (x, y) = (capture.x as f32, capture.y as f32);
+        let preset = self.beautify_preset.clone();
+        let info = capture.info();
+        slint::invoke_from_event_loop(move || {
+            let result = pin::open_pin(data, width, height, x, y, &preset, info)
+                .map_err(|err| err.to_string());
+            let _ = sender.send(result);
+        })?;
+        receiver.recv()??;
+        Ok("已钉到桌面".to_string())
+    }
+}
+
+struct CommandSink {
+    command: String,
+}
+
+impl Sink for CommandSink {
+    fn name(&self) -> &'static str {
+        "外部命令"
+    }
+
+    fn run(&self, capture: &mut Capture) -> Result<String, Box<dyn Error>> {
+        let path = capture.ensure_saved()?;
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .arg("screenshot-slint")
+            .arg(path)
+            .env("SCREENSHOT_PATH", path)
+            .status()
+            .map_err(|err| format!("无法执行 {}: {}", self.command, err))?;
+        if !status.success() {
+            return Err(format!("{} 退出码 {}", self.command, status).into());
+        }
+        Ok(format!("已执行: {}", self.command))
+    }
+}
+
+struct UploadSink {
+    config: UploadConfig,
+}
+
+impl Sink for UploadSink {
+    fn name(&self) -> &'static str {
+        "上传"
+    }
+
+    fn run(&self, capture: &mut Capture) -> Result<String, Box<dyn Error>> {
+        let png = export::png_bytes(&capture.data, capture.width, capture.height)?;
+        let url = upload::upload(&self.config, &png)?;
+        if self.config.copy_url {
+            arboard::Clipboard::new()?.set_text(url.clone())?;
+            return Ok(format!("{}（链接已复制）", url));
+        }
+        Ok(url)
+    }
+}
+
+struct HistorySink {
+    config: HistoryConfig,
+}
+
+impl Sink for HistorySink {
+    fn name(&self) -> &'static str {
+        "截图历史"
+    }
+
+    fn run(&self, capture: &mut Capture) -> Result<String, Box<dyn Error>> {
+        let mut history = history::open_configured(&self.config).ok_or("截图历史未启用")?;
+        let mut entry = history::HistoryEntry::new(
+            capture.x,
+            capture.y,
+            capture.selection_size.0,
+            capture.selection_size.1,
+            capture.screen_id,
+        );
+        entry.annotations = capture.annotations.clone();
+        entry.outputs.extend(capture.path.clone());
+        history.add(&capture.original, entry)?;
+        history.apply_retention(&self.config)?;
+        Ok("已记录".to_string())
+    }
+}
+
+// 按配置创建输出。clipboard_formats 是该操作默认的剪贴板格式
+fn build_sinks(
+    sinks: &[SinkConfig],
+    clipboard_formats: &[String],
+    config: &Config,
+) -> Vec<Box<dyn Sink>> {
+    sinks
+        .iter()
+        // 截图历史默认不启用，未启用时跳过历史输出而不是每次都报错
+        .filter(|sink| config.history.enabled || !matches!(sink, SinkConfig::History))
+        .map(|sink| -> Box<dyn Sink> {
+            match sink {
+                SinkConfig::Clipboard { formats } => Box::new(ClipboardSink {
+                    formats: if formats.is_empty() {
+                        clipboard_formats.to_vec()
+                    } else {
+                        formats.clone()
+                    },
+                }),
+                SinkConfig::Save { dir } => Box::new(SaveSink { dir: dir.clone() }),
+                SinkConfig::Pin => Box::new(PinSink {
+                    beautify_preset: config.beautify.preset.clone(),
+                }),
+                SinkConfig::Command { command } => Box::new(CommandSink {
+                    command: command.clone(),
+                }),
+                SinkConfig::Upload { url } => {
+                    let mut upload = config.upload.clone();
+                    if let Some(url) = url {
+                        upload.url = url.clone();
+                    }
+                    Box::new(UploadSink { config: upload })
+                }
+                SinkConfig::History => Box::new(HistorySink {
+                    config: config.history.clone(),
+                }),
+            }
+        })
+        .collect()
+}
+
+// 执行钩子，只有配置了命令时才编码 PNG
+fn run_hook(config: &HooksConfig, event: &str, capture: &Capture) -> Result<(), String> {
+    if hooks::commands(config, event).is_empty() {
+        return Ok(());
+    }
+    let png = export::png_bytes(&capture.data, capture.width, capture.height)
+        .map_err(|err| err.to_string())?;
+    hooks::run(
+        config,
+        event,
+        &hooks::HookContext {
+            path: capture.path.as_deref(),
+            geometry: Some((
+                capture.x,
+                capture.y,
+                capture.selection_size.0,
+                capture.selection_size.1,
+            )),
+            screen_id: Some(capture.screen_id),
+            png: Some(&png),
+        },
+    )
+}
+
+// 在后台线程中依次执行输出。执行期间算作一个进行中的截图流程，进程不会提前退出
+fn run(capture: Capture, sinks: Vec<Box<dyn Sink>>, config: Config) {
+    let beautify = beautify_preset(&config);
+    let hooks_config = config.hooks;
+    let notify = config.pipeline.notify;
+    lifecycle::capture_started();
+    std::thread::spawn(move || {
+        let mut capture = capture;
+        capture.decorate(&config.watermark, &config.footer);
+        if let Some(preset) = &beautify {
+            capture.beautify(preset);
+        }
+        let mut report = Vec::with_capacity(sinks.len());
+        let mut hook_result = run_hook(&hooks_config, hooks::AFTER_CAPTURE, &capture);
+        let mut pending = sinks.iter();
+        while hook_result.is_ok() {
+            let Some(sink) = pending.next() else {
+                break;
+            };
+            let saved = capture.path.is_some();
+            match sink.run(&mut capture) {
+                Ok(message) => report.push(format!("{}: {}", sink.name(), message)),
+                Err(err) => report.push(format!("{}失败: {}", sink.name(), err)),
+            }
+            if let Some(line) = report.last() {
+                println!("{}", line);
+            }
+            if !saved && capture.path.is_some() {
+                hook_result = run_hook(&hooks_config, hooks::AFTER_SAVE, &capture);
+            }
+        }
+        if let Err(err) = hook_result {
+            println!("{}", err);
+            report.push(format!("钩子失败，跳过之后的输出: {}", err));
+        }
+        if notify && !report.is_empty() {
+            send_notification(&report.join("\n"));
+        }
+        let _ = slint::invoke_from_event_loop(lifecycle::capture_ended);
+    });
+}
+
+// 选区截图完成：标注界面、capture --region、滚动截图
+pub fn run_capture(capture: Capture) {
+    let config = config::current();
+    let sinks = build_sinks(&config.pipeline.capture, &config.clipboard.capture, &config);
+    run(capture, sinks, config);
+}
+
+// 分享后完成选区截图：剪贴板里已经是分享链接，跳过剪贴板输出，其余输出照常执行
+pub fn run_shared_capture(capture: Capture) {
+    let config = config::current();
+    let sinks: Vec<SinkConfig> = config
+        .pipeline
+        .capture
+        .iter()
+        .filter(|sink| !matches!(sink, SinkConfig::Clipboard { .. }))
+        .cloned()
+        .collect();
+    let sinks = build_sinks(&sinks, &config.clipboard.capture, &config);
+    run(capture, sinks, config);
+}
+
+// 全屏截图完成
+pub fn run_fullscreen(capture: Capture) {
+    let config = config::current();
+    let sinks = build_sinks(
+        &config.pipeline.fullscreen,
+        &config.clipboard.fullscreen,
+        &config,
+    );
+    run(capture, sinks, config);
+}
+
+// 配置中选择的美化预设，未选择时不美化
+fn beautify_preset(config: &Config) -> Option<BeautifyPreset> {
+    let name = &config.beautify.preset;
+    if name.is_empty() {
+        return None;
+    }
+    let preset = config.beautify.presets.get(name).cloned();
+    if preset.is_none() {
+        println!("没有名为 {} 的美化预设，不做美化", name);
+    }
+    preset
+}
+
+fn send_notification(body: &str) {
+    let result = notify_rust::Notification::new()
+        .summary("截图完成")
+        .body(body)
+        .show();
+    if let Err(err) = result {
+        println!("无法显示通知: {}", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pipeline-test-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn capture() -> Capture {
+        Capture::new(vec![255u8; 4 * 2 * 2], (2, 2), (0, 0), 0, Vec::new())
+    }
+
+    #[test]
+    fn save_writes_once_and_is_reused() {
+        let dir = temp_dir("save");
+        let sink = SaveSink {
+            dir: Some(dir.clone()),
+        };
+        let mut capture = capture();
+        sink.run(&mut capture).unwrap();
+        let path = capture.path.clone().unwrap();
+        assert!(path.starts_with(&dir));
+
+        // 再次保存（例如剪贴板已经保存过）不会产生第二个文件
+        sink.run(&mut capture).unwrap();
+        assert_eq!(capture.path.as_ref(), Some(&path));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn save_keeps_file_written_by_earlier_sink() {
+        let dir = temp_dir("reuse");
+        let existing = dir.join("existing.png");
+        let mut capture = capture();
+        capture.save_to(existing.clone()).unwrap();
+        SaveSink {
+            dir: Some(dir.clone()),
+        }
+        .run(&mut capture)
+        .unwrap();
+        assert_eq!(capture.path, Some(existing));
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,175 @@
+// 平台相关的辅助功能
+
+use crate::detect::DetectedRect;
+
+// 从 X11 窗口树读取可见窗口的位置和大小
+#[cfg(target_os = "linux")]
+pub fn window_regions() -> Vec<DetectedRect> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{ConnectionExt, MapState, Window};
+
+    let Ok((conn, screen_num)) = x11rb::connect(None) else {
+        return Vec::new();
+    };
+    let root = conn.setup().roots[screen_num].root;
+
+    let window_rect = |window: Window| -> Option<DetectedRect> {
+        let attributes = conn.get_window_attributes(window).ok()?.reply().ok()?;
+        if attributes.map_state != MapState::VIEWABLE {
+            return None;
+        }
+        let geometry = conn.get_geometry(window).ok()?.reply().ok()?;
+        let origin = conn
+            .translate_coordinates(window, root, 0, 0)
+            .ok()?
+            .reply()
+            .ok()?;
+        Some(DetectedRect {
+            x: origin.dst_x as f32,
+            y: origin.dst_y as f32,
+            width: geometry.width as f32,
+            height: geometry.height as f32,
+        })
+    };
+
+    let children = |window: Window| -> Vec<Window> {
+        conn.query_tree(window)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|reply| reply.children)
+            .unwrap_or_default()
+    };
+
+    // 顶层窗口（通常是窗口管理器的边框）以及其中的客户端窗口
+    let mut regions = Vec::new();
+    for top_level in children(root) {
+        let Some(rect) = window_rect(top_level) else {
+            continue;
+        };
+        regions.push(rect);
+        for client in children(top_level) {
+            if let Some(rect) = window_rect(client) {
+                regions.push(rect);
+            }
+        }
+    }
+    regions
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn window_regions() -> Vec<DetectedRect> {
+    Vec::new()
+}
+
+// 通过 XTest 在指定位置模拟向下滚动鼠标滚轮，返回是否成功
+#[cfg(target_os = "linux")]
+pub fn scroll_down_at(x: f32, y: f32, clicks: u32) -> bool {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT, ConnectionExt as _};
+    use x11rb::protocol::xtest::ConnectionExt as _;
+
+    // 滚轮向下对应 5 号按键
+    const WHEEL_DOWN: u8 = 5;
+
+    let Ok((conn, screen_num)) = x11rb::connect(None) else {
+        return false;
+    };
+    let root = conn.setup().roots[screen_num].root;
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        conn.warp_pointer(x11rb::NONE, root, 0, 0, 0, 0, x as i16, y as i16)?;
+        for _ in 0..clicks {
+            conn.xtest_fake_input(BUTTON_PRESS_EVENT, WHEEL_DOWN, 0, root, 0, 0, 0)?;
+            conn.xtest_fake_input(BUTTON_RELEASE_EVENT, WHEEL_DOWN, 0, root, 0, 0, 0)?;
+        }
+        conn.flush()?;
+        Ok(())
+    })();
+    result.is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn scroll_down_at(_x: f32, _y: f32, _clicks: u32) -> bool {
+    false
+}
+
+// 设置窗口的鼠标穿透。开启后只有左上角 keep_width × keep_height（物理像素）的区域
+// 仍然响应鼠标，返回是否成功
+#[cfg(target_os = "linux")]
+pub fn set_click_through(
+    window: &slint::Window,
+    enabled: bool,
+    keep_width: u16,
+    keep_height: u16,
+) -> bool {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use x11rb::connection::Connection;
+    use x11rb::protocol::shape::{ConnectionExt as _, SK, SO};
+    use x11rb::protocol::xproto::{ClipOrdering, Rectangle};
+
+    let handle = window.window_handle();
+    let Ok(raw) = handle.window_handle() else {
+        return false;
+    };
+    let xid = match raw.as_raw() {
+        RawWindowHandle::Xlib(handle) => handle.window as u32,
+        RawWindowHandle::Xcb(handle) => handle.window.get(),
+        _ => return false,
+    };
+
+    let Ok((conn, _)) = x11rb::connect(None) else {
+        return false;
+    };
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        if enabled {
+            let keep = Rectangle {
+                x: 0,
+                y: 0,
+                width: keep_width,
+                height: keep_height,
+            };
+            conn.shape_rectangles(
+                SO::SET,
+                SK::INPUT,
+                ClipOrdering::UNSORTED,
+                xid,
+                0,
+                0,
+                &[keep],
+            )?;
+        } else {
+            // 清除输入区域的形状，恢复为整个窗口
+            conn.shape_mask(SO::SET, SK::INPUT, xid, 0, 0, x11rb::NONE)?;
+        }
+        conn.flush()?;
+        Ok(())
+    })();
+    result.is_ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_click_through(
+    _window: &slint::Window,
+    _enabled: bool,
+    _keep_width: u16,
+    _keep_height: u16,
+) -> bool {
+    false
+}
+
+// 当前鼠标在屏幕上的位置
+#[cfg(target_os = "linux")]
+pub fn cursor_position() -> Option<(f32, f32)> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::ConnectionExt;
+
+    let (conn, screen_num) = x11rb::connect(None).ok()?;
+    let root = conn.setup().roots[screen_num].root;
+    let pointer = conn.query_pointer(root).ok()?.reply().ok()?;
+    Some((pointer.root_x as f32, pointer.root_y as f32))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn cursor_position() -> Option<(f32, f32)> {
+    None
+}
@@ -0,0 +1,268 @@
+// 选区比例锁定与固定尺寸预设
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionPreset {
+    Free,
+    Ratio(f32, f32), // 固定宽高比
+    Size(f32, f32),  // 固定像素尺寸
+}
+
+impl SelectionPreset {
+    // 宽高比（宽 / 高），自由模式返回 None
+    pub fn ratio(&self) -> Option<f32> {
+        match *self {
+            SelectionPreset::Free => None,
+            SelectionPreset::Ratio(w, h) | SelectionPreset::Size(w, h) => Some(w / h),
+        }
+    }
+}
+
+pub struct PresetEntry {
+    pub label: &'static str,
+    pub preset: SelectionPreset,
+}
+
+// 预设菜单中的选项，顺序即菜单顺序
+pub const PRESETS: &[PresetEntry] = &[
+    PresetEntry {
+        label: "自由",
+        preset: SelectionPreset::Free,
+    },
+    PresetEntry {
+        label: "16:9",
+        preset: SelectionPreset::Ratio(16.0, 9.0),
+    },
+    PresetEntry {
+        label: "4:3",
+        preset: SelectionPreset::Ratio(4.0, 3.0),
+    },
+    PresetEntry {
+        label: "1:1",
+        preset: SelectionPreset::Ratio(1.0, 1.0),
+    },
+    PresetEntry {
+        label: "1920×1080",
+        preset: SelectionPreset::Size(1920.0, 1080.0),
+    },
+    PresetEntry {
+        label: "1280×720",
+        preset: SelectionPreset::Size(1280.0, 720.0),
+    },
+    PresetEntry {
+        label: "1080×1080 Instagram",
+        preset: SelectionPreset::Size(1080.0, 1080.0),
+    },
+    PresetEntry {
+        label: "1080×1920 Story",
+        preset: SelectionPreset::Size(1080.0, 1920.0),
+    },
+    PresetEntry {
+        label: "1200×675 X / Twitter",
+        preset: SelectionPreset::Size(1200.0, 675.0),
+    },
+    PresetEntry {
+        label: "1200×630 Facebook",
+        preset: SelectionPreset::Size(1200.0, 630.0),
+    },
+];
+
+// 可移动的范围：(左, 上, 右, 下)
+pub type Bounds = (f32, f32, f32, f32);
+
+// 根据锚点和鼠标位置计算受约束的对角点，保持拖动方向不变。
+// 超出 bounds 时先按边界截断，再缩小另一边，使比例（或固定尺寸的比例）保持不变
+pub fn constrain_corner(
+    anchor_x: f32,
+    anchor_y: f32,
+    x: f32,
+    y: f32,
+    preset: SelectionPreset,
+    (left, top, right, bottom): Bounds,
+) -> (f32, f32) {
+    let dx = x - anchor_x;
+    let dy = y - anchor_y;
+    let sign_x = if dx < 0.0 { -1.0 } else { 1.0 };
+    let sign_y = if dy < 0.0 { -1.0 } else { 1.0 };
+
+    let (width, height) = match preset {
+        SelectionPreset::Free => {
+            return (x.clamp(left, right), y.clamp(top, bottom));
+        }
+        SelectionPreset::Size(width, height) => (width, height),
+        SelectionPreset::Ratio(..) => {
+            let ratio = preset.ratio().unwrap_or(1.0);
+            // 以拖动幅度较大的方向为准
+            if dx.abs() / ratio >= dy.abs() {
+                (dx.abs(), dx.abs() / ratio)
+            } else {
+                (dy.abs() * ratio, dy.abs())
+            }
+        }
+    };
+
+    // 锚点到拖动方向上边界的距离
+    let available_x = if sign_x > 0.0 {
+        right - anchor_x
+    } else {
+        anchor_x - left
+    };
+    let available_y = if sign_y > 0.0 {
+        bottom - anchor_y
+    } else {
+        anchor_y - top
+    };
+    let mut scale: f32 = 1.0;
+    if width > available_x {
+        scale = scale.min(available_x.max(0.0) / width);
+    }
+    if height > available_y {
+        scale = scale.min(available_y.max(0.0) / height);
+    }
+    (
+        anchor_x + sign_x * width * scale,
+        anchor_y + sign_y * height * scale,
+    )
+}
+
+// 按比例调整边框：角点模式以对角为锚点并收缩到比例内，边模式保持拖动边的对边不动。
+// 边模式推算出的另一边超出 bounds 时，截断它并按比例缩小拖动的这一边
+pub fn constrain_box(
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    handle: &str,
+    ratio: f32,
+    (_, _, right, bottom): Bounds,
+) -> (f32, f32, f32, f32) {
+    match handle {
+        "e" | "w" => {
+            let new_height = (width / ratio).min((bottom - y).max(0.0));
+            let new_width = new_height * ratio;
+            // 拖动左边时右边不动
+            let new_x = if handle == "w" {
+                x + width - new_width
+            } else {
+                x
+            };
+            (new_x, y, new_width, new_height)
+        }
+        "n" | "s" => {
+            let new_width = (height * ratio).min((right - x).max(0.0));
+            let new_height = new_width / ratio;
+            // 拖动上边时下边不动
+            let new_y = if handle == "n" {
+                y + height - new_height
+            } else {
+                y
+            };
+            (x, new_y, new_width, new_height)
+        }
+        _ => {
+            // 收缩到比例内，结果不会超出原来的边框
+            let new_width = width.min(height * ratio);
+            let new_height = new_width / ratio;
+            let new_x = match handle {
+                "nw" | "sw" => x + width - new_width,
+                _ => x,
+            };
+            let new_y = match handle {
+                "nw" | "ne" => y + height - new_height,
+                _ => y,
+            };
+            (new_x, new_y, new_width, new_height)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Bounds = (0.0, 0.0, 1000.0, 600.0);
+
+    fn assert_close(actual: (f32, f32, f32, f32), expected: (f32, f32, f32, f32)) {
+        let pairs = [
+            (actual.0, expected.0),
+            (actual.1, expected.1),
+            (actual.2, expected.2),
+            (actual.3, expected.3),
+        ];
+        assert!(
+            pairs.iter().all(|(a, b)| (a - b).abs() < 0.01),
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn corner_keeps_ratio_inside_screen() {
+        let ratio = SelectionPreset::Ratio(16.0, 9.0);
+        assert_eq!(
+            constrain_corner(100.0, 100.0, 260.0, 110.0, ratio, SCREEN),
+            (260.0, 190.0)
+        );
+        // 向下的一边碰到屏幕底部，宽度按比例缩小
+        let (x, y) = constrain_corner(100.0, 400.0, 900.0, 410.0, ratio, SCREEN);
+        assert!((y - 600.0).abs() < 0.01);
+        assert!(((x - 100.0) / (y - 400.0) - 16.0 / 9.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn corner_shrinks_fixed_size_at_screen_edge() {
+        let size = SelectionPreset::Size(400.0, 200.0);
+        assert_eq!(
+            constrain_corner(900.0, 100.0, 950.0, 150.0, size, SCREEN),
+            (1000.0, 150.0)
+        );
+        assert_eq!(
+            constrain_corner(500.0, 100.0, 450.0, 50.0, size, SCREEN),
+            (300.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn corner_free_mode_only_clamps() {
+        assert_eq!(
+            constrain_corner(10.0, 10.0, 1200.0, -5.0, SelectionPreset::Free, SCREEN),
+            (1000.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn edge_handles_clamp_then_keep_ratio() {
+        // 没有碰到边界时保持原来的行为
+        assert_close(
+            constrain_box(100.0, 100.0, 160.0, 50.0, "e", 16.0 / 9.0, SCREEN),
+            (100.0, 100.0, 160.0, 90.0),
+        );
+        // 向下推算的高度超出屏幕，截断后缩小宽度
+        assert_close(
+            constrain_box(100.0, 500.0, 320.0, 50.0, "e", 16.0 / 9.0, SCREEN),
+            (100.0, 500.0, 177.78, 100.0),
+        );
+        // 拖动左边时右边不动
+        assert_close(
+            constrain_box(100.0, 500.0, 320.0, 50.0, "w", 16.0 / 9.0, SCREEN),
+            (242.22, 500.0, 177.78, 100.0),
+        );
+        // 向右推算的宽度超出屏幕，拖动上边时下边不动
+        assert_close(
+            constrain_box(900.0, 100.0, 50.0, 300.0, "n", 1.0, SCREEN),
+            (900.0, 300.0, 100.0, 100.0),
+        );
+    }
+
+    #[test]
+    fn corner_handles_shrink_towards_anchor() {
+        assert_close(
+            constrain_box(100.0, 100.0, 300.0, 100.0, "nw", 1.0, SCREEN),
+            (300.0, 100.0, 100.0, 100.0),
+        );
+        assert_close(
+            constrain_box(100.0, 100.0, 300.0, 100.0, "se", 1.0, SCREEN),
+            (100.0, 100.0, 100.0, 100.0),
+        );
+    }
+}
@@ -0,0 +1,302 @@
+// 可再次编辑的工程文件
+//
+// 工程是一个目录：background.png 是没有绘制标注的整屏截图，project.json 记录选区和按顺序
+// 排列的标注及其样式。project.json 带有版本号，旧版本的文件读取时先迁移到当前版本；
+// 无法识别的标注类型（例如更新版本加入的图形）会被跳过，而不是让整个工程无法打开。
+
+use crate::annotation::Annotation;
+use crate::export;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+pub const PROJECT_VERSION: u32 = 1;
+pub const PROJECT_EXTENSION: &str = "ssproj";
+const MANIFEST_FILE: &str = "project.json";
+const BACKGROUND_FILE: &str = "background.png";
+
+// 选区，屏幕坐标
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Selection {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub width: u32,
+    pub height: u32,
+    pub screen_id: u32,
+    pub background: Vec<u8>, // RGBA
+    pub selection: Option<Selection>,
+    pub annotations: Vec<Annotation>,
+    pub captured_at: Option<u64>, // 截取屏幕的 Unix 时间（秒），旧版本的工程没有记录
+}
+
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    version: u32,
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    screen_id: u32,
+    background: String,
+    #[serde(default)]
+    selection: Option<Selection>,
+    // 逐个解析，单个标注无法识别时不影响其他标注
+    #[serde(default)]
+    annotations: Vec<serde_json::Value>,
+    #[serde(default)]
+    captured_at: Option<u64>,
+}
+
+// 把旧版本的 project.json 迁移到当前版本。目前只有第 1 版
+fn migrate(manifest: serde_json::Value, version: u32) -> Result<serde_json::Value, String> {
+    match version {
+        PROJECT_VERSION => Ok(manifest),
+        _ => Err(format!("不支持的工程版本: {}", version)),
+    }
+}
+
+impl Project {
+    pub fn save(&self, dir: &Path) -> Result<(), Box<dyn Error>> {
+        std::fs::create_dir_all(dir)?;
+        export::save_png(
+            &dir.join(BACKGROUND_FILE),
+            &self.background,
+            self.width,
+            self.height,
+        )?;
+
+        let manifest = Manifest {
+            version: PROJECT_VERSION,
+            width: self.width,
+            height: self.height,
+            screen_id: self.screen_id,
+            background: BACKGROUND_FILE.to_string(),
+            selection: self.selection,
+            annotations: self
+                .annotations
+                .iter()
+                .map(serde_json::to_value)
+                .collect::<Result<_, _>>()?,
+            captured_at: self.captured_at,
+        };
+        std::fs::write(
+            dir.join(MANIFEST_FILE),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(dir.join(MANIFEST_FILE))
+            .map_err(|err| format!("无法读取工程 {}: {}", dir.display(), err))?;
+        let value: serde_json::Value = serde_json::from_str(&text)?;
+        let version = value
+            .get("version")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or("工程文件缺少版本号")? as u32;
+        if version > PROJECT_VERSION {
+            return Err(format!(
+                "工程由更新版本的程序创建（版本 {}），请升级后再打开",
+                version
+            )
+            .into());
+        }
+        let manifest: Manifest = serde_json::from_value(migrate(value, version)?)?;
+
+        let background = image::open(dir.join(&manifest.background))?.into_rgba8();
+        if background.dimensions() != (manifest.width, manifest.height) {
+            return Err("工程背景图的尺寸与记录不符".into());
+        }
+
+        let annotations = parse_annotations(manifest.annotations);
+        Ok(Self {
+            width: manifest.width,
+            height: manifest.height,
+            screen_id: manifest.screen_id,
+            background: background.into_raw(),
+            selection: manifest.selection,
+            annotations,
+            captured_at: manifest.captured_at,
+        })
+    }
+}
+
+// 逐个解析标注，跳过无法识别的类型
+pub fn parse_annotations(values: Vec<serde_json::Value>) -> Vec<Annotation> {
+    let total = values.len();
+    let annotations: Vec<Annotation> = values
+        .into_iter()
+        .filter_map(|value| serde_json::from_value(value).ok())
+        .collect();
+    if annotations.len() < total {
+        println!("跳过了 {} 个无法识别的标注", total - annotations.len());
+    }
+    annotations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    const WIDTH: u32 = 6;
+    const HEIGHT: u32 = 4;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "project-test-{}-{}.{}",
+            std::process::id(),
+            name,
+            PROJECT_EXTENSION
+        ))
+    }
+
+    fn all_annotations() -> Vec<Annotation> {
+        let color = [0x12, 0x34, 0x56, 0xff];
+        vec![
+            Annotation::Rectangle {
+                x: 1.0,
+                y: 1.0,
+                width: 3.0,
+                height: 2.0,
+                color,
+                stroke_width: 2.0,
+            },
+            Annotation::Ellipse {
+                x: 0.5,
+                y: 0.5,
+                width: 4.0,
+                height: 3.0,
+                color,
+                stroke_width: 1.0,
+            },
+            Annotation::Arrow {
+                start_x: 0.0,
+                start_y: 0.0,
+                end_x: 5.0,
+                end_y: 3.0,
+                color,
+                stroke_width: 3.0,
+            },
+            Annotation::Pen {
+                points: vec![(0.0, 0.0), (1.5, 2.5), (4.0, 1.0)],
+                color: [0xff, 0, 0, 0x80],
+                stroke_width: 4.0,
+            },
+            Annotation::Pixelate {
+                x: 2.0,
+                y: 1.0,
+                width: 2.0,
+                height: 2.0,
+                block_size: 2.0,
+            },
+            Annotation::Text {
+                x: 1.0,
+                y: 2.0,
+                text: "标注 text".to_string(),
+                color,
+                font_size: 14.0,
+            },
+        ]
+    }
+
+    fn sample_project() -> Project {
+        Project {
+            width: WIDTH,
+            height: HEIGHT,
+            screen_id: 7,
+            background: (0..WIDTH * HEIGHT * 4).map(|value| value as u8).collect(),
+            selection: Some(Selection {
+                x: 1.0,
+                y: 0.0,
+                width: 4.0,
+                height: 3.0,
+            }),
+            annotations: all_annotations(),
+            captured_at: Some(1_700_000_000),
+        }
+    }
+
+    // 修改 project.json 后重新写回
+    fn edit_manifest(dir: &Path, edit: impl FnOnce(&mut serde_json::Value)) {
+        let path = dir.join(MANIFEST_FILE);
+        let mut value: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        edit(&mut value);
+        std::fs::write(&path, value.to_string()).unwrap();
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = temp_dir("round-trip");
+        let project = sample_project();
+        project.save(&dir).unwrap();
+
+        let loaded = Project::load(&dir).unwrap();
+        assert_eq!((loaded.width, loaded.height), (WIDTH, HEIGHT));
+        assert_eq!(loaded.screen_id, project.screen_id);
+        assert_eq!(loaded.background, project.background);
+        assert_eq!(loaded.selection, project.selection);
+        assert_eq!(loaded.annotations, project.annotations);
+        assert_eq!(loaded.captured_at, project.captured_at);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let dir = temp_dir("newer");
+        sample_project().save(&dir).unwrap();
+        edit_manifest(&dir, |manifest| {
+            manifest["version"] = (PROJECT_VERSION + 1).into();
+        });
+        let err = Project::load(&dir).unwrap_err();
+        assert!(err.to_string().contains("更新版本"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_version_is_rejected() {
+        let dir = temp_dir("no-version");
+        sample_project().save(&dir).unwrap();
+        edit_manifest(&dir, |manifest| {
+            manifest.as_object_mut().unwrap().remove("version");
+        });
+        assert!(Project::load(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn unknown_fields_and_annotations_are_skipped() {
+        let dir = temp_dir("unknown");
+        sample_project().save(&dir).unwrap();
+        edit_manifest(&dir, |manifest| {
+            manifest["theme"] = "dark".into();
+            manifest.as_object_mut().unwrap().remove("captured_at");
+            let annotations = manifest["annotations"].as_array_mut().unwrap();
+            annotations.insert(1, serde_json::json!({ "type": "star", "points": 5 }));
+            annotations.push(serde_json::json!({ "type": "rectangle", "x": "bad" }));
+            annotations[0]["shadow"] = true.into();
+        });
+
+        let loaded = Project::load(&dir).unwrap();
+        assert_eq!(loaded.annotations, all_annotations());
+        assert_eq!(loaded.captured_at, None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn background_size_must_match_manifest() {
+        let dir = temp_dir("size");
+        sample_project().save(&dir).unwrap();
+        edit_manifest(&dir, |manifest| {
+            manifest["width"] = (WIDTH + 1).into();
+        });
+        assert!(Project::load(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,174 @@
+// 区域录制的控制流程：定时截取选区，停止后编码保存
+
+use crate::annotation::Annotation;
+use crate::config::RecordingConfig;
+use crate::recording::{self, RecordFormat, Recorder, ScreenFrameSource};
+use crate::{RecordingWindow, SelectionArea};
+use screenshots::Screen;
+use slint::{ComponentHandle, Timer, TimerMode};
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 开始录制前等待遮罩窗口消失
+const START_DELAY_MS: u64 = 300;
+// 控制窗口尺寸
+const CONTROL_WIDTH: f32 = 240.0;
+const CONTROL_HEIGHT: f32 = 80.0;
+
+// 开始录制选区。annotations 会叠加到每一帧上；保存成功后把文件路径交给 on_finish，
+// 取消时调用 on_cancel
+pub fn start_recording(
+    screen: Screen,
+    area: SelectionArea,
+    annotations: Vec<Annotation>,
+    config: RecordingConfig,
+    on_finish: impl Fn(Result<PathBuf, String>) + 'static,
+    on_cancel: impl Fn() + 'static,
+) -> Result<(), slint::PlatformError> {
+    let fps = config.fps.clamp(1, 60);
+    let format = RecordFormat::from_name(&config.format);
+    let max_frames = (config.max_seconds.max(1) * fps) as usize;
+
+    let source = ScreenFrameSource::new(
+        screen,
+        area.x as i32,
+        area.y as i32,
+        area.width as u32,
+        area.height as u32,
+    );
+    let recorder = Rc::new(RefCell::new(Some(Recorder::new(
+        Box::new(source),
+        annotations,
+        (area.x, area.y),
+        fps,
+        max_frames,
+    ))));
+
+    let window = RecordingWindow::new()?;
+    window.window().set_position(crate::control_position(
+        &area,
+        (CONTROL_WIDTH, CONTROL_HEIGHT),
+        (
+            screen.display_info.width as f32,
+            screen.display_info.height as f32,
+        ),
+    ));
+
+    let timer = Rc::new(Timer::default());
+
+    // 停止录制并编码保存
+    let stop: Rc<dyn Fn()> = {
+        let recorder = recorder.clone();
+        let timer = timer.clone();
+        let window_weak = window.as_weak();
+        Rc::new(move || {
+            let Some(recorder) = recorder.borrow_mut().take() else {
+                return;
+            };
+            timer.stop();
+            if let Some(window) = window_weak.upgrade() {
+                window.set_encoding(true);
+                window.set_status_text("正在编码…".into());
+            }
+
+            // 编码比较耗时，放到后台线程，完成后回到界面线程通知结果
+            let recording = recorder.finish();
+            let path = output_path(&config, format);
+            let gif_colors = config.gif_colors;
+            let window_weak = window_weak.clone();
+            std::thread::spawn(move || {
+                let (ok, message) = match recording::encode(&recording, format, gif_colors, &path) {
+                    Ok(()) => (true, path.display().to_string()),
+                    Err(err) => (false, err.to_string()),
+                };
+                let _ = window_weak.upgrade_in_event_loop(move |window| {
+                    window.invoke_encoding_finished(ok, message.into());
+                });
+            });
+        })
+    };
+
+    let window_weak = window.as_weak();
+    window.on_encoding_finished(move |ok, message| {
+        if let Some(window) = window_weak.upgrade() {
+            let _ = window.hide();
+        }
+        on_finish(if ok {
+            Ok(PathBuf::from(message.as_str()))
+        } else {
+            Err(message.to_string())
+        });
+    });
+
+    // 等遮罩隐藏后再开始按帧率截取
+    let window_weak = window.as_weak();
+    let recorder_clone = recorder.clone();
+    let timer_clone = timer.clone();
+    let stop_clone = stop.clone();
+    Timer::single_shot(Duration::from_millis(START_DELAY_MS), move || {
+        let window_weak = window_weak.clone();
+        let recorder = recorder_clone.clone();
+        let stop = stop_clone.clone();
+        timer_clone.start(
+            TimerMode::Repeated,
+            Duration::from_millis(1000 / fps as u64),
+            move || {
+                let (frames, full) = {
+                    let mut recorder = recorder.borrow_mut();
+                    let Some(recorder) = recorder.as_mut() else {
+                        return;
+                    };
+                    recorder.capture_frame();
+                    (recorder.frame_count(), recorder.is_full())
+                };
+
+                if let Some(window) = window_weak.upgrade() {
+                    window.set_status_text(
+                        format!(
+                            "录制中 {:.1} 秒（{} 帧）",
+                            frames as f32 / fps as f32,
+                            frames
+                        )
+                        .into(),
+                    );
+                }
+                // 达到最长录制时间时自动停止
+                if full {
+                    stop();
+                }
+            },
+        );
+    });
+
+    let stop_clone = stop.clone();
+    window.on_stop_recording(move || stop_clone());
+
+    let window_weak = window.as_weak();
+    let timer_clone = timer.clone();
+    window.on_cancel_recording(move || {
+        if recorder.borrow_mut().take().is_none() {
+            return;
+        }
+        timer_clone.stop();
+        if let Some(window) = window_weak.upgrade() {
+            let _ = window.hide();
+        }
+        on_cancel();
+    });
+
+    window.show()?;
+    Ok(())
+}
+
+// 输出文件名使用录制结束时的时间戳
+fn output_path(config: &RecordingConfig, format: RecordFormat) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    config
+        .output_dir()
+        .join(format!("recording-{}.{}", timestamp, format.extension()))
+}
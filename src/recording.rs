@@ -0,0 +1,435 @@
+// 区域录制：从帧源逐帧截取选区，叠加标注后编码为 GIF、APNG 或 WebM
+
+use crate::annotation::Annotation;
+use crate::render;
+use screenshots::Screen;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// 帧来源。录制只依赖这个接口，测试时可以用预先准备好的帧代替真实截屏
+pub trait FrameSource {
+    // 每一帧的尺寸
+    fn size(&self) -> (u32, u32);
+    // 取下一帧 RGBA 数据，失败时返回 None
+    fn next_frame(&mut self) -> Option<Vec<u8>>;
+}
+
+// 通过截屏后端截取屏幕上的固定区域
+pub struct ScreenFrameSource {
+    screen: Screen,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl ScreenFrameSource {
+    pub fn new(screen: Screen, x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            screen,
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+impl FrameSource for ScreenFrameSource {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn next_frame(&mut self) -> Option<Vec<u8>> {
+        let image = self
+            .screen
+            .capture_area(self.x, self.y, self.width, self.height)
+            .ok()?;
+        let (image_width, image_height) = (image.width(), image.height());
+        fit_frame(
+            image.into_raw(),
+            (image_width, image_height),
+            (self.width, self.height),
+        )
+    }
+}
+
+// 高分屏上截到的是物理像素，图像比选区大，缩放到选区尺寸后才能和其它帧一起编码
+pub fn fit_frame(
+    data: Vec<u8>,
+    (width, height): (u32, u32),
+    (target_width, target_height): (u32, u32),
+) -> Option<Vec<u8>> {
+    if (width, height) == (target_width, target_height) {
+        return Some(data);
+    }
+    let image = image::RgbaImage::from_raw(width, height, data)?;
+    let scaled = image::imageops::resize(
+        &image,
+        target_width,
+        target_height,
+        image::imageops::FilterType::Triangle,
+    );
+    Some(scaled.into_raw())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordFormat {
+    Gif,
+    Apng,
+    WebM,
+}
+
+impl RecordFormat {
+    // 根据配置中的名称选择格式，无法识别时使用 GIF
+    pub fn from_name(name: &str) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "apng" | "png" => RecordFormat::Apng,
+            "webm" => RecordFormat::WebM,
+            _ => RecordFormat::Gif,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::Gif => "gif",
+            RecordFormat::Apng => "png",
+            RecordFormat::WebM => "webm",
+        }
+    }
+}
+
+// 录制好的帧序列
+pub struct Recording {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub frames: Vec<Vec<u8>>,
+}
+
+pub struct Recorder {
+    source: Box<dyn FrameSource>,
+    annotations: Vec<Annotation>,
+    origin_x: f32,
+    origin_y: f32,
+    fps: u32,
+    max_frames: usize,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Recorder {
+    // origin 是帧左上角对应的屏幕位置，用于把标注坐标换算到帧内
+    pub fn new(
+        source: Box<dyn FrameSource>,
+        annotations: Vec<Annotation>,
+        origin: (f32, f32),
+        fps: u32,
+        max_frames: usize,
+    ) -> Self {
+        Self {
+            source,
+            annotations,
+            origin_x: origin.0,
+            origin_y: origin.1,
+            fps: fps.max(1),
+            max_frames,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.frames.len() >= self.max_frames
+    }
+
+    // 截取一帧并叠加标注，返回是否成功
+    pub fn capture_frame(&mut self) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        let (width, height) = self.source.size();
+        let Some(mut frame) = self.source.next_frame() else {
+            return false;
+        };
+        if frame.len() != (width * height * 4) as usize {
+            return false;
+        }
+        render::draw_annotations(
+            &mut frame,
+            width,
+            height,
+            self.origin_x,
+            self.origin_y,
+            &self.annotations,
+        );
+        self.frames.push(frame);
+        true
+    }
+
+    pub fn finish(self) -> Recording {
+        let (width, height) = self.source.size();
+        Recording {
+            width,
+            height,
+            fps: self.fps,
+            frames: self.frames,
+        }
+    }
+}
+
+// 把录制结果编码写入文件
+pub fn encode(
+    recording: &Recording,
+    format: RecordFormat,
+    gif_colors: u32,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if recording.frames.is_empty() {
+        return Err("没有录制到任何帧".into());
+    }
+    match format {
+        RecordFormat::Gif => encode_gif(recording, gif_colors, path),
+        RecordFormat::Apng => encode_apng(recording, path),
+        RecordFormat::WebM => encode_webm(recording, path),
+    }
+}
+
+// GIF：每帧单独用 NeuQuant 量化出局部调色板
+fn encode_gif(recording: &Recording, colors: u32, path: &Path) -> Result<(), Box<dyn Error>> {
+    // 数值越小量化质量越高、速度越慢
+    const QUANTIZE_SAMPLE_FACTOR: i32 = 10;
+
+    let width = u16::try_from(recording.width)?;
+    let height = u16::try_from(recording.height)?;
+    let colors = colors.clamp(2, 256) as usize;
+    // GIF 的延迟单位是 1/100 秒
+    let delay = (100 / recording.fps).max(1) as u16;
+
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = gif::Encoder::new(file, width, height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+
+    for frame in &recording.frames {
+        let quantizer = color_quant::NeuQuant::new(QUANTIZE_SAMPLE_FACTOR, colors, frame);
+        let indices: Vec<u8> = frame
+            .chunks_exact(4)
+            .map(|pixel| quantizer.index_of(pixel) as u8)
+            .collect();
+
+        let gif_frame = gif::Frame {
+            width,
+            height,
+            delay,
+            palette: Some(quantizer.color_map_rgb()),
+            buffer: std::borrow::Cow::Owned(indices),
+            ..gif::Frame::default()
+        };
+        encoder.write_frame(&gif_frame)?;
+    }
+    Ok(())
+}
+
+// APNG：无损，体积较大
+fn encode_apng(recording: &Recording, path: &Path) -> Result<(), Box<dyn Error>> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, recording.width, recording.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(recording.frames.len() as u32, 0)?;
+    encoder.set_frame_delay(1, recording.fps as u16)?;
+
+    let mut writer = encoder.write_header()?;
+    for frame in &recording.frames {
+        writer.write_image_data(frame)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+// WebM：交给系统中的 ffmpeg 编码为 VP9
+fn encode_webm(recording: &Recording, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .arg("-s")
+        .arg(format!("{}x{}", recording.width, recording.height))
+        .arg("-r")
+        .arg(recording.fps.to_string())
+        .args(["-i", "-", "-c:v", "libvpx-vp9", "-pix_fmt", "yuv420p"])
+        // yuv420p 要求宽高为偶数
+        .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"])
+        .arg(path)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("无法启动 ffmpeg: {}", err))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        for frame in &recording.frames {
+            stdin.write_all(frame)?;
+        }
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(format!("ffmpeg 编码失败: {}", status).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 32;
+    const HEIGHT: u32 = 24;
+    const RED: [u8; 4] = [255, 0, 0, 255];
+
+    // 按顺序返回预先准备好的帧，用完后返回 None
+    struct FakeFrameSource {
+        frames: Vec<Vec<u8>>,
+    }
+
+    impl FakeFrameSource {
+        // count 帧纯色图像，每帧颜色不同
+        fn solid(count: u8) -> Self {
+            let frames = (0..count)
+                .map(|index| [index * 40, 200, 100, 255].repeat((WIDTH * HEIGHT) as usize))
+                .collect();
+            Self { frames }
+        }
+    }
+
+    impl FrameSource for FakeFrameSource {
+        fn size(&self) -> (u32, u32) {
+            (WIDTH, HEIGHT)
+        }
+
+        fn next_frame(&mut self) -> Option<Vec<u8>> {
+            (!self.frames.is_empty()).then(|| self.frames.remove(0))
+        }
+    }
+
+    fn pixel(frame: &[u8], x: u32, y: u32) -> [u8; 4] {
+        let index = ((y * WIDTH + x) * 4) as usize;
+        frame[index..index + 4].try_into().unwrap()
+    }
+
+    fn record(count: u8, max_frames: usize, annotations: Vec<Annotation>) -> Recording {
+        let mut recorder = Recorder::new(
+            Box::new(FakeFrameSource::solid(count)),
+            annotations,
+            (100.0, 50.0),
+            10,
+            max_frames,
+        );
+        while recorder.capture_frame() {}
+        recorder.finish()
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("recording-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn records_until_source_is_exhausted_or_full() {
+        assert_eq!(record(3, 10, Vec::new()).frames.len(), 3);
+
+        let recording = record(5, 2, Vec::new());
+        assert_eq!(recording.frames.len(), 2);
+        assert_eq!(
+            (recording.width, recording.height, recording.fps),
+            (WIDTH, HEIGHT, 10)
+        );
+    }
+
+    #[test]
+    fn annotations_are_drawn_relative_to_origin() {
+        // 标注使用屏幕坐标，帧左上角在 (100, 50)
+        let annotations = vec![Annotation::Rectangle {
+            x: 104.0,
+            y: 54.0,
+            width: 16.0,
+            height: 10.0,
+            color: RED,
+            stroke_width: 2.0,
+        }];
+        let recording = record(2, 10, annotations);
+        for frame in &recording.frames {
+            assert_eq!(pixel(frame, 4, 8), RED);
+            assert_ne!(pixel(frame, 12, 8), RED);
+            assert_ne!(pixel(frame, 1, 1), RED);
+        }
+    }
+
+    #[test]
+    fn encodes_gif_frames() {
+        let recording = record(3, 10, Vec::new());
+        let path = temp_path("frames.gif");
+        encode(&recording, RecordFormat::Gif, 64, &path).unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+        assert_eq!(
+            (decoder.width(), decoder.height()),
+            (WIDTH as u16, HEIGHT as u16)
+        );
+        let mut frames = 0;
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 10);
+            frames += 1;
+        }
+        assert_eq!(frames, 3);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn encodes_apng_frames() {
+        let recording = record(4, 10, Vec::new());
+        let path = temp_path("frames.png");
+        encode(&recording, RecordFormat::Apng, 256, &path).unwrap();
+
+        let decoder = png::Decoder::new(File::open(&path).unwrap());
+        let reader = decoder.read_info().unwrap();
+        let info = reader.info();
+        assert_eq!((info.width, info.height), (WIDTH, HEIGHT));
+        assert_eq!(
+            info.animation_control().map(|control| control.num_frames),
+            Some(4)
+        );
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn empty_recording_is_an_error() {
+        let recording = record(0, 10, Vec::new());
+        assert!(encode(&recording, RecordFormat::Gif, 256, &temp_path("empty.gif")).is_err());
+    }
+
+    #[test]
+    fn hidpi_frames_are_scaled_to_selection_size() {
+        let physical = [10, 20, 30, 255].repeat((WIDTH * 2 * HEIGHT * 2) as usize);
+        let frame = fit_frame(physical, (WIDTH * 2, HEIGHT * 2), (WIDTH, HEIGHT)).unwrap();
+        assert_eq!(frame.len(), (WIDTH * HEIGHT * 4) as usize);
+        assert_eq!(pixel(&frame, 5, 5), [10, 20, 30, 255]);
+
+        let same = vec![0; (WIDTH * HEIGHT * 4) as usize];
+        assert_eq!(
+            fit_frame(same.clone(), (WIDTH, HEIGHT), (WIDTH, HEIGHT)),
+            Some(same)
+        );
+        assert_eq!(fit_frame(vec![0; 3], (WIDTH, HEIGHT), (1, 1)), None);
+    }
+}
@@ -0,0 +1,230 @@
+// 敏感信息打码建议：在文字识别的结果中按正则查找邮箱、IP 地址、JWT、API 密钥和银行卡号，
+// 每处匹配给出一个打码区域，用户确认后才加入标注
+//
+// 同一行的词用空格拼接后再匹配，跨越多个词的匹配（例如分组书写的卡号）取这些词的范围；
+// 只匹配到词的一部分时按字符数的比例估算横向范围。
+
+use crate::config::RedactPattern;
+use crate::ocr::{Recognizer, Word};
+use std::ops::Range;
+
+// 打码区域向外扩展的距离，避免文字边缘露出
+const PADDING: f32 = 2.0;
+
+// 一处打码建议，坐标与传入的词相同
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub name: String, // 匹配的规则名称
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+// 识别图像中的文字并按规则查找需要打码的位置
+pub fn find(
+    recognizer: &dyn Recognizer,
+    data: &[u8],
+    width: u32,
+    height: u32,
+    patterns: &[RedactPattern],
+) -> Result<Vec<Suggestion>, String> {
+    let words = recognizer
+        .recognize(data, width, height)
+        .map_err(|err| err.to_string())?;
+    suggest(&words, patterns)
+}
+
+// 按规则查找需要打码的文字。规则的正则无效时返回错误
+pub fn suggest(words: &[Word], patterns: &[RedactPattern]) -> Result<Vec<Suggestion>, String> {
+    let regexes = patterns
+        .iter()
+        .map(|pattern| {
+            regex::Regex::new(&pattern.regex)
+                .map(|regex| (pattern, regex))
+                .map_err(|err| format!("打码规则 {} 无效: {}", pattern.name, err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut suggestions = Vec::new();
+    for line in words.chunk_by(|a, b| a.line == b.line) {
+        // 拼接这一行，记录每个词在文字中的范围
+        let mut text = String::new();
+        let mut spans = Vec::with_capacity(line.len());
+        for word in line {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            let start = text.len();
+            text.push_str(&word.text);
+            spans.push(start..text.len());
+        }
+
+        // 靠前的规则优先，已经被匹配的文字不再重复提示
+        let mut taken: Vec<Range<usize>> = Vec::new();
+        for (pattern, regex) in &regexes {
+            for found in regex.find_iter(&text) {
+                let range = found.range();
+                if range.is_empty()
+                    || taken
+                        .iter()
+                        .any(|other| other.start < range.end && range.start < other.end)
+                {
+                    continue;
+                }
+                if pattern.luhn && !luhn_valid(found.as_str()) {
+                    continue;
+                }
+                let Some((left, top, right, bottom)) = bounds(line, &spans, &text, &range) else {
+                    continue;
+                };
+                taken.push(range);
+                suggestions.push(Suggestion {
+                    name: pattern.name.clone(),
+                    text: found.as_str().to_string(),
+                    x: left - PADDING,
+                    y: top - PADDING,
+                    width: right - left + PADDING * 2.0,
+                    height: bottom - top + PADDING * 2.0,
+                });
+            }
+        }
+    }
+    Ok(suggestions)
+}
+
+// 匹配范围涉及的词的外接矩形
+fn bounds(
+    line: &[Word],
+    spans: &[Range<usize>],
+    text: &str,
+    range: &Range<usize>,
+) -> Option<(f32, f32, f32, f32)> {
+    let mut result: Option<(f32, f32, f32, f32)> = None;
+    for (word, span) in line.iter().zip(spans) {
+        let start = range.start.max(span.start);
+        let end = range.end.min(span.end);
+        if start >= end {
+            continue;
+        }
+        let chars = text[span.clone()].chars().count().max(1) as f32;
+        let before = text[span.start..start].chars().count() as f32;
+        let matched = text[start..end].chars().count() as f32;
+        let left = word.x + word.width * before / chars;
+        let right = word.x + word.width * (before + matched) / chars;
+        let (top, bottom) = (word.y, word.y + word.height);
+        result = Some(match result {
+            Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(right), b.max(bottom)),
+            None => (left, top, right, bottom),
+        });
+    }
+    result
+}
+
+// 卡号的 Luhn 校验，忽略空格和连字符
+fn luhn_valid(text: &str) -> bool {
+    let digits: Vec<u32> = text.chars().filter_map(|ch| ch.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, &digit)| {
+            if index % 2 == 1 {
+                let doubled = digit * 2;
+                if doubled > 9 { doubled - 9 } else { doubled }
+            } else {
+                digit
+            }
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error;
+
+    // 直接返回给定词的识别引擎
+    struct FakeRecognizer(Vec<Word>);
+
+    impl Recognizer for FakeRecognizer {
+        fn recognize(&self, _: &[u8], _: u32, _: u32) -> Result<Vec<Word>, Box<dyn Error>> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn word(text: &str, x: f32, line: u32) -> Word {
+        Word {
+            text: text.to_string(),
+            x,
+            y: line as f32 * 30.0,
+            width: text.chars().count() as f32 * 10.0,
+            height: 20.0,
+            line,
+        }
+    }
+
+    fn pattern(name: &str, regex: &str, luhn: bool) -> RedactPattern {
+        RedactPattern {
+            name: name.to_string(),
+            regex: regex.to_string(),
+            luhn,
+        }
+    }
+
+    #[test]
+    fn finds_matches_in_recognized_words() {
+        let recognizer = FakeRecognizer(vec![
+            word("mail:", 0.0, 0),
+            word("a@b.io", 60.0, 0),
+            word("card", 0.0, 1),
+            word("4111", 50.0, 1),
+            word("1111", 100.0, 1),
+            word("1111", 150.0, 1),
+            word("1111", 200.0, 1),
+        ]);
+        let patterns = [
+            pattern("邮箱", r"[\w.]+@[\w.]+", false),
+            pattern("卡号", r"\d{4}( \d{4}){3}", true),
+        ];
+        let found = find(&recognizer, &[], 0, 0, &patterns).unwrap();
+        assert_eq!(found.len(), 2);
+
+        assert_eq!(found[0].name, "邮箱");
+        assert_eq!(found[0].text, "a@b.io");
+        assert_eq!(
+            (found[0].x, found[0].y, found[0].width, found[0].height),
+            (58.0, -2.0, 64.0, 24.0)
+        );
+        // 跨越多个词的卡号取这些词的范围
+        assert_eq!(found[1].text, "4111 1111 1111 1111");
+        assert_eq!((found[1].x, found[1].width), (48.0, 194.0));
+    }
+
+    #[test]
+    fn partial_match_covers_part_of_word() {
+        let recognizer = FakeRecognizer(vec![word("key=abcd", 0.0, 0)]);
+        let found = find(&recognizer, &[], 0, 0, &[pattern("密钥", "abcd", false)]).unwrap();
+        // 8 个字符中的后 4 个
+        assert_eq!((found[0].x, found[0].width), (38.0, 44.0));
+    }
+
+    #[test]
+    fn skips_numbers_failing_luhn_check() {
+        let recognizer = FakeRecognizer(vec![word("4111111111111112", 0.0, 0)]);
+        let found = find(&recognizer, &[], 0, 0, &[pattern("卡号", r"\d{16}", true)]).unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn reports_invalid_pattern() {
+        let recognizer = FakeRecognizer(vec![word("text", 0.0, 0)]);
+        let err = find(&recognizer, &[], 0, 0, &[pattern("坏规则", "(", false)]).unwrap_err();
+        assert!(err.contains("坏规则"), "{}", err);
+    }
+}
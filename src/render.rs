@@ -0,0 +1,324 @@
+// 把标注光栅化到 RGBA 图像上
+//
+// 所有线条都按「到线段的距离」逐像素着色，边缘做一像素宽的抗锯齿。
+
+use crate::annotation::{self, Annotation};
+use crate::text;
+
+// 椭圆近似成多边形时的边数
+const ELLIPSE_SEGMENTS: usize = 96;
+
+struct Canvas<'a> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+}
+
+impl Canvas<'_> {
+    // 按覆盖率把颜色混合到像素上
+    fn blend(&mut self, x: u32, y: u32, color: [u8; 4], coverage: f32) {
+        let alpha = coverage * color[3] as f32 / 255.0;
+        if alpha <= 0.0 {
+            return;
+        }
+        let index = ((y * self.width + x) * 4) as usize;
+        let pixel = &mut self.data[index..index + 4];
+        for channel in 0..3 {
+            let dst = pixel[channel] as f32;
+            pixel[channel] = (dst + (color[channel] as f32 - dst) * alpha).round() as u8;
+        }
+        let dst_alpha = pixel[3] as f32 / 255.0;
+        pixel[3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+    }
+
+    fn stroke_segment(
+        &mut self,
+        from: (f32, f32),
+        to: (f32, f32),
+        stroke_width: f32,
+        color: [u8; 4],
+    ) {
+        let half = (stroke_width / 2.0).max(0.5);
+        let min_x = (from.0.min(to.0) - half - 1.0).floor().max(0.0) as u32;
+        let min_y = (from.1.min(to.1) - half - 1.0).floor().max(0.0) as u32;
+        let max_x = (from.0.max(to.0) + half + 1.0)
+            .ceil()
+            .min(self.width as f32) as u32;
+        let max_y = (from.1.max(to.1) + half + 1.0)
+            .ceil()
+            .min(self.height as f32) as u32;
+
+        let dx = to.0 - from.0;
+        let dy = to.1 - from.1;
+        let length_sq = dx * dx + dy * dy;
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+                let t = if length_sq > 0.0 {
+                    (((px - from.0) * dx + (py - from.1) * dy) / length_sq).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let cx = from.0 + t * dx;
+                let cy = from.1 + t * dy;
+                let distance = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+                let coverage = (half + 0.5 - distance).clamp(0.0, 1.0);
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+
+    // 打码：方块从区域左上角开始排列，每个方块填充其中像素的平均值。
+    // 区域向外取整，边缘不会留下原来的像素
+    fn pixelate(&mut self, (left, top): (f32, f32), (right, bottom): (f32, f32), block_size: f32) {
+        let min_x = left.floor().clamp(0.0, self.width as f32) as u32;
+        let min_y = top.floor().clamp(0.0, self.height as f32) as u32;
+        let max_x = right.ceil().clamp(0.0, self.width as f32) as u32;
+        let max_y = bottom.ceil().clamp(0.0, self.height as f32) as u32;
+        let block = block_size.round().max(1.0) as u32;
+
+        for block_y in (min_y..max_y).step_by(block as usize) {
+            for block_x in (min_x..max_x).step_by(block as usize) {
+                let end_x = (block_x + block).min(max_x);
+                let end_y = (block_y + block).min(max_y);
+                let mut sum = [0u32; 4];
+                for y in block_y..end_y {
+                    for x in block_x..end_x {
+                        let index = ((y * self.width + x) * 4) as usize;
+                        for (total, value) in sum.iter_mut().zip(&self.data[index..index + 4]) {
+                            *total += *value as u32;
+                        }
+                    }
+                }
+                let count = (end_x - block_x) * (end_y - block_y);
+                let average = sum.map(|value| (value / count) as u8);
+                for y in block_y..end_y {
+                    for x in block_x..end_x {
+                        let index = ((y * self.width + x) * 4) as usize;
+                        self.data[index..index + 4].copy_from_slice(&average);
+                    }
+                }
+            }
+        }
+    }
+
+    // 折线的相邻线段会在连接点重叠，先求每个像素的最大覆盖率再统一混合，避免接缝处颜色加深
+    fn stroke_polyline(
+        &mut self,
+        points: &[(f32, f32)],
+        closed: bool,
+        stroke_width: f32,
+        color: [u8; 4],
+    ) {
+        if points.is_empty() {
+            return;
+        }
+        if points.len() == 1 {
+            self.stroke_segment(points[0], points[0], stroke_width, color);
+            return;
+        }
+
+        let half = (stroke_width / 2.0).max(0.5);
+        let bounds = points.iter().fold(
+            (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+            |(x0, y0, x1, y1), &(x, y)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+        );
+        let min_x = (bounds.0 - half - 1.0).floor().max(0.0) as u32;
+        let min_y = (bounds.1 - half - 1.0).floor().max(0.0) as u32;
+        let max_x = (bounds.2 + half + 1.0).ceil().min(self.width as f32) as u32;
+        let max_y = (bounds.3 + half + 1.0).ceil().min(self.height as f32) as u32;
+        if min_x >= max_x || min_y >= max_y {
+            return;
+        }
+
+        let mask_width = (max_x - min_x) as usize;
+        let mut mask = vec![0.0f32; mask_width * (max_y - min_y) as usize];
+
+        let mut segments: Vec<((f32, f32), (f32, f32))> =
+            points.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        if closed {
+            segments.push((points[points.len() - 1], points[0]));
+        }
+
+        for (from, to) in segments {
+            let dx = to.0 - from.0;
+            let dy = to.1 - from.1;
+            let length_sq = dx * dx + dy * dy;
+            let x0 = (from.0.min(to.0) - half - 1.0).floor().max(min_x as f32) as u32;
+            let y0 = (from.1.min(to.1) - half - 1.0).floor().max(min_y as f32) as u32;
+            let x1 = (from.0.max(to.0) + half + 1.0).ceil().min(max_x as f32) as u32;
+            let y1 = (from.1.max(to.1) + half + 1.0).ceil().min(max_y as f32) as u32;
+
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    let px = x as f32 + 0.5;
+                    let py = y as f32 + 0.5;
+                    let t = if length_sq > 0.0 {
+                        (((px - from.0) * dx + (py - from.1) * dy) / length_sq).clamp(0.0, 1.0)
+                    } else {
+                        0.0
+                    };
+                    let distance =
+                        ((px - from.0 - t * dx).powi(2) + (py - from.1 - t * dy).powi(2)).sqrt();
+                    let coverage = (half + 0.5 - distance).clamp(0.0, 1.0);
+                    let cell = &mut mask[(y - min_y) as usize * mask_width + (x - min_x) as usize];
+                    *cell = cell.max(coverage);
+                }
+            }
+        }
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let coverage = mask[(y - min_y) as usize * mask_width + (x - min_x) as usize];
+                if coverage > 0.0 {
+                    self.blend(x, y, color, coverage);
+                }
+            }
+        }
+    }
+}
+
+// 把标注绘制到图像上。标注坐标是屏幕坐标，origin 是图像左上角对应的屏幕位置
+pub fn draw_annotations(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    origin_x: f32,
+    origin_y: f32,
+    annotations: &[Annotation],
+) {
+    if data.len() < (width * height * 4) as usize {
+        return;
+    }
+    let mut canvas = Canvas {
+        data,
+        width,
+        height,
+    };
+    let local = |x: f32, y: f32| (x - origin_x, y - origin_y);
+    let mut font = None;
+
+    for annotation in annotations {
+        match annotation {
+            Annotation::Rectangle {
+                x,
+                y,
+                width,
+                height,
+                color,
+                stroke_width,
+            } => {
+                // 边框画在矩形内侧，与界面上的 border 一致
+                let inset = stroke_width / 2.0;
+                let (left, top) = local(x + inset, y + inset);
+                let right = left + (width - stroke_width).max(0.0);
+                let bottom = top + (height - stroke_width).max(0.0);
+                canvas.stroke_polyline(
+                    &[(left, top), (right, top), (right, bottom), (left, bottom)],
+                    true,
+                    *stroke_width,
+                    *color,
+                );
+            }
+            Annotation::Ellipse {
+                x,
+                y,
+                width,
+                height,
+                color,
+                stroke_width,
+            } => {
+                let (center_x, center_y) = local(x + width / 2.0, y + height / 2.0);
+                let radius_x = ((width - stroke_width) / 2.0).max(0.0);
+                let radius_y = ((height - stroke_width) / 2.0).max(0.0);
+                let points: Vec<(f32, f32)> = (0..ELLIPSE_SEGMENTS)
+                    .map(|i| {
+                        let angle = i as f32 / ELLIPSE_SEGMENTS as f32 * std::f32::consts::TAU;
+                        (
+                            center_x + radius_x * angle.cos(),
+                            center_y + radius_y * angle.sin(),
+                        )
+                    })
+                    .collect();
+                canvas.stroke_polyline(&points, true, *stroke_width, *color);
+            }
+            Annotation::Arrow {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                color,
+                stroke_width,
+            } => {
+                let start = local(*start_x, *start_y);
+                let end = local(*end_x, *end_y);
+                canvas.stroke_segment(start, end, *stroke_width, *color);
+                for (head_x, head_y) in
+                    annotation::arrow_head_points(*start_x, *start_y, *end_x, *end_y)
+                {
+                    canvas.stroke_segment(end, local(head_x, head_y), *stroke_width, *color);
+                }
+            }
+            Annotation::Pen {
+                points,
+                color,
+                stroke_width,
+            } => {
+                let points: Vec<(f32, f32)> = points.iter().map(|&(x, y)| local(x, y)).collect();
+                canvas.stroke_polyline(&points, false, *stroke_width, *color);
+            }
+            Annotation::Pixelate {
+                x,
+                y,
+                width,
+                height,
+                block_size,
+            } => {
+                canvas.pixelate(local(*x, *y), local(x + width, y + height), *block_size);
+            }
+            Annotation::Text {
+                x,
+                y,
+                text,
+                color,
+                font_size,
+            } => {
+                // 只在有文字标注时加载字体
+                let Some(font) = font.get_or_insert_with(text::load_system_font) else {
+                    continue;
+                };
+                text::draw_text(
+                    canvas.data,
+                    (canvas.width, canvas.height),
+                    font,
+                    *font_size,
+                    local(*x, *y),
+                    text,
+                    *color,
+                );
+            }
+        }
+    }
+}
+
+// 只执行打码。SVG 和 PDF 中其余的标注是矢量图形，打码必须在嵌入截图之前完成，
+// 否则原来的像素仍然留在文件里
+pub fn apply_pixelate(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    origin_x: f32,
+    origin_y: f32,
+    annotations: &[Annotation],
+) {
+    let pixelate: Vec<Annotation> = annotations
+        .iter()
+        .filter(|annotation| matches!(annotation, Annotation::Pixelate { .. }))
+        .cloned()
+        .collect();
+    draw_annotations(data, width, height, origin_x, origin_y, &pixelate);
+}
@@ -0,0 +1,164 @@
+// 滚动截图：反复截取选区，拼接成长图
+
+use crate::platform;
+use crate::stitch::{StitchOutcome, Stitcher};
+use crate::{ScrollCaptureWindow, SelectionArea};
+use screenshots::Screen;
+use slint::{ComponentHandle, Timer, TimerMode};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
+
+// 两次截取之间的间隔
+const CAPTURE_INTERVAL_MS: u64 = 300;
+// 自动滚动时每次滚动的格数
+const AUTO_SCROLL_CLICKS: u32 = 3;
+// 自动滚动时连续多少次画面不变视为到达底部
+const AUTO_SCROLL_IDLE_LIMIT: u32 = 4;
+// 拼接结果的最大高度，防止占用过多内存
+const MAX_STITCH_HEIGHT: u32 = 32768;
+// 控制窗口尺寸
+const CONTROL_WIDTH: f32 = 280.0;
+const CONTROL_HEIGHT: f32 = 80.0;
+
+struct ScrollCaptureState {
+    stitcher: Stitcher,
+    auto_scroll: bool,
+    idle_ticks: u32,
+    finished: bool,
+}
+
+// 开始滚动截图。完成时把拼接结果交给 on_finish，取消时调用 on_cancel
+pub fn start_scroll_capture(
+    screen: Screen,
+    area: SelectionArea,
+    on_finish: impl Fn(Vec<u8>, u32, u32) + 'static,
+    on_cancel: impl Fn() + 'static,
+) -> Result<(), slint::PlatformError> {
+    let capture_x = area.x as i32;
+    let capture_y = area.y as i32;
+    let capture_width = area.width as u32;
+    let capture_height = area.height as u32;
+
+    let window = ScrollCaptureWindow::new()?;
+    window.window().set_position(crate::control_position(
+        &area,
+        (CONTROL_WIDTH, CONTROL_HEIGHT),
+        (
+            screen.display_info.width as f32,
+            screen.display_info.height as f32,
+        ),
+    ));
+
+    let state = Rc::new(RefCell::new(ScrollCaptureState {
+        stitcher: Stitcher::new(capture_width, capture_height),
+        auto_scroll: false,
+        idle_ticks: 0,
+        finished: false,
+    }));
+    let timer = Rc::new(Timer::default());
+
+    // 完成和取消共用的收尾逻辑
+    let finish: Rc<dyn Fn(bool)> = {
+        let state = state.clone();
+        let timer = timer.clone();
+        let window_weak = window.as_weak();
+        Rc::new(move |keep_result: bool| {
+            let stitcher = {
+                let mut state = state.borrow_mut();
+                if state.finished {
+                    return;
+                }
+                state.finished = true;
+                std::mem::replace(
+                    &mut state.stitcher,
+                    Stitcher::new(capture_width, capture_height),
+                )
+            };
+            timer.stop();
+            if let Some(window) = window_weak.upgrade() {
+                let _ = window.hide();
+            }
+
+            if keep_result && stitcher.frame_count() > 0 {
+                let (data, width, height) = stitcher.finish();
+                on_finish(data, width, height);
+            } else {
+                on_cancel();
+            }
+        })
+    };
+
+    let window_weak = window.as_weak();
+    let state_clone = state.clone();
+    let finish_clone = finish.clone();
+    timer.start(
+        TimerMode::Repeated,
+        Duration::from_millis(CAPTURE_INTERVAL_MS),
+        move || {
+            let Ok(image) =
+                screen.capture_area(capture_x, capture_y, capture_width, capture_height)
+            else {
+                return;
+            };
+            let frame = image.into_raw();
+
+            let (status, reached_end) = {
+                let mut state = state_clone.borrow_mut();
+                let outcome = state.stitcher.push_frame(&frame);
+                match outcome {
+                    StitchOutcome::Appended(_) => state.idle_ticks = 0,
+                    StitchOutcome::Unchanged => state.idle_ticks += 1,
+                    StitchOutcome::NoOverlap => {}
+                }
+
+                let mut status = format!(
+                    "已拼接 {} 帧，高度 {} px",
+                    state.stitcher.frame_count(),
+                    state.stitcher.height()
+                );
+                if outcome == StitchOutcome::NoOverlap {
+                    status.push_str("（未找到重叠，请慢一点滚动）");
+                }
+
+                let reached_end = state.stitcher.height() >= MAX_STITCH_HEIGHT
+                    || (state.auto_scroll && state.idle_ticks >= AUTO_SCROLL_IDLE_LIMIT);
+                if state.auto_scroll && !reached_end {
+                    platform::scroll_down_at(
+                        area.x + area.width / 2.0,
+                        area.y + area.height / 2.0,
+                        AUTO_SCROLL_CLICKS,
+                    );
+                }
+                (status, reached_end)
+            };
+
+            if let Some(window) = window_weak.upgrade() {
+                window.set_status_text(status.into());
+            }
+            if reached_end {
+                finish_clone(true);
+            }
+        },
+    );
+
+    let window_weak = window.as_weak();
+    let state_clone = state.clone();
+    window.on_toggle_auto_scroll(move || {
+        let mut state = state_clone.borrow_mut();
+        state.auto_scroll = !state.auto_scroll;
+        state.idle_ticks = 0;
+        if let Some(window) = window_weak.upgrade() {
+            window.set_auto_scroll(state.auto_scroll);
+        }
+    });
+
+    let finish_clone = finish.clone();
+    window.on_finish_capture(move || finish_clone(true));
+
+    let finish_clone = finish.clone();
+    window.on_cancel_capture(move || finish_clone(false));
+
+    window.show()?;
+    Ok(())
+}
@@ -0,0 +1,354 @@
+// 局域网分享：内置的 HTTP 服务器
+//
+// 第一次分享时在配置的地址上启动服务器，之后一直运行到进程退出（修改监听地址需要重启）。
+// 每张截图放在一个随机的短链接下，到期或超出数量上限后失效。非守护进程模式下，
+// 进程会保持运行到最后一个链接到期。
+
+use crate::config::ShareConfig;
+use crate::lifecycle;
+use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const TOKEN_LENGTH: usize = 8;
+const TOKEN_ALPHABET: &[u8] = b"23456789abcdefghijkmnpqrstuvwxyzABCDEFGHJKLMNPQRSTUVWXYZ";
+// 同时处理的连接数上限，超出时直接回复 503，避免局域网上的大量连接耗尽线程
+const MAX_CONNECTIONS: usize = 8;
+// 拒绝连接时读写请求的最长时间，期间不接受新连接
+const REJECT_TIMEOUT: Duration = Duration::from_millis(200);
+
+struct SharedImage {
+    token: String,
+    png: Arc<Vec<u8>>,
+    expires: Instant,
+}
+
+// 分享中的截图，由服务器线程读取
+static SHARED: Mutex<Vec<SharedImage>> = Mutex::new(Vec::new());
+// 服务器已经启动时为链接的前缀
+static BASE_URL: Mutex<Option<String>> = Mutex::new(None);
+// 正在处理的连接数（所有监听地址合计）
+static ACTIVE_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+// 分享一张 PNG，返回链接
+pub fn share(png: Vec<u8>, config: &ShareConfig) -> Result<String, Box<dyn Error>> {
+    if !config.enabled {
+        return Err("本地分享未启用，请在配置文件中设置 share.enabled".into());
+    }
+    let base_url = ensure_server(config)?;
+
+    let token = random_token();
+    let lifetime = Duration::from_secs(config.expire_minutes.max(1) * 60);
+    let mut shared = SHARED.lock().map_err(|_| "分享列表不可用")?;
+    store(
+        &mut shared,
+        SharedImage {
+            token: token.clone(),
+            png: Arc::new(png),
+            expires: Instant::now() + lifetime,
+        },
+        config.max_items,
+    );
+    drop(shared);
+
+    // 链接有效期内保持进程运行
+    lifecycle::capture_started();
+    slint::Timer::single_shot(lifetime, lifecycle::capture_ended);
+    Ok(format!("{}/{}", base_url, token))
+}
+
+// 加入一张截图，同时去掉过期的和超出数量上限的最旧的截图
+fn store(shared: &mut Vec<SharedImage>, image: SharedImage, max_items: usize) {
+    let now = Instant::now();
+    shared.retain(|image| image.expires > now);
+    shared.push(image);
+    let excess = shared.len().saturating_sub(max_items.max(1));
+    shared.drain(..excess);
+}
+
+fn ensure_server(config: &ShareConfig) -> Result<String, Box<dyn Error>> {
+    let mut base_url = BASE_URL.lock().map_err(|_| "分享服务器状态不可用")?;
+    if let Some(url) = base_url.as_ref() {
+        return Ok(url.clone());
+    }
+
+    let first = config.bind.first().ok_or("没有配置监听地址")?;
+    let mut listeners = Vec::with_capacity(config.bind.len());
+    for address in &config.bind {
+        let listener = TcpListener::bind((address.as_str(), config.port))
+            .map_err(|err| format!("无法监听 {}:{}: {}", address, config.port, err))?;
+        listeners.push(listener);
+    }
+    for listener in listeners {
+        println!("分享服务器已启动: http://{}", listener.local_addr()?);
+        std::thread::spawn(move || serve(listener, &ACTIVE_CONNECTIONS));
+    }
+
+    let host = config.public_host.as_ref().unwrap_or(first);
+    let host = if host.contains(':') {
+        format!("[{}]", host) // IPv6 地址
+    } else {
+        host.clone()
+    };
+    let url = format!("http://{}:{}", host, config.port);
+    *base_url = Some(url.clone());
+    Ok(url)
+}
+
+// active 是所有监听地址共用的连接计数
+fn serve(listener: TcpListener, active: &'static AtomicUsize) {
+    for stream in listener.incoming().flatten() {
+        if active.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            active.fetch_sub(1, Ordering::SeqCst);
+            reject(stream);
+            continue;
+        }
+        std::thread::spawn(move || {
+            if let Err(err) = handle(stream) {
+                println!("分享请求处理失败: {}", err);
+            }
+            active.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+// 连接数超出上限时回复 503。先短暂读取请求再关闭，否则未读的请求会让客户端收到连接重置
+// 而看不到回复
+fn reject(mut stream: TcpStream) {
+    let _ = stream.set_read_timeout(Some(REJECT_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(REJECT_TIMEOUT));
+    let _ = respond(
+        &mut stream,
+        "503 Service Unavailable",
+        "text/plain",
+        b"",
+        false,
+    );
+    let _ = stream.shutdown(Shutdown::Write);
+    let _ = std::io::copy(&mut (&stream).take(64 * 1024), &mut std::io::sink());
+}
+
+// 只支持 GET /<token>
+fn handle(mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    // 客户端一直不读取时也要释放连接名额
+    stream.set_write_timeout(Some(Duration::from_secs(30)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // 读完请求头，忽略其内容
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    if method != "GET" && method != "HEAD" {
+        return respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"",
+            false,
+        );
+    }
+    let token = path.trim_start_matches('/');
+    let png = SHARED.lock().ok().and_then(|shared| {
+        shared
+            .iter()
+            .find(|image| image.token == token && image.expires > Instant::now())
+            .map(|image| image.png.clone())
+    });
+    let head_only = method == "HEAD";
+    match png {
+        Some(png) => respond(&mut stream, "200 OK", "image/png", &png, head_only),
+        None => respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            "链接不存在或已过期".as_bytes(),
+            head_only,
+        ),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    head_only: bool,
+) -> Result<(), Box<dyn Error>> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    if !head_only {
+        stream.write_all(body)?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+// 随机的短链接，去掉了容易混淆的 0/O、1/l/I
+fn random_token() -> String {
+    // RandomState 使用进程启动时生成的随机密钥，再混入当前时间
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    hasher.write_u128(nanos);
+    let mut value = hasher.finish();
+    let base = TOKEN_ALPHABET.len() as u64;
+    (0..TOKEN_LENGTH)
+        .map(|_| {
+            let ch = TOKEN_ALPHABET[(value % base) as usize] as char;
+            value /= base;
+            ch
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    // 在随机端口上启动服务器，每个测试使用自己的连接计数
+    fn start_server(active: &'static AtomicUsize) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(listener, active));
+        address
+    }
+
+    // 发送请求，返回 (状态行, 响应体)
+    fn request(address: SocketAddr, method: &str, path: &str) -> (String, Vec<u8>) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: test\r\n\r\n", method, path).unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let header_end = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .unwrap();
+        let head = String::from_utf8_lossy(&response[..header_end]).into_owned();
+        let status = head.lines().next().unwrap().to_string();
+        (status, response[header_end + 4..].to_vec())
+    }
+
+    fn image(token: &str, png: &[u8], lifetime: Duration) -> SharedImage {
+        SharedImage {
+            token: token.to_string(),
+            png: Arc::new(png.to_vec()),
+            expires: Instant::now() + lifetime,
+        }
+    }
+
+    fn share_test_image(token: &str, png: &[u8], lifetime: Duration) {
+        store(
+            &mut SHARED.lock().unwrap(),
+            image(token, png, lifetime),
+            1000,
+        );
+    }
+
+    #[test]
+    fn valid_token_returns_image() {
+        static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+        let address = start_server(&ACTIVE);
+        share_test_image("validTok", b"\x89PNG data", Duration::from_secs(60));
+
+        let (status, body) = request(address, "GET", "/validTok");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, b"\x89PNG data");
+        let (status, body) = request(address, "HEAD", "/validTok");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn unknown_or_expired_token_is_rejected() {
+        static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+        let address = start_server(&ACTIVE);
+        share_test_image("expiredT", b"old", Duration::ZERO);
+        share_test_image("knownTok", b"png", Duration::from_secs(60));
+
+        for path in [
+            "/expiredT",
+            "/unknownT",
+            "/knownTo",
+            "/knownTokX",
+            "/",
+            "/knownTok/x",
+        ] {
+            let (status, body) = request(address, "GET", path);
+            assert_eq!(status, "HTTP/1.1 404 Not Found", "{}", path);
+            assert_ne!(body, b"png");
+        }
+        let (status, _) = request(address, "POST", "/knownTok");
+        assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+    }
+
+    #[test]
+    fn connections_over_limit_get_503() {
+        static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+        let address = start_server(&ACTIVE);
+        share_test_image("limitTok", b"png", Duration::from_secs(60));
+
+        // 占满连接名额：连上但不发送请求
+        let idle: Vec<TcpStream> = (0..MAX_CONNECTIONS)
+            .map(|_| TcpStream::connect(address).unwrap())
+            .collect();
+        let (status, _) = request(address, "GET", "/limitTok");
+        assert_eq!(status, "HTTP/1.1 503 Service Unavailable");
+
+        // 空闲连接关闭后名额释放
+        drop(idle);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while ACTIVE.load(Ordering::SeqCst) > 0 {
+            assert!(Instant::now() < deadline, "连接名额没有释放");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let (status, body) = request(address, "GET", "/limitTok");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, b"png");
+    }
+
+    #[test]
+    fn store_drops_expired_and_oldest_images() {
+        let mut shared = Vec::new();
+        store(&mut shared, image("expired", b"", Duration::ZERO), 2);
+        store(&mut shared, image("first", b"", Duration::from_secs(60)), 2);
+        assert_eq!(shared.len(), 1);
+        store(
+            &mut shared,
+            image("second", b"", Duration::from_secs(60)),
+            2,
+        );
+        store(&mut shared, image("third", b"", Duration::from_secs(60)), 2);
+        let tokens: Vec<&str> = shared.iter().map(|image| image.token.as_str()).collect();
+        assert_eq!(tokens, ["second", "third"]);
+    }
+
+    #[test]
+    fn tokens_use_unambiguous_alphabet() {
+        let first = random_token();
+        assert_eq!(first.len(), TOKEN_LENGTH);
+        assert!(first.bytes().all(|byte| TOKEN_ALPHABET.contains(&byte)));
+        for ch in ['0', 'O', '1', 'l', 'I'] {
+            assert!(!TOKEN_ALPHABET.contains(&(ch as u8)));
+        }
+        assert_ne!(first, random_token());
+    }
+}
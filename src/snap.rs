@@ -0,0 +1,172 @@
+// 边缘吸附
+//
+// 拖动选区或图形的控制柄时，把边缘吸附到截图中的强亮度边缘、
+// 其它图形的边缘 / 中心以及屏幕中线。
+
+use crate::detect;
+
+// 吸附生效的距离（像素）
+const SNAP_DISTANCE: f32 = 6.0;
+// 一列（行）像素的平均亮度差超过该值才视为强边缘
+const EDGE_STRENGTH_THRESHOLD: f32 = 28.0;
+
+pub struct Snapper {
+    luma: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl Snapper {
+    pub fn new(data: &[u8], width: u32, height: u32) -> Self {
+        let luma = detect::luminance(data, width, height);
+        Self {
+            luma,
+            width,
+            height,
+        }
+    }
+
+    fn luma_at(&self, x: u32, y: u32) -> u8 {
+        self.luma[(y * self.width + x) as usize]
+    }
+
+    // 第 col 列与左侧一列之间，在 y0..y1 范围内的平均亮度差
+    fn column_strength(&self, col: u32, y0: u32, y1: u32) -> f32 {
+        if col == 0 || col >= self.width || y1 <= y0 {
+            return 0.0;
+        }
+        let total: u32 = (y0..y1)
+            .map(|y| self.luma_at(col, y).abs_diff(self.luma_at(col - 1, y)) as u32)
+            .sum();
+        total as f32 / (y1 - y0) as f32
+    }
+
+    // 第 row 行与上方一行之间，在 x0..x1 范围内的平均亮度差
+    fn row_strength(&self, row: u32, x0: u32, x1: u32) -> f32 {
+        if row == 0 || row >= self.height || x1 <= x0 {
+            return 0.0;
+        }
+        let total: u32 = (x0..x1)
+            .map(|x| self.luma_at(x, row).abs_diff(self.luma_at(x, row - 1)) as u32)
+            .sum();
+        total as f32 / (x1 - x0) as f32
+    }
+
+    // 在 value 附近寻找最强的图像边缘
+    fn strongest_edge(&self, value: f32, limit: u32, strength: impl Fn(u32) -> f32) -> Option<f32> {
+        let from = (value - SNAP_DISTANCE).max(1.0) as u32;
+        let to = ((value + SNAP_DISTANCE).max(0.0) as u32).min(limit.saturating_sub(1));
+
+        let mut best: Option<(f32, f32)> = None;
+        for position in from..=to {
+            let score = strength(position);
+            if score < EDGE_STRENGTH_THRESHOLD {
+                continue;
+            }
+            let distance = (position as f32 - value).abs();
+            let better = match best {
+                Some((best_score, best_position)) => {
+                    score > best_score
+                        || (score == best_score && distance < (best_position - value).abs())
+                }
+                None => true,
+            };
+            if better {
+                best = Some((score, position as f32));
+            }
+        }
+        best.map(|(_, position)| position)
+    }
+
+    // 吸附竖直边缘：x 为边缘位置，y0..y1 为边缘覆盖的范围，targets 为参考线
+    pub fn snap_x(&self, x: f32, y0: f32, y1: f32, targets: &[f32]) -> Option<f32> {
+        let (y0, y1) = self.clamp_span(y0, y1, self.height);
+        let image_edge =
+            self.strongest_edge(x, self.width, |col| self.column_strength(col, y0, y1));
+        nearest(x, targets.iter().copied().chain(image_edge))
+    }
+
+    // 吸附水平边缘：y 为边缘位置，x0..x1 为边缘覆盖的范围，targets 为参考线
+    pub fn snap_y(&self, y: f32, x0: f32, x1: f32, targets: &[f32]) -> Option<f32> {
+        let (x0, x1) = self.clamp_span(x0, x1, self.width);
+        let image_edge = self.strongest_edge(y, self.height, |row| self.row_strength(row, x0, x1));
+        nearest(y, targets.iter().copied().chain(image_edge))
+    }
+
+    fn clamp_span(&self, a: f32, b: f32, limit: u32) -> (u32, u32) {
+        let from = a.min(b).max(0.0) as u32;
+        let to = (a.max(b).max(0.0) as u32).min(limit);
+        (from.min(to), to)
+    }
+}
+
+// 在吸附距离内选出离 value 最近的候选位置
+fn nearest(value: f32, candidates: impl Iterator<Item = f32>) -> Option<f32> {
+    candidates
+        .filter(|candidate| (candidate - value).abs() <= SNAP_DISTANCE)
+        .min_by(|a, b| (a - value).abs().total_cmp(&(b - value).abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 100;
+    const HEIGHT: u32 = 80;
+
+    // 左上角 (left, top) 以右下方为亮色 bright，其余为黑色的 RGBA 图像
+    fn image(left: u32, top: u32, bright: u8) -> Vec<u8> {
+        (0..WIDTH * HEIGHT)
+            .flat_map(|index| {
+                let (x, y) = (index % WIDTH, index / WIDTH);
+                let value = if x >= left && y >= top { bright } else { 0 };
+                [value, value, value, 255]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn snaps_to_image_edges_within_distance() {
+        let snapper = Snapper::new(&image(50, 40, 255), WIDTH, HEIGHT);
+        // 竖直边缘在第 50 列，只在 40 行以下存在
+        assert_eq!(snapper.snap_x(47.0, 40.0, 80.0, &[]), Some(50.0));
+        assert_eq!(snapper.snap_x(56.0, 80.0, 40.0, &[]), Some(50.0));
+        assert_eq!(snapper.snap_y(43.5, 50.0, 100.0, &[]), Some(40.0));
+    }
+
+    #[test]
+    fn no_snap_outside_distance() {
+        let snapper = Snapper::new(&image(50, 40, 255), WIDTH, HEIGHT);
+        assert_eq!(
+            snapper.snap_x(50.0 - SNAP_DISTANCE - 1.0, 40.0, 80.0, &[]),
+            None
+        );
+        assert_eq!(snapper.snap_y(30.0, 50.0, 100.0, &[]), None);
+        assert_eq!(snapper.snap_x(20.0, 0.0, 80.0, &[10.0, 30.0]), None);
+    }
+
+    #[test]
+    fn edges_outside_span_or_too_weak_are_ignored() {
+        let snapper = Snapper::new(&image(50, 40, 255), WIDTH, HEIGHT);
+        // 边缘只在 40 行以下，0..40 行之间没有可吸附的边缘
+        assert_eq!(snapper.snap_x(48.0, 0.0, 40.0, &[]), None);
+
+        let faint = Snapper::new(&image(50, 40, 20), WIDTH, HEIGHT);
+        assert_eq!(faint.snap_x(48.0, 40.0, 80.0, &[]), None);
+    }
+
+    #[test]
+    fn snaps_to_nearest_window_or_guide() {
+        let snapper = Snapper::new(&image(WIDTH, HEIGHT, 255), WIDTH, HEIGHT);
+        // 窗口边缘和屏幕中线作为参考线
+        assert_eq!(snapper.snap_x(33.0, 0.0, 80.0, &[30.0, 50.0]), Some(30.0));
+        assert_eq!(snapper.snap_y(37.0, 0.0, 100.0, &[32.0, 40.0]), Some(40.0));
+    }
+
+    #[test]
+    fn nearest_candidate_wins_over_stronger_edge() {
+        let snapper = Snapper::new(&image(50, 40, 255), WIDTH, HEIGHT);
+        assert_eq!(snapper.snap_x(47.0, 40.0, 80.0, &[46.0]), Some(46.0));
+        assert_eq!(snapper.snap_x(49.0, 40.0, 80.0, &[44.0]), Some(50.0));
+    }
+}
@@ -0,0 +1,339 @@
+// 滚动截图拼接
+//
+// 输入一系列同宽的 RGBA 帧，通过逐行哈希匹配找出相邻两帧之间的滚动距离，
+// 把新出现的内容追加到结果图像末尾。固定不动的顶部（标题栏）和底部（状态栏）
+// 只保留一份。
+
+// 参与匹配的有效行（非纯色行）最少数量
+const MIN_INFORMATIVE_ROWS: usize = 8;
+// 有效行匹配比例需要达到的阈值
+const MIN_MATCH_RATIO: f32 = 0.95;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StitchOutcome {
+    Appended(u32), // 追加了多少行新内容
+    Unchanged,     // 与上一帧相同（还没有滚动）
+    NoOverlap,     // 找不到重叠部分（滚动过快或内容变化）
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct RowHash {
+    hash: u64,
+    uniform: bool, // 整行只有一种颜色，这种行到处都能匹配上，不作为判断依据
+}
+
+// 计算每一行的哈希
+fn row_hashes(frame: &[u8], width: u32, height: u32) -> Vec<RowHash> {
+    let row_bytes = (width * 4) as usize;
+    (0..height as usize)
+        .map(|row| {
+            let pixels = &frame[row * row_bytes..(row + 1) * row_bytes];
+            // FNV-1a
+            let mut hash: u64 = 0xcbf29ce484222325;
+            for byte in pixels {
+                hash ^= *byte as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+            let first = &pixels[..4.min(pixels.len())];
+            let uniform = pixels.chunks_exact(4).all(|pixel| pixel == first);
+            RowHash { hash, uniform }
+        })
+        .collect()
+}
+
+// 两帧之间的滚动信息
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScrollMatch {
+    pub offset: u32, // 内容向上滚动的行数
+    pub header: u32, // 顶部固定行数
+    pub footer: u32, // 底部固定行数
+}
+
+// 找出 next 相对于 prev 向上滚动了多少行；两帧完全相同时 offset 为 0
+pub fn find_scroll_offset(
+    prev: &[u8],
+    next: &[u8],
+    width: u32,
+    height: u32,
+) -> Option<ScrollMatch> {
+    let frame_len = (width * height * 4) as usize;
+    if width == 0 || height == 0 || prev.len() < frame_len || next.len() < frame_len {
+        return None;
+    }
+
+    let prev_rows = row_hashes(prev, width, height);
+    let next_rows = row_hashes(next, width, height);
+    let rows = height as usize;
+
+    // 顶部和底部位置不变的行视为固定区域
+    let header = (0..rows)
+        .take_while(|&i| prev_rows[i] == next_rows[i])
+        .count();
+    if header == rows {
+        return Some(ScrollMatch {
+            offset: 0,
+            header: 0,
+            footer: 0,
+        });
+    }
+    let footer = (0..rows)
+        .rev()
+        .take_while(|&i| prev_rows[i] == next_rows[i])
+        .count();
+
+    let region_end = rows - footer;
+    let region_len = region_end - header;
+
+    let mut best: Option<(f32, usize)> = None;
+    for offset in 1..region_len {
+        let mut compared = 0usize;
+        let mut matched = 0usize;
+        for i in header..region_end - offset {
+            let next_row = next_rows[i];
+            let prev_row = prev_rows[i + offset];
+            if next_row.uniform && prev_row.uniform {
+                continue;
+            }
+            compared += 1;
+            if next_row.hash == prev_row.hash {
+                matched += 1;
+            }
+        }
+        if compared < MIN_INFORMATIVE_ROWS {
+            continue;
+        }
+        let ratio = matched as f32 / compared as f32;
+        if ratio < MIN_MATCH_RATIO {
+            continue;
+        }
+        if best.is_none_or(|(best_ratio, _)| ratio > best_ratio) {
+            best = Some((ratio, offset));
+        }
+    }
+
+    best.map(|(_, offset)| ScrollMatch {
+        offset: offset as u32,
+        header: header as u32,
+        footer: footer as u32,
+    })
+}
+
+pub struct Stitcher {
+    width: u32,
+    frame_height: u32,
+    data: Vec<u8>,
+    last_frame: Option<Vec<u8>>,
+    frame_count: u32,
+}
+
+impl Stitcher {
+    pub fn new(width: u32, frame_height: u32) -> Self {
+        Self {
+            width,
+            frame_height,
+            data: Vec::new(),
+            last_frame: None,
+            frame_count: 0,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        (self.data.len() / (self.width * 4).max(1) as usize) as u32
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    pub fn push_frame(&mut self, frame: &[u8]) -> StitchOutcome {
+        let row_bytes = (self.width * 4) as usize;
+        let frame_len = row_bytes * self.frame_height as usize;
+        if frame.len() < frame_len {
+            return StitchOutcome::NoOverlap;
+        }
+        let frame = &frame[..frame_len];
+
+        let Some(ref last_frame) = self.last_frame else {
+            // 第一帧直接作为起点
+            self.data.extend_from_slice(frame);
+            self.last_frame = Some(frame.to_vec());
+            self.frame_count = 1;
+            return StitchOutcome::Appended(self.frame_height);
+        };
+
+        let Some(scroll) = find_scroll_offset(last_frame, frame, self.width, self.frame_height)
+        else {
+            return StitchOutcome::NoOverlap;
+        };
+        if scroll.offset == 0 {
+            return StitchOutcome::Unchanged;
+        }
+
+        // 结果末尾总是上一帧的底部，先去掉其中的固定区域，追加新内容后再补回
+        let keep = self.data.len() - scroll.footer as usize * row_bytes;
+        self.data.truncate(keep);
+
+        let content_end = (self.frame_height - scroll.footer) as usize;
+        let content_start = content_end - scroll.offset as usize;
+        self.data
+            .extend_from_slice(&frame[content_start * row_bytes..content_end * row_bytes]);
+        self.data
+            .extend_from_slice(&frame[content_end * row_bytes..]);
+
+        self.last_frame = Some(frame.to_vec());
+        self.frame_count += 1;
+        StitchOutcome::Appended(scroll.offset)
+    }
+
+    // 取出拼接结果：(RGBA 数据, 宽, 高)
+    pub fn finish(self) -> (Vec<u8>, u32, u32) {
+        let height = self.height();
+        (self.data, self.width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: u32 = 24;
+    const HEIGHT: u32 = 120;
+
+    // 每一行内容都不同的长文档，第 row 行由行号决定
+    fn document_row(row: u32) -> Vec<u8> {
+        (0..WIDTH)
+            .flat_map(|x| {
+                [
+                    ((x * 5 + row * 3) % 256) as u8,
+                    (row % 256) as u8,
+                    (row / 256) as u8,
+                    255,
+                ]
+            })
+            .collect()
+    }
+
+    // 文档从第 scroll 行开始的一屏
+    fn frame_at(scroll: u32) -> Vec<u8> {
+        (scroll..scroll + HEIGHT).flat_map(document_row).collect()
+    }
+
+    // 带固定标题栏和状态栏的一屏
+    fn frame_with_chrome(scroll: u32, header: u32, footer: u32) -> Vec<u8> {
+        let mut frame = frame_at(scroll);
+        let row_bytes = (WIDTH * 4) as usize;
+        for row in (0..header).chain(HEIGHT - footer..HEIGHT) {
+            let start = row as usize * row_bytes;
+            frame[start..start + row_bytes].copy_from_slice(&document_row(10_000 + row));
+        }
+        frame
+    }
+
+    #[test]
+    fn finds_known_offset() {
+        for offset in [1, 37, 90] {
+            let scroll = find_scroll_offset(&frame_at(0), &frame_at(offset), WIDTH, HEIGHT);
+            assert_eq!(
+                scroll,
+                Some(ScrollMatch {
+                    offset,
+                    header: 0,
+                    footer: 0
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn identical_frames_have_zero_offset() {
+        let frame = frame_at(5);
+        let scroll = find_scroll_offset(&frame, &frame, WIDTH, HEIGHT).unwrap();
+        assert_eq!(scroll.offset, 0);
+    }
+
+    #[test]
+    fn detects_fixed_header_and_footer() {
+        let prev = frame_with_chrome(0, 10, 6);
+        let next = frame_with_chrome(25, 10, 6);
+        assert_eq!(
+            find_scroll_offset(&prev, &next, WIDTH, HEIGHT),
+            Some(ScrollMatch {
+                offset: 25,
+                header: 10,
+                footer: 6
+            })
+        );
+    }
+
+    #[test]
+    fn zero_overlap_is_not_matched() {
+        assert_eq!(
+            find_scroll_offset(&frame_at(0), &frame_at(HEIGHT), WIDTH, HEIGHT),
+            None
+        );
+    }
+
+    #[test]
+    fn unrelated_content_is_not_matched() {
+        let unrelated: Vec<u8> = (0..WIDTH * HEIGHT)
+            .flat_map(|index| [(index * 31 % 251) as u8, (index * 17 % 241) as u8, 7, 255])
+            .collect();
+        assert_eq!(
+            find_scroll_offset(&frame_at(0), &unrelated, WIDTH, HEIGHT),
+            None
+        );
+    }
+
+    #[test]
+    fn rejects_short_buffers() {
+        assert_eq!(find_scroll_offset(&[], &[], WIDTH, HEIGHT), None);
+    }
+
+    #[test]
+    fn stitches_frames_into_document() {
+        let mut stitcher = Stitcher::new(WIDTH, HEIGHT);
+        assert_eq!(
+            stitcher.push_frame(&frame_at(0)),
+            StitchOutcome::Appended(HEIGHT)
+        );
+        assert_eq!(stitcher.push_frame(&frame_at(0)), StitchOutcome::Unchanged);
+        assert_eq!(
+            stitcher.push_frame(&frame_at(30)),
+            StitchOutcome::Appended(30)
+        );
+        assert_eq!(
+            stitcher.push_frame(&frame_at(75)),
+            StitchOutcome::Appended(45)
+        );
+        // 滚动超过一屏，没有重叠部分
+        assert_eq!(
+            stitcher.push_frame(&frame_at(75 + HEIGHT + 10)),
+            StitchOutcome::NoOverlap
+        );
+        assert_eq!(stitcher.frame_count(), 3);
+
+        let (data, width, height) = stitcher.finish();
+        assert_eq!((width, height), (WIDTH, 75 + HEIGHT));
+        let expected: Vec<u8> = (0..75 + HEIGHT).flat_map(document_row).collect();
+        assert!(data == expected);
+    }
+
+    #[test]
+    fn stitching_keeps_one_copy_of_fixed_areas() {
+        let mut stitcher = Stitcher::new(WIDTH, HEIGHT);
+        stitcher.push_frame(&frame_with_chrome(0, 10, 6));
+        assert_eq!(
+            stitcher.push_frame(&frame_with_chrome(40, 10, 6)),
+            StitchOutcome::Appended(40)
+        );
+
+        let (data, _, height) = stitcher.finish();
+        assert_eq!(height, HEIGHT + 40);
+        // 状态栏只在末尾出现一次
+        let expected = frame_with_chrome(40, 10, 6);
+        let row_bytes = (WIDTH * 4) as usize;
+        let tail = &data[data.len() - 6 * row_bytes..];
+        assert!(tail == &expected[expected.len() - 6 * row_bytes..]);
+        assert!(data[..10 * row_bytes] == expected[..10 * row_bytes]);
+    }
+}
@@ -0,0 +1,184 @@
+// SVG 导出：截图以 base64 编码的 PNG 嵌入 <image>，标注输出为矢量元素（打码直接作用在截图上）
+//
+// 图形的位置和线宽与 render.rs 的光栅化结果保持一致：矩形和椭圆的边框画在内侧，
+// 箭头头部使用 annotation::arrow_head_points。
+
+use crate::annotation::{self, Annotation};
+use crate::{export, render};
+use base64::Engine;
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+// 保留两位小数，去掉多余的 0
+fn number(value: f32) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    format!("{}", rounded)
+}
+
+// 描边属性：颜色、不透明度和线宽
+fn stroke(color: [u8; 4], width: f32) -> String {
+    let mut attributes = format!(
+        r##"fill="none" stroke="#{:02x}{:02x}{:02x}" stroke-width="{}""##,
+        color[0],
+        color[1],
+        color[2],
+        number(width)
+    );
+    if color[3] < 255 {
+        attributes.push_str(&format!(
+            r#" stroke-opacity="{}""#,
+            number(color[3] as f32 / 255.0)
+        ));
+    }
+    attributes
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 生成 SVG 文档。标注坐标是屏幕坐标，origin 是截图左上角对应的屏幕位置
+pub fn render_svg(
+    data: &[u8],
+    (width, height): (u32, u32),
+    (origin_x, origin_y): (f32, f32),
+    annotations: &[Annotation],
+) -> Result<String, Box<dyn Error>> {
+    let mut data = data.to_vec();
+    render::apply_pixelate(&mut data, width, height, origin_x, origin_y, annotations);
+    let png = export::png_bytes(&data, width, height)?;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(png);
+    let local = |x: f32, y: f32| (number(x - origin_x), number(y - origin_y));
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )?;
+    writeln!(
+        svg,
+        r#"  <image x="0" y="0" width="{width}" height="{height}" href="data:image/png;base64,{encoded}"/>"#
+    )?;
+
+    for annotation in annotations {
+        match annotation {
+            Annotation::Rectangle {
+                x,
+                y,
+                width,
+                height,
+                color,
+                stroke_width,
+            } => {
+                let inset = stroke_width / 2.0;
+                let (left, top) = local(x + inset, y + inset);
+                writeln!(
+                    svg,
+                    r#"  <rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                    left,
+                    top,
+                    number((width - stroke_width).max(0.0)),
+                    number((height - stroke_width).max(0.0)),
+                    stroke(*color, *stroke_width)
+                )?;
+            }
+            Annotation::Ellipse {
+                x,
+                y,
+                width,
+                height,
+                color,
+                stroke_width,
+            } => {
+                let (center_x, center_y) = local(x + width / 2.0, y + height / 2.0);
+                writeln!(
+                    svg,
+                    r#"  <ellipse cx="{}" cy="{}" rx="{}" ry="{}" {}/>"#,
+                    center_x,
+                    center_y,
+                    number(((width - stroke_width) / 2.0).max(0.0)),
+                    number(((height - stroke_width) / 2.0).max(0.0)),
+                    stroke(*color, *stroke_width)
+                )?;
+            }
+            Annotation::Arrow {
+                start_x,
+                start_y,
+                end_x,
+                end_y,
+                color,
+                stroke_width,
+            } => {
+                let (sx, sy) = local(*start_x, *start_y);
+                let (ex, ey) = local(*end_x, *end_y);
+                let [(h1x, h1y), (h2x, h2y)] =
+                    annotation::arrow_head_points(*start_x, *start_y, *end_x, *end_y)
+                        .map(|(x, y)| local(x, y));
+                writeln!(
+                    svg,
+                    r#"  <path d="M {sx} {sy} L {ex} {ey} M {h1x} {h1y} L {ex} {ey} L {h2x} {h2y}" stroke-linecap="round" stroke-linejoin="round" {}/>"#,
+                    stroke(*color, *stroke_width)
+                )?;
+            }
+            Annotation::Pen {
+                points,
+                color,
+                stroke_width,
+            } => {
+                // 与 RustPenPath 生成的命令格式相同，只是换算成截图内的绝对坐标
+                let mut commands = String::new();
+                for (index, &(x, y)) in points.iter().enumerate() {
+                    let (px, py) = local(x, y);
+                    let command = if index == 0 { "M" } else { " L" };
+                    write!(commands, "{} {} {}", command, px, py)?;
+                }
+                writeln!(
+                    svg,
+                    r#"  <path d="{}" stroke-linecap="round" stroke-linejoin="round" {}/>"#,
+                    commands,
+                    stroke(*color, *stroke_width)
+                )?;
+            }
+            // 已经在嵌入的截图上完成
+            Annotation::Pixelate { .. } => {}
+            Annotation::Text {
+                x,
+                y,
+                text,
+                color,
+                font_size,
+            } => {
+                let (tx, ty) = local(*x, *y);
+                writeln!(
+                    svg,
+                    r##"  <text x="{}" y="{}" font-size="{}" dominant-baseline="text-before-edge" fill="#{:02x}{:02x}{:02x}" fill-opacity="{}">{}</text>"##,
+                    tx,
+                    ty,
+                    number(*font_size),
+                    color[0],
+                    color[1],
+                    color[2],
+                    number(color[3] as f32 / 255.0),
+                    escape(text)
+                )?;
+            }
+        }
+    }
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+pub fn save_svg(
+    path: &Path,
+    data: &[u8],
+    size: (u32, u32),
+    origin: (f32, f32),
+    annotations: &[Annotation],
+) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, render_svg(data, size, origin, annotations)?)?;
+    Ok(())
+}
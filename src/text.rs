@@ -0,0 +1,145 @@
+// 文字渲染：查找系统字体，用 ab_glyph 把文字画成 RGBA 图像
+
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont};
+use std::path::Path;
+
+// 按顺序尝试的系统字体，优先选择支持中文的字体
+const FONT_CANDIDATES: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/Helvetica.ttc",
+];
+
+// 加载第一个可用的系统字体
+pub fn load_system_font() -> Option<FontVec> {
+    FONT_CANDIDATES
+        .iter()
+        .map(Path::new)
+        .filter(|path| path.exists())
+        .find_map(|path| {
+            let data = std::fs::read(path).ok()?;
+            FontVec::try_from_vec(data).ok()
+        })
+}
+
+// 在图像的 position 处绘制一行文字，position 是文字左上角
+pub fn draw_text(
+    data: &mut [u8],
+    (width, height): (u32, u32),
+    font: &FontVec,
+    size: f32,
+    (x, y): (f32, f32),
+    text: &str,
+    color: [u8; 4],
+) {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let baseline = y + scaled.ascent();
+    let mut caret = x;
+    let mut previous = None;
+
+    for ch in text.chars() {
+        let glyph_id = scaled.glyph_id(ch);
+        if let Some(previous) = previous {
+            caret += scaled.kern(previous, glyph_id);
+        }
+        previous = Some(glyph_id);
+
+        let glyph = glyph_id.with_scale_and_position(size, ab_glyph::point(caret, baseline));
+        caret += scaled.h_advance(glyph_id);
+
+        let Some(outlined) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|glyph_x, glyph_y, coverage| {
+            let px = bounds.min.x as i32 + glyph_x as i32;
+            let py = bounds.min.y as i32 + glyph_y as i32;
+            if px < 0 || py < 0 || px >= width as i32 || py >= height as i32 {
+                return;
+            }
+            let alpha = coverage * color[3] as f32 / 255.0;
+            let index = ((py as u32 * width + px as u32) * 4) as usize;
+            let pixel = &mut data[index..index + 4];
+            for channel in 0..3 {
+                let dst = pixel[channel] as f32;
+                pixel[channel] = (dst + (color[channel] as f32 - dst) * alpha).round() as u8;
+            }
+            let dst_alpha = pixel[3] as f32 / 255.0;
+            pixel[3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+        });
+    }
+}
+
+// 一行文字的宽度
+pub fn text_width(font: &FontVec, size: f32, text: &str) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut width = 0.0;
+    let mut previous = None;
+    for ch in text.chars() {
+        let glyph_id = scaled.glyph_id(ch);
+        if let Some(previous) = previous {
+            width += scaled.kern(previous, glyph_id);
+        }
+        width += scaled.h_advance(glyph_id);
+        previous = Some(glyph_id);
+    }
+    width
+}
+
+// 行高
+pub fn line_height(font: &FontVec, size: f32) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    scaled.height() + scaled.line_gap()
+}
+
+// 把一段文字渲染成便签样式的图像：(RGBA 数据, 宽, 高)
+pub fn render_note(text: &str) -> Option<(Vec<u8>, u32, u32)> {
+    const FONT_SIZE: f32 = 16.0;
+    const PADDING: f32 = 12.0;
+    const MAX_LINES: usize = 60;
+    const MAX_WIDTH: f32 = 800.0;
+    const BACKGROUND: [u8; 4] = [0xff, 0xf8, 0xc5, 0xff];
+    const TEXT_COLOR: [u8; 4] = [0x33, 0x33, 0x33, 0xff];
+
+    let font = load_system_font()?;
+    let lines: Vec<&str> = text.lines().take(MAX_LINES).collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    let line_height = line_height(&font, FONT_SIZE);
+    let content_width = lines
+        .iter()
+        .map(|line| text_width(&font, FONT_SIZE, line))
+        .fold(0.0f32, f32::max)
+        .min(MAX_WIDTH);
+    let width = (content_width + PADDING * 2.0).ceil().max(1.0) as u32;
+    let height = (line_height * lines.len() as f32 + PADDING * 2.0).ceil() as u32;
+
+    let mut data: Vec<u8> = BACKGROUND
+        .iter()
+        .copied()
+        .cycle()
+        .take((width * height * 4) as usize)
+        .collect();
+    for (index, line) in lines.iter().enumerate() {
+        draw_text(
+            &mut data,
+            (width, height),
+            &font,
+            FONT_SIZE,
+            (PADDING, PADDING + line_height * index as f32),
+            line,
+            TEXT_COLOR,
+        );
+    }
+    Some((data, width, height))
+}
@@ -0,0 +1,323 @@
+// 上传截图到自建图床
+//
+// 请求体可以是 multipart/form-data，也可以直接是 PNG。上传成功后按 url_path 从 JSON 响应中
+// 取出链接（支持 $.a.b[0].c 这样的路径）；url_path 为空时整个响应体就是链接。网络错误和
+// 5xx 响应会按配置重试，4xx 说明请求本身有问题，不再重试。
+
+use crate::config::UploadConfig;
+use std::error::Error;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const FILE_NAME: &str = "screenshot.png";
+
+// 上传 PNG，返回图床给出的链接
+pub fn upload(config: &UploadConfig, png: &[u8]) -> Result<String, Box<dyn Error>> {
+    if config.url.is_empty() {
+        return Err("没有配置上传地址".into());
+    }
+    let (content_type, body) = match config.body.as_str() {
+        "raw" => ("image/png".to_string(), png.to_vec()),
+        "multipart" => multipart_body(config, png),
+        other => return Err(format!("不支持的请求体格式: {}", other).into()),
+    };
+
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(config.timeout_secs.max(1)))
+        .build();
+    let mut attempt = 0;
+    let response = loop {
+        let mut request = agent
+            .request(&config.method, &config.url)
+            .set("Content-Type", &content_type);
+        for (name, value) in &config.headers {
+            request = request.set(name, value);
+        }
+        if let Some(token) = &config.token {
+            request = request.set("Authorization", &format!("Bearer {}", token));
+        }
+
+        match request.send_bytes(&body) {
+            Ok(response) => break response.into_string()?,
+            Err(ureq::Error::Status(code, response)) if code < 500 || attempt >= config.retries => {
+                let text = response.into_string().unwrap_or_default();
+                return Err(format!("服务器返回 {}: {}", code, text.trim()).into());
+            }
+            Err(ureq::Error::Transport(err)) if attempt >= config.retries => {
+                return Err(err.to_string().into());
+            }
+            Err(err) => {
+                attempt += 1;
+                println!("上传失败（{}），第 {} 次重试", err, attempt);
+                std::thread::sleep(Duration::from_secs(attempt as u64));
+            }
+        }
+    };
+
+    if config.url_path.is_empty() {
+        let url = response.trim();
+        if url.is_empty() {
+            return Err("服务器返回了空的响应".into());
+        }
+        return Ok(url.to_string());
+    }
+    let value: serde_json::Value = serde_json::from_str(&response)
+        .map_err(|err| format!("响应不是 JSON（{}）: {}", err, response.trim()))?;
+    extract(&value, &config.url_path)
+        .ok_or_else(|| format!("响应中没有 {}: {}", config.url_path, response.trim()).into())
+}
+
+// 拼出 multipart/form-data 请求体：附加字段在前，文件在最后
+fn multipart_body(config: &UploadConfig, png: &[u8]) -> (String, Vec<u8>) {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    let boundary = format!("----screenshot-slint-{:x}", nanos);
+
+    let mut body = Vec::with_capacity(png.len() + 512);
+    for (name, value) in &config.form {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n\
+             Content-Type: image/png\r\n\r\n",
+            boundary, config.field, FILE_NAME
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(png);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+// 按 $.a.b[0].c 形式的路径取出 JSON 中的值；字符串和数字转换为文字
+fn extract(value: &serde_json::Value, path: &str) -> Option<String> {
+    let path = path.trim();
+    let path = path.strip_prefix('$').unwrap_or(path);
+    let mut current = value;
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        // 每一段是字段名后面跟零个或多个 [下标]
+        let (name, mut indexes) = match segment.find('[') {
+            Some(start) => segment.split_at(start),
+            None => (segment, ""),
+        };
+        if !name.is_empty() {
+            current = current.get(name)?;
+        }
+        while let Some(rest) = indexes.strip_prefix('[') {
+            let end = rest.find(']')?;
+            current = current.get(rest[..end].trim().parse::<usize>().ok()?)?;
+            indexes = &rest[end + 1..];
+        }
+    }
+    match current {
+        serde_json::Value::String(text) => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    // 模拟服务器收到的请求
+    struct Received {
+        head: String,
+        body: Vec<u8>,
+    }
+
+    impl Received {
+        fn header(&self, name: &str) -> Option<&str> {
+            self.head.lines().skip(1).find_map(|line| {
+                let (key, value) = line.split_once(':')?;
+                key.eq_ignore_ascii_case(name).then(|| value.trim())
+            })
+        }
+    }
+
+    // 在 127.0.0.1 的随机端口上依次处理请求，每个连接回复 responses 中的一项
+    fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, JoinHandle<Vec<Received>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/upload", listener.local_addr().unwrap());
+        let handle = std::thread::spawn(move || {
+            let mut received = Vec::new();
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line == "\r\n" || line.is_empty() {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                let mut request = Received {
+                    head,
+                    body: Vec::new(),
+                };
+                let length = request
+                    .header("Content-Length")
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(0);
+                request.body = vec![0; length];
+                reader.read_exact(&mut request.body).unwrap();
+
+                let mut stream = reader.into_inner();
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+                received.push(request);
+            }
+            received
+        });
+        (url, handle)
+    }
+
+    fn config(url: String) -> UploadConfig {
+        UploadConfig {
+            url,
+            retries: 0,
+            timeout_secs: 5,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn sends_multipart_body_and_headers() {
+        let (url, server) = mock_server(vec![(200, r#"{"data":{"url":"https://img/1.png"}}"#)]);
+        let mut config = config(url);
+        config.field = "image".to_string();
+        config.form.insert("album".to_string(), "work".to_string());
+        config
+            .headers
+            .insert("X-Api-Key".to_string(), "secret".to_string());
+        config.token = Some("abc".to_string());
+        config.url_path = "$.data.url".to_string();
+
+        let link = upload(&config, b"PNGDATA").unwrap();
+        assert_eq!(link, "https://img/1.png");
+
+        let received = server.join().unwrap();
+        let request = &received[0];
+        assert!(request.head.starts_with("POST /upload "));
+        assert_eq!(request.header("X-Api-Key"), Some("secret"));
+        assert_eq!(request.header("Authorization"), Some("Bearer abc"));
+
+        let content_type = request.header("Content-Type").unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let body = String::from_utf8_lossy(&request.body);
+        let expected = format!(
+            "--{b}\r\nContent-Disposition: form-data; name=\"album\"\r\n\r\nwork\r\n\
+             --{b}\r\nContent-Disposition: form-data; name=\"image\"; filename=\"screenshot.png\"\r\n\
+             Content-Type: image/png\r\n\r\nPNGDATA\r\n--{b}--\r\n",
+            b = boundary
+        );
+        assert_eq!(body, expected);
+    }
+
+    #[test]
+    fn raw_body_returns_whole_response() {
+        let (url, server) = mock_server(vec![(200, "  https://img/raw.png\n")]);
+        let mut config = config(url);
+        config.body = "raw".to_string();
+        config.method = "PUT".to_string();
+
+        assert_eq!(upload(&config, b"PNG").unwrap(), "https://img/raw.png");
+        let received = server.join().unwrap();
+        assert!(received[0].head.starts_with("PUT "));
+        assert_eq!(received[0].header("Content-Type"), Some("image/png"));
+        assert_eq!(received[0].body, b"PNG");
+    }
+
+    #[test]
+    fn retries_after_server_error() {
+        let (url, server) = mock_server(vec![(503, "busy"), (200, "https://img/2.png")]);
+        let mut config = config(url);
+        config.retries = 1;
+
+        assert_eq!(upload(&config, b"PNG").unwrap(), "https://img/2.png");
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn gives_up_after_last_retry() {
+        let (url, server) = mock_server(vec![(500, "oops"), (502, "still down")]);
+        let mut config = config(url);
+        config.retries = 1;
+
+        let err = upload(&config, b"PNG").unwrap_err().to_string();
+        assert!(err.contains("502"), "{}", err);
+        assert_eq!(server.join().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn does_not_retry_client_error() {
+        let (url, server) = mock_server(vec![(403, "forbidden")]);
+        let mut config = config(url);
+        config.retries = 3;
+
+        // 模拟服务器只回复一次，如果重试了会得到连接错误而不是 403
+        let err = upload(&config, b"PNG").unwrap_err().to_string();
+        assert!(err.contains("403") && err.contains("forbidden"), "{}", err);
+        assert_eq!(server.join().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn times_out_when_server_does_not_answer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/upload", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            // 接受连接后一直不回复
+            let (stream, _) = listener.accept().unwrap();
+            std::thread::sleep(Duration::from_secs(2));
+            drop(stream);
+        });
+        let mut config = config(url);
+        config.timeout_secs = 1;
+
+        let started = std::time::Instant::now();
+        assert!(upload(&config, b"PNG").is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn extracts_nested_values() {
+        let value: serde_json::Value = serde_json::from_str(
+            r#"{"data":{"url":"https://img/a.png","id":42},"a":[{"b":"first"},{"b":"second"}],"m":[[1,2],[3]]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            extract(&value, "$.data.url").as_deref(),
+            Some("https://img/a.png")
+        );
+        assert_eq!(extract(&value, "data.id").as_deref(), Some("42"));
+        assert_eq!(extract(&value, "$.a[0].b").as_deref(), Some("first"));
+        assert_eq!(extract(&value, "$.a[1].b").as_deref(), Some("second"));
+        assert_eq!(extract(&value, "$.m[0][1]").as_deref(), Some("2"));
+        assert_eq!(extract(&value, "$.a[2].b"), None);
+        assert_eq!(extract(&value, "$.data"), None);
+        assert_eq!(extract(&value, "$.a[x].b"), None);
+    }
+}
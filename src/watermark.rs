@@ -0,0 +1,289 @@
+// 水印和页脚：导出的截图需要注明来源时（例如附在审计工单上），叠加文字或图片水印，
+// 并在图像下方加一条记录截图时间、主机名和屏幕的页脚
+//
+// 水印先渲染成一张带透明度的小图，再按位置（或平铺）以配置的不透明度混合到截图上。
+
+use crate::annotation::parse_hex_color;
+use crate::config::{FooterConfig, WatermarkConfig};
+use crate::{history, text};
+use screenshots::Screen;
+
+// 页脚文字上下左右的留白
+const FOOTER_PADDING: f32 = 6.0;
+const FOOTER_SEPARATOR: &str = "  |  ";
+
+// 页脚记录的截图信息
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureInfo {
+    pub timestamp: u64,         // 截取屏幕的 Unix 时间（秒）
+    pub screen_id: Option<u32>, // 不知道来自哪个屏幕时（例如剪贴板中的图像）为 None
+}
+
+// 按配置叠加水印并加上页脚，返回 (RGBA 数据, 宽, 高)。所有导出截图的路径都经过这里
+pub fn decorate(
+    mut data: Vec<u8>,
+    width: u32,
+    height: u32,
+    watermark: &WatermarkConfig,
+    footer: &FooterConfig,
+    info: &CaptureInfo,
+) -> (Vec<u8>, u32, u32) {
+    apply_watermark(&mut data, width, height, watermark);
+    if !footer.enabled {
+        return (data, width, height);
+    }
+    add_footer(&data, width, height, footer, info)
+}
+
+// 按配置把水印叠加到 RGBA 图像上，没有配置水印时不做任何事
+pub fn apply_watermark(data: &mut [u8], width: u32, height: u32, config: &WatermarkConfig) {
+    let Some((mark, mark_width, mark_height)) = watermark_image(config) else {
+        return;
+    };
+    let opacity = config.opacity.clamp(0.0, 1.0);
+    let margin = config.margin.max(0.0).round() as i64;
+    let (width_i, height_i) = (width as i64, height as i64);
+    let (mark_width_i, mark_height_i) = (mark_width as i64, mark_height as i64);
+
+    if config.tile {
+        // 相邻两行错开半个水印，避免排成整齐的竖列
+        let step_x = mark_width_i + margin.max(1);
+        let step_y = mark_height_i + margin.max(1);
+        for (row, y) in (0..height_i).step_by(step_y as usize).enumerate() {
+            let shift = if row % 2 == 1 { step_x / 2 } else { 0 };
+            let mut x = -shift;
+            while x < width_i {
+                composite(
+                    data,
+                    (width, height),
+                    &mark,
+                    (mark_width, mark_height),
+                    (x, y),
+                    opacity,
+                );
+                x += step_x;
+            }
+        }
+        return;
+    }
+
+    let left = margin;
+    let right = width_i - mark_width_i - margin;
+    let top = margin;
+    let bottom = height_i - mark_height_i - margin;
+    let position = match config.position.as_str() {
+        "top-left" => (left, top),
+        "top-right" => (right, top),
+        "bottom-left" => (left, bottom),
+        "center" => ((width_i - mark_width_i) / 2, (height_i - mark_height_i) / 2),
+        "bottom-right" => (right, bottom),
+        other => {
+            println!("不支持的水印位置 {}，改用右下角", other);
+            (right, bottom)
+        }
+    };
+    composite(
+        data,
+        (width, height),
+        &mark,
+        (mark_width, mark_height),
+        position,
+        opacity,
+    );
+}
+
+// 水印图像：设置了图片时读取图片，否则把文字渲染成透明背景的图像
+fn watermark_image(config: &WatermarkConfig) -> Option<(Vec<u8>, u32, u32)> {
+    if let Some(path) = &config.image {
+        match image::open(path) {
+            Ok(image) => {
+                let image = image.into_rgba8();
+                let (width, height) = image.dimensions();
+                return Some((image.into_raw(), width, height));
+            }
+            Err(err) => println!("无法读取水印图片 {}: {}", path.display(), err),
+        }
+    }
+    if config.text.is_empty() {
+        return None;
+    }
+    let Some(font) = text::load_system_font() else {
+        println!("没有找到可用的字体，无法添加文字水印");
+        return None;
+    };
+    let size = config.font_size.max(1.0);
+    let width = text::text_width(&font, size, &config.text).ceil().max(1.0) as u32;
+    let height = text::line_height(&font, size).ceil().max(1.0) as u32;
+    // 底色用文字颜色、透明度为 0，抗锯齿的边缘不会混入黑色
+    let color = parse_hex_color(&config.color);
+    let mut data = [color[0], color[1], color[2], 0].repeat((width * height) as usize);
+    text::draw_text(
+        &mut data,
+        (width, height),
+        &font,
+        size,
+        (0.0, 0.0),
+        &config.text,
+        color,
+    );
+    Some((data, width, height))
+}
+
+// 把 source 以 opacity 混合到 data 的 (x, y) 处，超出图像的部分忽略
+fn composite(
+    data: &mut [u8],
+    (width, height): (u32, u32),
+    source: &[u8],
+    (source_width, source_height): (u32, u32),
+    (x, y): (i64, i64),
+    opacity: f32,
+) {
+    for source_y in 0..source_height as i64 {
+        let target_y = y + source_y;
+        if target_y < 0 || target_y >= height as i64 {
+            continue;
+        }
+        for source_x in 0..source_width as i64 {
+            let target_x = x + source_x;
+            if target_x < 0 || target_x >= width as i64 {
+                continue;
+            }
+            let src = ((source_y * source_width as i64 + source_x) * 4) as usize;
+            let alpha = source[src + 3] as f32 / 255.0 * opacity;
+            if alpha <= 0.0 {
+                continue;
+            }
+            let dst = ((target_y * width as i64 + target_x) * 4) as usize;
+            let pixel = &mut data[dst..dst + 4];
+            for channel in 0..3 {
+                let value = pixel[channel] as f32;
+                pixel[channel] =
+                    (value + (source[src + channel] as f32 - value) * alpha).round() as u8;
+            }
+            let dst_alpha = pixel[3] as f32 / 255.0;
+            pixel[3] = ((alpha + dst_alpha * (1.0 - alpha)) * 255.0).round() as u8;
+        }
+    }
+}
+
+// 在图像下方加上页脚，返回 (RGBA 数据, 宽, 高)。找不到字体时返回原图
+pub fn add_footer(
+    data: &[u8],
+    width: u32,
+    height: u32,
+    config: &FooterConfig,
+    info: &CaptureInfo,
+) -> (Vec<u8>, u32, u32) {
+    let footer = footer_text(config, info);
+    let font = (!footer.is_empty()).then(text::load_system_font).flatten();
+    let Some(font) = font else {
+        if !footer.is_empty() {
+            println!("没有找到可用的字体，无法添加页脚");
+        }
+        return (data.to_vec(), width, height);
+    };
+
+    let size = config.font_size.max(1.0);
+    let footer_height = (text::line_height(&font, size) + FOOTER_PADDING * 2.0).ceil() as u32;
+    let mut bar = parse_hex_color(&config.background).repeat((width * footer_height) as usize);
+    text::draw_text(
+        &mut bar,
+        (width, footer_height),
+        &font,
+        size,
+        (FOOTER_PADDING, FOOTER_PADDING),
+        &footer,
+        parse_hex_color(&config.color),
+    );
+
+    let mut result = Vec::with_capacity(data.len() + bar.len());
+    result.extend_from_slice(data);
+    result.extend_from_slice(&bar);
+    (result, width, height + footer_height)
+}
+
+fn footer_text(config: &FooterConfig, info: &CaptureInfo) -> String {
+    let mut parts = Vec::new();
+    if config.timestamp {
+        parts.push(history::format_timestamp_with_zone(info.timestamp));
+    }
+    if config.hostname {
+        parts.push(hostname().unwrap_or_else(|| "未知主机".to_string()));
+    }
+    if config.screen
+        && let Some(screen_id) = info.screen_id
+    {
+        parts.push(match screen_index(screen_id) {
+            Some(index) => format!("屏幕 {}", index + 1),
+            None => format!("屏幕 ID {}", screen_id),
+        });
+    }
+    parts.join(FOOTER_SEPARATOR)
+}
+
+// 主机名：依次尝试环境变量、/etc/hostname 和 hostname 命令
+fn hostname() -> Option<String> {
+    let name = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .or_else(|| {
+            let output = std::process::Command::new("hostname").output().ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+        })?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// 屏幕在系统屏幕列表中的序号
+fn screen_index(screen_id: u32) -> Option<usize> {
+    Screen::all()
+        .ok()?
+        .iter()
+        .position(|screen| screen.display_info.id == screen_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decorate_without_config_keeps_image() {
+        let data = vec![10u8; 4 * 3 * 2];
+        let info = CaptureInfo {
+            timestamp: 0,
+            screen_id: None,
+        };
+        let result = decorate(
+            data.clone(),
+            3,
+            2,
+            &WatermarkConfig::default(),
+            &FooterConfig::default(),
+            &info,
+        );
+        assert_eq!(result, (data, 3, 2));
+    }
+
+    #[test]
+    fn footer_uses_capture_time_and_skips_unknown_screen() {
+        let config = FooterConfig {
+            hostname: false,
+            ..Default::default()
+        };
+        let timestamp = 1_700_000_082;
+        let text = footer_text(
+            &config,
+            &CaptureInfo {
+                timestamp,
+                screen_id: None,
+            },
+        );
+        assert_eq!(text, history::format_timestamp_with_zone(timestamp));
+        assert!(text.starts_with(&history::format_timestamp(timestamp)));
+        assert!(text.contains(":42 "));
+    }
+}